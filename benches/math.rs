@@ -0,0 +1,161 @@
+//! The SIMD kernels against the scalar ones on the same storage, and the scalar kernels against
+//! the math types before SIMD. Run with `cargo bench`; build with `--no-default-features` to see
+//! the types themselves fall back to scalar code.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use triangle::matrix::Matrix4;
+use triangle::quaternion::Quaternion;
+use triangle::simd::{self, scalar, Rows};
+use triangle::vector::{Vector3, Vector4};
+
+/// The matrix products as the math types did them before the SIMD kernels: sixteen named fields
+/// reached through a match per element.
+mod before {
+    use std::ops::Index;
+
+    #[derive(Copy, Clone)]
+    pub struct Mat4 {
+        m00: f32, m10: f32, m20: f32, m30: f32,
+        m01: f32, m11: f32, m21: f32, m31: f32,
+        m02: f32, m12: f32, m22: f32, m32: f32,
+        m03: f32, m13: f32, m23: f32, m33: f32,
+    }
+
+    impl Index<(usize, usize)> for Mat4 {
+        type Output = f32;
+        fn index(&self, (r, c): (usize, usize)) -> &f32 {
+            match (r, c) {
+                (0, 0) => &self.m00, (1, 0) => &self.m10, (2, 0) => &self.m20, (3, 0) => &self.m30,
+                (0, 1) => &self.m01, (1, 1) => &self.m11, (2, 1) => &self.m21, (3, 1) => &self.m31,
+                (0, 2) => &self.m02, (1, 2) => &self.m12, (2, 2) => &self.m22, (3, 2) => &self.m32,
+                (0, 3) => &self.m03, (1, 3) => &self.m13, (2, 3) => &self.m23, (3, 3) => &self.m33,
+                _ => panic!("Index out of bounds"),
+            }
+        }
+    }
+
+    impl Mat4 {
+        pub fn from_rows(r: &[[f32; 4]; 4]) -> Self {
+            Self {
+                m00: r[0][0], m10: r[1][0], m20: r[2][0], m30: r[3][0],
+                m01: r[0][1], m11: r[1][1], m21: r[2][1], m31: r[3][1],
+                m02: r[0][2], m12: r[1][2], m22: r[2][2], m32: r[3][2],
+                m03: r[0][3], m13: r[1][3], m23: r[2][3], m33: r[3][3],
+            }
+        }
+
+        pub fn mul(self, rhs: Self) -> Self {
+            let mut result = [[0.; 4]; 4];
+            for (r, row) in result.iter_mut().enumerate() {
+                for (c, value) in row.iter_mut().enumerate() {
+                    for k in 0..4 {
+                        *value += self[(r, k)] * rhs[(k, c)];
+                    }
+                }
+            }
+            Self::from_rows(&result)
+        }
+    }
+
+    pub fn vec4_mul_mat4(v: [f32; 4], m: &Mat4) -> [f32; 4] {
+        let column = |c: usize| v[0] * m[(0, c)] + v[1] * m[(1, c)] + v[2] * m[(2, c)] + v[3] * m[(3, c)];
+        [column(0), column(1), column(2), column(3)]
+    }
+}
+
+const ITERATIONS: u32 = 2_000_000;
+const ROUNDS: u32 = 9;
+
+/// Best of a few rounds, which filters out most scheduling noise; prints the time per call.
+fn bench(name: &str, mut f: impl FnMut()) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        best = best.min(time(&mut f));
+    }
+    report(name, best);
+    best
+}
+
+fn time(f: &mut impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed()
+}
+
+fn report(name: &str, time: Duration) {
+    println!("{:<32} {:>8.2} ns", name, time.as_secs_f64() * 1e9 / ITERATIONS as f64);
+}
+
+/// Like [`bench`] for two versions of the same product, taking turns so both see the same load.
+/// Prints how much faster the SIMD version is.
+fn race(what: &str, mut scalar: impl FnMut(), mut simd: impl FnMut()) -> Duration {
+    let (mut best_scalar, mut best_simd) = (Duration::MAX, Duration::MAX);
+    for _ in 0..ROUNDS {
+        best_scalar = best_scalar.min(time(&mut scalar));
+        best_simd = best_simd.min(time(&mut simd));
+    }
+    report(&format!("{}, scalar", what), best_scalar);
+    report(&format!("{}, simd", what), best_simd);
+    compare(&format!("{} simd over scalar", what), best_scalar, best_simd);
+    best_scalar
+}
+
+fn compare(what: &str, slow: Duration, fast: Duration) {
+    println!("{:<32} {:>8.2}x", what, slow.as_secs_f64() / fast.as_secs_f64());
+}
+
+fn rows(seed: f32) -> Rows<f32> {
+    let mut rows = [[0.; 4]; 4];
+    for (r, row) in rows.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = seed + (r * 4 + c) as f32 * 0.25;
+        }
+    }
+    rows
+}
+
+fn main() {
+    let (a, b) = (rows(1.), rows(-2.));
+    let s = race("mat4 * mat4",
+        || { black_box(scalar::mat4_mul(black_box(&a), black_box(&b))); },
+        || { black_box(simd::mat4_mul(black_box(&a), black_box(&b))); },
+    );
+    let (old_a, old_b) = (before::Mat4::from_rows(&a), before::Mat4::from_rows(&b));
+    let o = bench("mat4 * mat4, before simd", || { black_box(black_box(old_a).mul(black_box(old_b))); });
+    compare("mat4 * mat4 rows over fields", o, s);
+    println!();
+
+    let p = [1., 2., 3., 1.];
+    let s = race("vec4 * mat4",
+        || { black_box(scalar::vec4_mul_mat4(black_box(p), black_box(&a))); },
+        || { black_box(simd::vec4_mul_mat4(black_box(p), black_box(&a))); },
+    );
+    let o = bench("vec4 * mat4, before simd", || { black_box(before::vec4_mul_mat4(black_box(p), black_box(&old_a))); });
+    compare("vec4 * mat4 rows over fields", o, s);
+    println!();
+
+    let (q, r) = ([0.9, 0.1, 0.3, -0.2], [0.5, -0.5, 0.5, 0.5]);
+    race("quat * quat",
+        || { black_box(scalar::quat_mul(black_box(q), black_box(r))); },
+        || { black_box(simd::quat_mul(black_box(q), black_box(r))); },
+    );
+    println!();
+
+    // what the renderer does per model: compose a transform and upload it
+    let rotation = Quaternion::from_angle_axis(0.7, Vector3::new(1., 2., 3.));
+    let view = Matrix4::translate(Vector3::new(0., 0., -5.));
+    let projection = Matrix4::project(1.2, 1.5, 0.1, 100.);
+    let model = Matrix4::scale(Vector3::new(2., 2., 2.)) * rotation.as_mat4() * Matrix4::translate(Vector3::new(1., 2., 3.));
+    bench("model * view * projection", || {
+        black_box(black_box(model) * black_box(view) * black_box(projection));
+    });
+    bench("point through model", || {
+        black_box(Vector4::new(1., 2., 3., 1.) * black_box(model));
+    });
+    let s = bench("Matrix4::as_vec", || { black_box(black_box(model).as_vec()); });
+    let v = bench("Matrix4::to_cols_array", || { black_box(black_box(model).to_cols_array()); });
+    compare("upload without allocating", s, v);
+}
@@ -1,5 +1,5 @@
-use rand::random;
-use std::{fmt};
+use std::fmt;
+use crate::vector::Vector3;
 
 #[derive(Copy, Clone)]
 pub struct Vertex {
@@ -22,9 +22,32 @@ impl Vertex {
         }
     }
 
+    pub fn get_position(&self) -> Vector3<f32> {
+        Vector3::new(self.pos[0], self.pos[1], self.pos[2])
+    }
+
+    pub fn get_normal(&self) -> Vector3<f32> {
+        Vector3::new(self.normal[0], self.normal[1], self.normal[2])
+    }
+
+    pub fn get_uv(&self) -> [f32; 2] {
+        self.uv
+    }
+
+    pub fn get_color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    pub fn set_position(&mut self, pos: Vector3<f32>) {
+        self.pos = [pos.x, pos.y, pos.z];
+    }
+
+    pub fn set_normal(&mut self, normal: Vector3<f32>) {
+        self.normal = [normal.x, normal.y, normal.z];
+    }
+
     pub fn as_vec(&self) -> Vec<f32> {
-        let a = [self.pos.to_vec(),self.normal.to_vec(), self.uv.to_vec(), self.color.to_vec()].concat();
-        a
+        [self.pos.to_vec(),self.normal.to_vec(), self.uv.to_vec(), self.color.to_vec()].concat()
     }
 }
 
@@ -62,6 +85,18 @@ impl VertexBuffer {
         verts
     }
 
+    pub fn get_vertex(&self, index: usize) -> Vertex {
+        self.vertices[index]
+    }
+
+    pub fn set_vertex(&mut self, index: usize, vertex: Vertex) {
+        self.vertices[index] = vertex;
+    }
+
+    pub fn get_vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
     pub const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
         array_stride: size_of::<Vertex>() as wgpu::BufferAddress,
         step_mode: wgpu::VertexStepMode::Vertex,
@@ -74,6 +109,12 @@ impl VertexBuffer {
     };
 }
 
+impl Default for VertexBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct IndexBuffer{
     indices: Vec<u32>,
 }
@@ -102,4 +143,18 @@ impl IndexBuffer {
     pub fn get_indices(&self) -> Vec<u32> {
        self.indices.clone()
     }
-}
\ No newline at end of file
+
+    pub fn get_index(&self, index: usize) -> u32 {
+        self.indices[index]
+    }
+
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.indices.swap(a, b);
+    }
+}
+
+impl Default for IndexBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
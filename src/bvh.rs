@@ -0,0 +1,391 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use crate::geometry::{Aabb, Ray, Triangle, TriangleHit};
+use crate::mesh::Mesh;
+use crate::vector::Vector3;
+
+/// Centroid bins per axis when looking for the cheapest split.
+const BINS: usize = 12;
+/// Cost of visiting a node, relative to intersecting one triangle.
+const TRAVERSAL_COST: f32 = 1.;
+/// Leaves this small are never split.
+const MIN_LEAF_SIZE: usize = 2;
+/// Leaves this large are split even when the heuristic says it does not pay off.
+const MAX_LEAF_SIZE: usize = 16;
+
+const MAGIC: [u8; 4] = *b"TBVH";
+const VERSION: u32 = 2;
+
+/// Bounding volume hierarchy over the triangles of a [`Mesh`], in the mesh's own space.
+#[derive(Default)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    /// Copies of the triangles in leaf order, so queries do not go through the index buffer.
+    triangles: Vec<Triangle>,
+    /// Index of each entry of `triangles` in the mesh.
+    ids: Vec<u32>,
+}
+
+/// A leaf when `count` > 0, holding `triangles[first..first + count]`; otherwise an inner node
+/// whose children are `nodes[first]` and `nodes[first + 1]`. Children always come after their parent.
+#[derive(Copy, Clone)]
+struct Node {
+    bounds: Aabb,
+    first: u32,
+    count: u32,
+}
+
+/// Splits a node between the centroid bins up to and including `bin` and the rest.
+struct Split {
+    axis: usize,
+    min: f32,
+    scale: f32,
+    bin: usize,
+}
+
+impl Split {
+    fn bin(axis: usize, min: f32, scale: f32, centroid: Vector3<f32>) -> usize {
+        (((centroid[axis] - min) * scale) as usize).min(BINS - 1)
+    }
+
+    fn goes_left(&self, centroid: Vector3<f32>) -> bool {
+        Self::bin(self.axis, self.min, self.scale, centroid) <= self.bin
+    }
+}
+
+impl Bvh {
+    /// Builds the hierarchy top down, splitting where the surface area heuristic is cheapest.
+    pub fn build(mesh: &Mesh) -> Self {
+        let count = mesh.get_triangle_count();
+        if count == 0 {
+            return Self::default();
+        }
+        let triangles: Vec<Triangle> = (0..count).map(|t| mesh.get_triangle(t)).collect();
+        let bounds: Vec<Aabb> = triangles.iter().map(Triangle::bounds).collect();
+        let centroids: Vec<Vector3<f32>> = bounds.iter().map(Aabb::center).collect();
+
+        let mut ids: Vec<u32> = (0..count as u32).collect();
+        let mut nodes = Vec::with_capacity(2 * count / MIN_LEAF_SIZE);
+        nodes.push(Node { bounds: Aabb::default(), first: 0, count: count as u32 });
+        // an explicit stack, as degenerate meshes can make the tree deep
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let Node { first, count, .. } = nodes[index];
+            let range = &mut ids[first as usize..(first + count) as usize];
+            let node_bounds = range.iter().fold(Aabb::default(), |b, &t| b.union(bounds[t as usize]));
+            nodes[index].bounds = node_bounds;
+
+            let Some(split) = best_split(range, &bounds, &centroids, &node_bounds) else {
+                continue;
+            };
+            let mid = partition(range, |t| split.goes_left(centroids[t as usize])) as u32;
+
+            let left = nodes.len();
+            nodes.push(Node { bounds: Aabb::default(), first, count: mid });
+            nodes.push(Node { bounds: Aabb::default(), first: first + mid, count: count - mid });
+            nodes[index].first = left as u32;
+            nodes[index].count = 0;
+            stack.push(left);
+            stack.push(left + 1);
+        }
+
+        Self {
+            nodes,
+            triangles: ids.iter().map(|&t| triangles[t as usize]).collect(),
+            ids,
+        }
+    }
+
+    /// The nearest triangle along the ray, with its index in the mesh.
+    pub fn closest_hit(&self, ray: &Ray) -> Option<(usize, TriangleHit)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inv_direction = ray.direction.map(|d| 1. / d);
+        let mut closest: Option<(usize, TriangleHit)> = None;
+        let mut max_distance = f32::INFINITY;
+        let mut stack = Vec::new();
+        if entry_distance(&self.nodes[0].bounds, ray, inv_direction).is_some() {
+            stack.push(0);
+        }
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.count > 0 {
+                for i in node.first as usize..(node.first + node.count) as usize {
+                    if let Some(hit) = ray.intersect_triangle(&self.triangles[i])
+                        && hit.distance < max_distance {
+                        max_distance = hit.distance;
+                        closest = Some((self.ids[i] as usize, hit));
+                    }
+                }
+                continue;
+            }
+
+            // the nearer child goes on top of the stack, so its hits can prune the other one
+            let left = node.first as usize;
+            let distance = |i: usize| entry_distance(&self.nodes[i].bounds, ray, inv_direction).filter(|&d| d <= max_distance);
+            match (distance(left), distance(left + 1)) {
+                (Some(l), Some(r)) if l <= r => stack.extend([left + 1, left]),
+                (Some(_), Some(_)) => stack.extend([left, left + 1]),
+                (Some(_), None) => stack.push(left),
+                (None, Some(_)) => stack.push(left + 1),
+                (None, None) => {}
+            }
+        }
+        closest
+    }
+
+    /// Whether anything lies along the ray closer than `max_distance`, e.g. for shadow rays.
+    /// Stops at the first hit found.
+    pub fn any_hit(&self, ray: &Ray, max_distance: f32) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let inv_direction = ray.direction.map(|d| 1. / d);
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if entry_distance(&node.bounds, ray, inv_direction).is_none_or(|d| d > max_distance) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.extend([node.first as usize, node.first as usize + 1]);
+                continue;
+            }
+            let leaf = &self.triangles[node.first as usize..(node.first + node.count) as usize];
+            if leaf.iter().any(|t| ray.intersect_triangle(t).is_some_and(|hit| hit.distance <= max_distance)) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Updates the bounds after the mesh's vertices moved, keeping the tree as it is. Much faster
+    /// than rebuilding, but queries slow down as the mesh drifts from the shape it was built for.
+    /// The mesh must still have the same triangles.
+    pub fn refit(&mut self, mesh: &Mesh) {
+        for (triangle, &id) in self.triangles.iter_mut().zip(&self.ids) {
+            *triangle = mesh.get_triangle(id as usize);
+        }
+        // children come after their parents, so walking backwards sees them first
+        for i in (0..self.nodes.len()).rev() {
+            let Node { first, count, .. } = self.nodes[i];
+            self.nodes[i].bounds = if count > 0 {
+                self.triangles[first as usize..(first + count) as usize].iter().fold(Aabb::default(), |b, t| b.union(t.bounds()))
+            } else {
+                self.nodes[first as usize].bounds.union(self.nodes[first as usize + 1].bounds)
+            };
+        }
+    }
+
+    /// Bounds of the whole mesh.
+    pub fn get_bounds(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::default(), |n| n.bounds)
+    }
+
+    pub fn get_node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Writes the tree to a file kept next to the mesh, see [`Bvh::load`].
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Reads a tree written by [`Bvh::save`] for `mesh`, skipping the build.
+    pub fn load(path: &str, mesh: &Mesh) -> io::Result<Self> {
+        Self::read(&mut BufReader::new(File::open(path)?), mesh)
+    }
+
+    /// Little endian: a header with the triangle and node counts and the mesh's fingerprint, the
+    /// nodes, then the triangle order. The triangles themselves are taken from the mesh when
+    /// reading.
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(&MAGIC)?;
+        for value in [VERSION, self.ids.len() as u32, self.nodes.len() as u32] {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        let mut in_mesh_order = self.triangles.clone();
+        for (&id, &triangle) in self.ids.iter().zip(&self.triangles) {
+            in_mesh_order[id as usize] = triangle;
+        }
+        let (bounds, hash) = fingerprint(in_mesh_order.into_iter());
+        for value in bounds.min.as_array().into_iter().chain(bounds.max.as_array()) {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        w.write_all(&hash.to_le_bytes())?;
+        for node in &self.nodes {
+            for value in node.bounds.min.as_array().into_iter().chain(node.bounds.max.as_array()) {
+                w.write_all(&value.to_le_bytes())?;
+            }
+            w.write_all(&node.first.to_le_bytes())?;
+            w.write_all(&node.count.to_le_bytes())?;
+        }
+        for id in &self.ids {
+            w.write_all(&id.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads what [`Bvh::write`] wrote, checking that it fits `mesh` and is a well formed tree.
+    pub fn read(r: &mut impl Read, mesh: &Mesh) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("not a BVH file"));
+        }
+        if read_u32(r)? != VERSION {
+            return Err(invalid("unsupported BVH version"));
+        }
+        let triangle_count = read_u32(r)? as usize;
+        let node_count = read_u32(r)? as usize;
+        if triangle_count != mesh.get_triangle_count() {
+            return Err(invalid("BVH was built for a different mesh"));
+        }
+        let mut v = [0.; 6];
+        for value in &mut v {
+            *value = f32::from_bits(read_u32(r)?);
+        }
+        let hash = read_u32(r)? as u64 | (read_u32(r)? as u64) << 32;
+        let (bounds, mesh_hash) = fingerprint((0..triangle_count).map(|t| mesh.get_triangle(t)));
+        let same_bounds = bounds.min.as_array().into_iter().chain(bounds.max.as_array()).zip(v).all(|(a, b)| a.to_bits() == b.to_bits());
+        if !same_bounds || hash != mesh_hash {
+            return Err(invalid("BVH was built for a different mesh"));
+        }
+
+        let mut nodes = Vec::with_capacity(node_count.min(2 * triangle_count));
+        for index in 0..node_count {
+            let mut v = [0.; 6];
+            for value in &mut v {
+                *value = f32::from_bits(read_u32(r)?);
+            }
+            let (first, count) = (read_u32(r)?, read_u32(r)?);
+            let valid = if count > 0 {
+                first as usize + count as usize <= triangle_count
+            } else {
+                first as usize > index && (first as usize) < node_count - 1
+            };
+            if !valid {
+                return Err(invalid("malformed BVH node"));
+            }
+            let bounds = Aabb::new(Vector3::new(v[0], v[1], v[2]), Vector3::new(v[3], v[4], v[5]));
+            nodes.push(Node { bounds, first, count });
+        }
+
+        let mut ids = Vec::with_capacity(triangle_count);
+        for _ in 0..triangle_count {
+            let id = read_u32(r)?;
+            if id as usize >= triangle_count {
+                return Err(invalid("BVH triangle index out of range"));
+            }
+            ids.push(id);
+        }
+
+        Ok(Self {
+            nodes,
+            triangles: ids.iter().map(|&t| mesh.get_triangle(t as usize)).collect(),
+            ids,
+        })
+    }
+}
+
+/// Binned surface area heuristic: the split with the least expected cost of intersecting both
+/// children, or `None` when keeping the node as a leaf is cheaper.
+fn best_split(range: &[u32], bounds: &[Aabb], centroids: &[Vector3<f32>], node_bounds: &Aabb) -> Option<Split> {
+    if range.len() <= MIN_LEAF_SIZE {
+        return None;
+    }
+    let centroid_bounds = Aabb::from_points(range.iter().map(|&t| centroids[t as usize]));
+    let parent_area = node_bounds.surface_area().max(f32::MIN_POSITIVE);
+    let mut best: Option<(f32, Split)> = None;
+
+    for axis in 0..3 {
+        let (min, max) = (centroid_bounds.min[axis], centroid_bounds.max[axis]);
+        if max <= min {
+            continue;
+        }
+        let scale = BINS as f32 / (max - min);
+        let mut bins = [(Aabb::default(), 0); BINS];
+        for &t in range {
+            let bin = &mut bins[Split::bin(axis, min, scale, centroids[t as usize])];
+            *bin = (bin.0.union(bounds[t as usize]), bin.1 + 1);
+        }
+
+        // everything right of each boundary, swept from the right
+        let mut right = [(0., 0); BINS - 1];
+        let mut sweep = (Aabb::default(), 0);
+        for i in (1..BINS).rev() {
+            sweep = (sweep.0.union(bins[i].0), sweep.1 + bins[i].1);
+            right[i - 1] = (sweep.0.surface_area(), sweep.1);
+        }
+        let mut left = (Aabb::default(), 0);
+        for (bin, &(right_area, right_count)) in right.iter().enumerate() {
+            left = (left.0.union(bins[bin].0), left.1 + bins[bin].1);
+            if left.1 == 0 || right_count == 0 {
+                continue;
+            }
+            let cost = TRAVERSAL_COST + (left.0.surface_area() * left.1 as f32 + right_area * right_count as f32) / parent_area;
+            if best.as_ref().is_none_or(|(c, _)| cost < *c) {
+                best = Some((cost, Split { axis, min, scale, bin }));
+            }
+        }
+    }
+
+    // without a split every centroid is in the same place, and nothing separates them
+    let (cost, split) = best?;
+    (cost < range.len() as f32 || range.len() > MAX_LEAF_SIZE).then_some(split)
+}
+
+/// Moves the entries for which `left` holds to the front, returning how many there are.
+fn partition(ids: &mut [u32], left: impl Fn(u32) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..ids.len() {
+        if left(ids[i]) {
+            ids.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+/// Where the ray enters the box, zero from inside it.
+fn entry_distance(bounds: &Aabb, ray: &Ray, inv_direction: Vector3<f32>) -> Option<f32> {
+    let t0 = (bounds.min - ray.origin) * inv_direction;
+    let t1 = (bounds.max - ray.origin) * inv_direction;
+    let (near, far) = (t0.min(t1), t0.max(t1));
+    let near = near.x.max(near.y).max(near.z).max(0.);
+    let far = far.x.min(far.y).min(far.z);
+    (near <= far).then_some(near)
+}
+
+/// Bounds of the triangles and a hash of their corners, in mesh order, to tell whether a saved
+/// tree was built for a mesh.
+fn fingerprint(triangles: impl Iterator<Item = Triangle>) -> (Aabb, u64) {
+    let mut bounds = Aabb::default();
+    // FNV-1a, which unlike the std hashers stays the same from one build to the next
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for triangle in triangles {
+        for p in [triangle.a, triangle.b, triangle.c] {
+            bounds = bounds.extended(p);
+            for byte in p.as_array().into_iter().flat_map(f32::to_le_bytes) {
+                hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+            }
+        }
+    }
+    (bounds, hash)
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,20 +1,184 @@
+use std::f32::consts::FRAC_PI_2;
+use serde::{Deserialize, Serialize};
+use crate::geometry::{Frustum, Ray};
 use crate::matrix::Matrix4;
-use crate::transform::Transform;
+use crate::quaternion::Quaternion;
+use crate::transform::DTransform;
+use crate::vector::{Vector3, Vector4};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    Orthographic,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ViewPreset {
+    Front,
+    Right,
+    Top,
+}
+
+impl ViewPreset {
+    /// Orientation of a camera looking at the origin from this side.
+    pub fn orientation(self) -> Quaternion {
+        match self {
+            ViewPreset::Front => Quaternion::default(),
+            ViewPreset::Right => Quaternion::from_angle_axis(FRAC_PI_2, Vector3::up()),
+            ViewPreset::Top => Quaternion::from_angle_axis(-FRAC_PI_2, Vector3::right()),
+        }
+    }
+}
 
 pub struct Camera {
-    pub transform: Transform,
-    projection_matrix: Matrix4,
+    /// Placement in world space, in double precision so the camera can sit at large coordinates.
+    pub transform: DTransform,
+    projection: Projection,
+    fov: f32,
+    aspect: f32,
+    near: f32,
+    far: f32,
+    ortho_height: f32,
+    focus_distance: f32,
 }
 
 impl Camera{
     pub fn new(fov: f32, aspect: f32, near: f32, far: f32) -> Self {
         Self{
-            transform: Transform::default(),
-            projection_matrix: Matrix4::project(fov, aspect, near, far),
+            transform: DTransform::default(),
+            projection: Projection::Perspective,
+            fov,
+            aspect,
+            near,
+            far,
+            ortho_height: 2.,
+            focus_distance: 1.,
         }
     }
 
+    /// Takes world space into camera space, the inverse of the camera's transform.
+    pub fn view_matrix(&self) -> Matrix4 {
+        self.transform.inverse_matrix().cast()
+    }
+
+    /// View matrix for camera-relative world space, where the camera sits at the origin.
+    /// Rendering uses this with model matrices made relative to [`Camera::get_position`].
+    pub fn relative_view_matrix(&self) -> Matrix4 {
+        self.transform.relative_to(self.get_position()).inverse_matrix()
+    }
+
+    /// Ray from the camera through a point of the viewport, given in pixels from the top left
+    /// corner. Like [`Camera::relative_view_matrix`] it lives in camera-relative world space.
+    pub fn screen_ray(&self, x: f32, y: f32, width: f32, height: f32) -> Ray {
+        let ndc_x = 2. * x / width - 1.;
+        let ndc_y = 1. - 2. * y / height;
+        // only a degenerate projection (zero field of view or clip range) has no inverse
+        let unproject = (self.relative_view_matrix() * self.get_projection_matrix()).inverse().unwrap_or(Matrix4::identity());
+        let near = (Vector4::new(ndc_x, ndc_y, 0., 1.) * unproject).project();
+        let far = (Vector4::new(ndc_x, ndc_y, 1., 1.) * unproject).project();
+        Ray::new(near, far - near)
+    }
+
+    /// What the camera sees, in camera-relative world space like [`Camera::relative_view_matrix`].
+    pub fn get_frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.relative_view_matrix() * self.get_projection_matrix())
+    }
+
+    pub fn get_position(&self) -> Vector3<f64> {
+        self.transform.get_position()
+    }
+
     pub fn get_projection_matrix(&self) -> Matrix4 {
-        self.projection_matrix
+        match self.projection {
+            Projection::Perspective => Matrix4::project(self.fov, self.aspect, self.near, self.far),
+            Projection::Orthographic => Matrix4::orthographic(self.ortho_height, self.aspect, self.near, self.far),
+        }
+    }
+
+    /// Switches between perspective and orthographic projection. Whatever sits at the
+    /// focus distance keeps its apparent size across the switch.
+    pub fn set_projection(&mut self, projection: Projection) {
+        if projection == self.projection {
+            return;
+        }
+        let half_fov_tan = (self.fov / 2.).tan();
+        match projection {
+            Projection::Orthographic => self.ortho_height = 2. * self.focus_distance * half_fov_tan,
+            Projection::Perspective => self.focus_distance = self.ortho_height / (2. * half_fov_tan),
+        }
+        self.projection = projection;
+    }
+
+    pub fn toggle_projection(&mut self) {
+        match self.projection {
+            Projection::Perspective => self.set_projection(Projection::Orthographic),
+            Projection::Orthographic => self.set_projection(Projection::Perspective),
+        }
+    }
+
+    /// Looks at the origin along one of the world axes, keeping the focus distance.
+    pub fn set_view_preset(&mut self, preset: ViewPreset) {
+        let orientation = preset.orientation();
+        self.transform.set_rotation(orientation.cast());
+        self.transform.set_position(orientation.rotate(Vector3::new(0., 0., self.focus_distance)).cast());
+    }
+
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov;
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
+
+    /// Sets the aspect ratio from a viewport size, ignoring empty (minimized) viewports.
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+        self.near = near;
+        self.far = far;
+    }
+
+    /// Height of the visible area in world units while the projection is orthographic.
+    pub fn set_ortho_height(&mut self, height: f32) {
+        self.ortho_height = height;
+    }
+
+    /// Distance from the camera to the point it is looking at.
+    pub fn set_focus_distance(&mut self, distance: f32) {
+        self.focus_distance = distance;
+    }
+
+    pub fn get_projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn get_fov(&self) -> f32 {
+        self.fov
+    }
+
+    pub fn get_aspect(&self) -> f32 {
+        self.aspect
+    }
+
+    pub fn get_near(&self) -> f32 {
+        self.near
+    }
+
+    pub fn get_far(&self) -> f32 {
+        self.far
+    }
+
+    pub fn get_ortho_height(&self) -> f32 {
+        self.ortho_height
+    }
+
+    pub fn get_focus_distance(&self) -> f32 {
+        self.focus_distance
     }
 }
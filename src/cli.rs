@@ -0,0 +1,411 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use crate::color::Color;
+use crate::display::Shading;
+use crate::mesh_file::{ConvertOptions, Handedness, ImportOptions, Normals, UpAxis};
+use crate::repair::RepairOptions;
+use crate::simplify::{LodOptions, SimplifyOptions};
+use crate::scene_file::{NodeDescription, SceneFile};
+use crate::shader::Light;
+use crate::vector::Vector3;
+
+/// View, render, inspect and convert triangle meshes.
+///
+/// Without a subcommand the inputs are opened in the viewer, so `triangle model.obj` is
+/// `triangle view model.obj`.
+#[derive(Parser, Debug)]
+#[command(name = "triangle", version, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub view: ViewArgs,
+}
+
+impl Cli {
+    /// The subcommand, viewing when none was given.
+    pub fn into_command(self) -> Command {
+        self.command.unwrap_or(Command::View(self.view))
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Open models and scenes in a window
+    View(ViewArgs),
+    /// Render models and scenes to a PNG image without opening a window
+    Render(RenderArgs),
+    /// Print what the models contain and what is wrong with them
+    Info(InfoArgs),
+    /// Write a model to another file, converting between OBJ, STL, PLY and glTF
+    ///
+    /// Polygons, triangle strips and fans always become triangles, as that is all a mesh holds.
+    Convert(ConvertArgs),
+    /// Fix common defects of a model and report what was fixed and what is left
+    ///
+    /// Without an output file nothing is written, so this checks what a repair would do.
+    Repair(RepairArgs),
+    /// Write a model with fewer triangles, keeping its shape, boundaries and texture seams
+    Simplify(SimplifyArgs),
+}
+
+/// What to show and how, shared by `view` and `render`.
+#[derive(Args, Debug)]
+pub struct SceneArgs {
+    /// OBJ, STL, PLY or glTF models and RON or JSON scene files, shown together [default: assets/cube.obj]
+    pub inputs: Vec<PathBuf>,
+    /// How surfaces are shaded
+    #[arg(long, value_enum, default_value_t = Shading::Phong)]
+    pub shader: Shading,
+    /// Size in pixels
+    #[arg(long, value_name = "WxH", value_parser = parse_size, default_value = "600x420")]
+    pub size: Size,
+    /// Background colour as r,g,b[,a] from 0 to 1, or #rrggbb [default: the scene's, or black]
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub background: Option<Color>,
+    /// Camera position, looking at the scene's camera target [default: the scene's, or 0,0,5]
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_vector::<f64>, allow_hyphen_values = true)]
+    pub camera: Option<Vector3<f64>>,
+    /// Direction the light travels in [default: the scene's, or -1,-1,1]
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_vector::<f32>, allow_hyphen_values = true)]
+    pub light: Option<Vector3<f32>>,
+    /// How to read the models given directly; scene files say so per model
+    #[command(flatten)]
+    pub import: ImportArgs,
+    /// Make this many coarser levels of detail per model, drawn further from the camera
+    #[arg(long, value_name = "LEVELS", default_value_t = 0)]
+    pub lods: usize,
+}
+
+impl SceneArgs {
+    /// How to make levels of detail, None without `--lods`.
+    pub fn get_lod_options(&self) -> Option<LodOptions> {
+        (self.lods > 0).then(|| LodOptions { levels: self.lods, ..LodOptions::default() })
+    }
+}
+
+/// How the coordinates of model files are read, see [`ImportOptions`].
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// Up axis of the models
+    #[arg(long, value_enum, alias = "input-up", default_value_t = UpAxis::Y)]
+    pub up: UpAxis,
+    /// Handedness of the models' coordinates; left-handed ones are mirrored
+    #[arg(long, value_enum, default_value_t = Handedness::Right)]
+    pub handedness: Handedness,
+    /// Metres per unit of the models, e.g. 0.001 for millimetres
+    #[arg(long, alias = "scale", value_parser = parse_scale, default_value_t = 1.)]
+    pub unit_scale: f32,
+}
+
+impl ImportArgs {
+    pub fn get_options(&self) -> ImportOptions {
+        ImportOptions { up_axis: self.up, handedness: self.handedness, unit_scale: self.unit_scale }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ViewArgs {
+    #[command(flatten)]
+    pub scene: SceneArgs,
+    /// Keep the models still instead of spinning them
+    #[arg(long)]
+    pub no_rotate: bool,
+    /// Wait for the display between frames
+    #[arg(long, value_name = "on|off", value_parser = parse_switch, action = ArgAction::Set, default_value = "on")]
+    pub vsync: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RenderArgs {
+    #[command(flatten)]
+    pub scene: SceneArgs,
+    /// PNG file to write
+    #[arg(short, long, default_value = "render.png")]
+    pub output: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct InfoArgs {
+    /// OBJ, STL, PLY or glTF models
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+    /// How to print the report
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+}
+
+/// Output of `info`: readable text, or JSON for scripts.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+    /// Model to read: .obj, .stl, .ply, .gltf or .glb
+    pub input: PathBuf,
+    /// File to write, in the format of its extension
+    pub output: PathBuf,
+    /// Share vertices that are equal in every attribute
+    #[arg(long)]
+    pub weld: bool,
+    #[command(flatten)]
+    pub import: ImportArgs,
+    /// Replace the normals with face normals or smooth ones
+    #[arg(long, value_enum)]
+    pub normals: Option<Normals>,
+    /// Up axis to write
+    #[arg(long, value_enum, default_value_t = UpAxis::Y)]
+    pub output_up: UpAxis,
+    /// Put every triangle in one group without materials
+    #[arg(long)]
+    pub merge: bool,
+}
+
+impl ConvertArgs {
+    pub fn get_options(&self) -> ConvertOptions {
+        ConvertOptions {
+            import: self.import.get_options(),
+            output_up: self.output_up,
+            normals: self.normals,
+            weld: self.weld,
+            merge: self.merge,
+        }
+    }
+}
+
+/// Which repairs to make. Without any of them, degenerate and duplicate triangles and
+/// unreferenced vertices are removed.
+#[derive(Args, Debug)]
+pub struct RepairArgs {
+    /// Model to read: .obj, .stl, .ply, .gltf or .glb
+    pub input: PathBuf,
+    /// File to write the repaired model to, in the format of its extension
+    pub output: Option<PathBuf>,
+    /// Move vertices within this distance of each other onto one position
+    #[arg(long, value_name = "DISTANCE", value_parser = parse_distance)]
+    pub merge_vertices: Option<f32>,
+    /// Remove triangles without area
+    #[arg(long)]
+    pub degenerate: bool,
+    /// Remove triangles with the same corners as another
+    #[arg(long)]
+    pub duplicates: bool,
+    /// Make neighbouring triangles agree on their winding and face outwards
+    #[arg(long)]
+    pub winding: bool,
+    /// Fill holes bounded by at most this many edges
+    #[arg(long, value_name = "EDGES")]
+    pub fill_holes: Option<usize>,
+    /// Remove vertices no triangle uses
+    #[arg(long)]
+    pub unreferenced: bool,
+}
+
+impl RepairArgs {
+    pub fn get_options(&self) -> RepairOptions {
+        let chosen = RepairOptions {
+            merge_distance: self.merge_vertices,
+            degenerate: self.degenerate,
+            duplicates: self.duplicates,
+            winding: self.winding,
+            fill_holes: self.fill_holes,
+            unreferenced: self.unreferenced,
+        };
+        let none = RepairOptions { merge_distance: None, degenerate: false, duplicates: false, winding: false, fill_holes: None, unreferenced: false };
+        if chosen == none { RepairOptions::default() } else { chosen }
+    }
+}
+
+/// How far to simplify. Without any limit, half the triangles go.
+#[derive(Args, Debug)]
+pub struct SimplifyArgs {
+    /// Model to read: .obj, .stl, .ply, .gltf or .glb
+    pub input: PathBuf,
+    /// File to write, in the format of its extension
+    pub output: PathBuf,
+    /// Triangles to keep
+    #[arg(long, conflicts_with = "ratio")]
+    pub triangles: Option<usize>,
+    /// Share of the triangles to keep, from 0 to 1
+    #[arg(long, value_parser = parse_ratio)]
+    pub ratio: Option<f32>,
+    /// Largest quadric error to allow, in the model's units: about how far vertices may leave
+    /// the planes of the faces around them, with boundaries and seams weighted 10 times
+    #[arg(long, value_parser = parse_distance)]
+    pub error: Option<f32>,
+}
+
+impl SimplifyArgs {
+    /// The options for a mesh of `triangles` triangles.
+    pub fn get_options(&self, triangles: usize) -> SimplifyOptions {
+        let ratio = match (self.ratio, self.triangles, self.error) {
+            (None, None, None) => Some(0.5),
+            (ratio, _, _) => ratio,
+        };
+        SimplifyOptions {
+            target_triangles: self.triangles.or(ratio.map(|r| (triangles as f32 * r).round() as usize)),
+            max_error: self.error,
+        }
+    }
+}
+
+/// Width and height in pixels.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Parses `WxH`, e.g. `1280x720`.
+pub fn parse_size(s: &str) -> Result<Size, String> {
+    let (w, h) = s.split_once(['x', 'X']).ok_or_else(|| format!("expected WIDTHxHEIGHT, e.g. 1280x720, not {}", s))?;
+    let parse = |v: &str| match v.trim().parse::<u32>() {
+        Ok(0) => Err(String::from("the size cannot be zero")),
+        Ok(v) => Ok(v),
+        Err(_) => Err(format!("{} is not a whole number of pixels", v)),
+    };
+    Ok(Size { width: parse(w)?, height: parse(h)? })
+}
+
+/// Parses three comma separated numbers, e.g. `1,-2.5,3`.
+pub fn parse_vector<T: std::str::FromStr>(s: &str) -> Result<Vector3<T>, String> {
+    let values = s.split(',').map(|v| v.trim().parse::<T>().map_err(|_| format!("{} is not a number", v.trim())))
+        .collect::<Result<Vec<T>, String>>()?;
+    match <[T; 3]>::try_from(values) {
+        Ok([x, y, z]) => Ok(Vector3::new(x, y, z)),
+        Err(_) => Err(format!("expected X,Y,Z, not {}", s)),
+    }
+}
+
+/// Parses `r,g,b` or `r,g,b,a` from 0 to 1, or `#rrggbb`.
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    if let Some(hex) = s.strip_prefix('#') {
+        let value = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6)
+            .ok_or_else(|| format!("expected #rrggbb, not {}", s))?;
+        let channel = |shift: u32| ((value >> shift) & 0xff) as f32 / 255.;
+        return Ok(Color::new(channel(16), channel(8), channel(0), 1.));
+    }
+    let values = s.split(',').map(|v| v.trim().parse::<f32>().map_err(|_| format!("{} is not a number", v.trim())))
+        .collect::<Result<Vec<f32>, String>>()?;
+    if values.iter().any(|v| !(0. ..=1.).contains(v)) {
+        return Err(format!("colour channels go from 0 to 1, not {}", s));
+    }
+    match values[..] {
+        [r, g, b] => Ok(Color::new(r, g, b, 1.)),
+        [r, g, b, a] => Ok(Color::new(r, g, b, a)),
+        _ => Err(format!("expected r,g,b or r,g,b,a, not {}", s)),
+    }
+}
+
+/// Parses a positive scale factor.
+pub fn parse_scale(s: &str) -> Result<f32, String> {
+    match s.trim().parse::<f32>() {
+        Ok(v) if v > 0. && v.is_finite() => Ok(v),
+        Ok(_) => Err(format!("the scale must be positive, not {}", s)),
+        Err(_) => Err(format!("{} is not a number", s)),
+    }
+}
+
+/// Parses a distance, which must be positive.
+pub fn parse_distance(s: &str) -> Result<f32, String> {
+    match s.trim().parse::<f32>() {
+        Ok(v) if v > 0. && v.is_finite() => Ok(v),
+        Ok(_) => Err(format!("the distance must be positive, not {}", s)),
+        Err(_) => Err(format!("{} is not a number", s)),
+    }
+}
+
+/// Parses a share from 0 to 1.
+pub fn parse_ratio(s: &str) -> Result<f32, String> {
+    match s.trim().parse::<f32>() {
+        Ok(v) if (0. ..=1.).contains(&v) => Ok(v),
+        Ok(_) => Err(format!("the ratio must be from 0 to 1, not {}", s)),
+        Err(_) => Err(format!("{} is not a number", s)),
+    }
+}
+
+/// Parses `on` or `off`.
+pub fn parse_switch(s: &str) -> Result<bool, String> {
+    match s {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("expected on or off, not {}", s)),
+    }
+}
+
+fn is_scene_file(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("ron") || e.eq_ignore_ascii_case("json"))
+}
+
+impl SceneArgs {
+    /// Gathers the inputs into one scene with the overrides applied, along with the path it is
+    /// saved to: the scene file when exactly one was given, otherwise scene.ron. Model paths are
+    /// relative to the working directory. The first scene file sets the camera, lights and
+    /// background; further scenes only add their nodes and materials, whose names must not
+    /// clash with those of the scenes before.
+    pub fn load(&self) -> io::Result<(SceneFile, PathBuf)> {
+        let default_input = [PathBuf::from("assets/cube.obj")];
+        let inputs = if self.inputs.is_empty() { &default_input[..] } else { &self.inputs[..] };
+
+        let mut file = SceneFile::default();
+        let mut scene_paths = Vec::new();
+        for input in inputs {
+            if is_scene_file(input) {
+                let mut scene = SceneFile::load(input)?;
+                let dir = input.parent().unwrap_or(Path::new(""));
+                let mut stack: Vec<&mut NodeDescription> = scene.nodes.iter_mut().collect();
+                while let Some(node) = stack.pop() {
+                    if let Some(model) = &mut node.model {
+                        *model = dir.join(&*model).to_string_lossy().into_owned();
+                    }
+                    stack.extend(node.children.iter_mut());
+                }
+                if scene_paths.is_empty() {
+                    file.camera = scene.camera;
+                    file.lights = scene.lights;
+                    file.background = scene.background;
+                }
+                for material in scene.materials {
+                    if file.get_material(&material.name).is_some() {
+                        let message = format!("{}: material {} is defined more than once", input.display(), material.name);
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                    }
+                    file.materials.push(material);
+                }
+                file.nodes.extend(scene.nodes);
+                scene_paths.push(input.clone());
+            } else {
+                let name = input.file_stem().map_or(String::from("model"), |s| s.to_string_lossy().into_owned());
+                file.nodes.push(NodeDescription {
+                    name,
+                    model: Some(input.to_string_lossy().into_owned()),
+                    import: self.import.get_options(),
+                    ..NodeDescription::default()
+                });
+            }
+        }
+
+        if let Some(background) = self.background {
+            file.background = background;
+        }
+        if let Some(position) = self.camera {
+            file.camera.position = position;
+        }
+        if let Some(direction) = self.light {
+            match file.lights.first_mut() {
+                Some(Light::Directional { direction: d }) => *d = direction,
+                None => file.lights.push(Light::Directional { direction }),
+            }
+        }
+
+        let save_path = match &scene_paths[..] {
+            [path] => path.clone(),
+            _ => PathBuf::from("scene.ron"),
+        };
+        Ok((file, save_path))
+    }
+}
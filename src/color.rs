@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -12,13 +15,7 @@ impl Color {
         }
     }
     pub fn as_vec(&self) -> Vec<f32>{
-        let mut v: Vec<f32> = Vec::new();
-        v.push(self.r);
-        v.push(self.g);
-        v.push(self.b);
-        v.push(self.a);
-
-        v
+        vec![self.r, self.g, self.b, self.a]
     }
 }
 
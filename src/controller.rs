@@ -0,0 +1,224 @@
+use std::f32::consts::FRAC_PI_2;
+use crate::camera::{Camera, Projection, ViewPreset};
+use crate::quaternion::Quaternion;
+use crate::vector::Vector3;
+
+/// Orbits the camera around a target point.
+///
+/// Input is collected as pending rotation, pan and zoom which [`OrbitController::update`]
+/// eases in over a few frames, so motion keeps going for a moment after the mouse stops.
+pub struct OrbitController {
+    target: Vector3<f64>,
+    distance: f32,
+    rotation: Quaternion,
+    pub rotate_sensitivity: f32,
+    pub pan_sensitivity: f32,
+    pub zoom_sensitivity: f32,
+    /// How quickly pending motion is applied, per second. Zero or less applies it immediately.
+    pub damping: f32,
+    pending_rotation: Vector3<f32>,
+    pending_pan: Vector3<f32>,
+    pending_zoom: f32,
+}
+
+impl OrbitController {
+    pub fn new(target: Vector3<f64>, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            rotation: Quaternion::default(),
+            rotate_sensitivity: 1.,
+            pan_sensitivity: 1.,
+            zoom_sensitivity: 0.1,
+            damping: 20.,
+            pending_rotation: Vector3::default(),
+            pending_pan: Vector3::default(),
+            pending_zoom: 0.,
+        }
+    }
+
+    /// Arcball rotation for a cursor drag from `from` to `to`, in pixels of a `width` x `height` viewport.
+    pub fn rotate(&mut self, from: (f32, f32), to: (f32, f32), width: f32, height: f32) {
+        let p0 = Self::arcball_point(from, width, height);
+        let p1 = Self::arcball_point(to, width, height);
+        let mut axis = p0.cross(&p1);
+        if axis.length_squared() == 0. {
+            return;
+        }
+        axis.normalize();
+        let angle = p0.dot(&p1).clamp(-1., 1.).acos() * self.rotate_sensitivity;
+        // the drag turns the scene, so the camera turns the other way
+        self.pending_rotation -= axis * angle;
+    }
+
+    /// Moves the target with the cursor, so the point under it stays there.
+    pub fn pan(&mut self, dx: f32, dy: f32, camera: &Camera, height: f32) {
+        let view_height = match camera.get_projection() {
+            Projection::Perspective => 2. * self.distance * (camera.get_fov() / 2.).tan(),
+            Projection::Orthographic => camera.get_ortho_height(),
+        };
+        let scale = view_height / height * self.pan_sensitivity;
+        self.pending_pan += Vector3::new(-dx * scale, dy * scale, 0.);
+    }
+
+    /// Dollies towards the target for positive amounts, e.g. scroll wheel lines.
+    pub fn zoom(&mut self, amount: f32) {
+        self.pending_zoom += amount * self.zoom_sensitivity;
+    }
+
+    /// Moves the target along the camera's own axes.
+    pub fn translate(&mut self, offset: Vector3<f32>) {
+        self.pending_pan += offset;
+    }
+
+    pub fn set_view(&mut self, preset: ViewPreset) {
+        self.rotation = preset.orientation();
+        self.pending_rotation = Vector3::default();
+    }
+
+    pub fn set_rotation(&mut self, rotation: Quaternion) {
+        self.rotation = rotation;
+        self.pending_rotation = Vector3::default();
+    }
+
+    pub fn set_target(&mut self, target: Vector3<f64>) {
+        self.target = target;
+    }
+
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance;
+    }
+
+    pub fn get_target(&self) -> Vector3<f64> {
+        self.target
+    }
+
+    pub fn get_distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn get_rotation(&self) -> Quaternion {
+        self.rotation
+    }
+
+    /// Where the camera sits on the orbit.
+    pub fn get_position(&self) -> Vector3<f64> {
+        self.target + self.rotation.rotate(Vector3::new(0., 0., self.distance)).cast()
+    }
+
+    /// Applies the share of pending motion that is due after `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        let f = if self.damping <= 0. { 1. } else { 1. - (-self.damping * dt).exp() };
+
+        let step = self.pending_rotation * f;
+        let angle = step.length();
+        if angle > 0. {
+            self.rotation = (self.rotation * Quaternion::from_angle_axis(angle, step)).normalize();
+        }
+        self.pending_rotation -= step;
+
+        let step = self.pending_pan * f;
+        let right = self.rotation.rotate(Vector3::right());
+        let up = self.rotation.rotate(Vector3::up());
+        let back = self.rotation.rotate(Vector3::forward());
+        self.target += (right * step.x + up * step.y + back * step.z).cast();
+        self.pending_pan -= step;
+
+        let step = self.pending_zoom * f;
+        self.distance *= (-step).exp();
+        self.pending_zoom -= step;
+    }
+
+    /// Places the camera on its orbit.
+    pub fn apply(&self, camera: &mut Camera) {
+        place_camera(camera, self.get_position(), self.rotation);
+        camera.set_focus_distance(self.distance);
+        if camera.get_projection() == Projection::Orthographic {
+            camera.set_ortho_height(2. * self.distance * (camera.get_fov() / 2.).tan());
+        }
+    }
+
+    // Bell's arcball: a sphere in the middle of the viewport blending into a hyperbolic sheet
+    fn arcball_point((x, y): (f32, f32), width: f32, height: f32) -> Vector3<f32> {
+        let radius = width.min(height) / 2.;
+        let px = (x - width / 2.) / radius;
+        let py = (height / 2. - y) / radius;
+        let d2 = px * px + py * py;
+        let pz = if d2 <= 0.5 { (1. - d2).sqrt() } else { 0.5 / d2.sqrt() };
+        let mut p = Vector3::new(px, py, pz);
+        p.normalize();
+        p
+    }
+}
+
+/// First-person camera: mouse look plus movement along the camera's own axes.
+pub struct FlyController {
+    position: Vector3<f64>,
+    yaw: f32,
+    pitch: f32,
+    /// Movement speed in units per second.
+    pub speed: f32,
+    /// Radians turned per pixel of mouse motion.
+    pub look_sensitivity: f32,
+}
+
+// stop just short of straight up or down, where yaw stops meaning anything
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+impl FlyController {
+    pub fn new(position: Vector3<f64>, speed: f32) -> Self {
+        Self {
+            position,
+            yaw: 0.,
+            pitch: 0.,
+            speed,
+            look_sensitivity: 0.003,
+        }
+    }
+
+    /// Turns the camera for a mouse motion of `dx`, `dy` pixels.
+    pub fn look(&mut self, dx: f32, dy: f32) {
+        self.yaw -= dx * self.look_sensitivity;
+        self.pitch = (self.pitch - dy * self.look_sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Moves for `dt` seconds. `direction` is in camera space (x right, y up, z back), except
+    /// that up is always world up; `speed_factor` scales the configured speed.
+    pub fn update(&mut self, dt: f32, mut direction: Vector3<f32>, speed_factor: f32) {
+        if direction.length_squared() == 0. {
+            return;
+        }
+        // diagonal movement is not faster than straight movement
+        direction.normalize();
+        let rotation = self.get_rotation();
+        let right = rotation.rotate(Vector3::right());
+        let back = rotation.rotate(Vector3::forward());
+        let velocity = right * direction.x + Vector3::up() * direction.y + back * direction.z;
+        self.position += (velocity * (self.speed * speed_factor * dt)).cast();
+    }
+
+    /// Takes over a camera at `position` looking along `rotation`. Roll is dropped.
+    pub fn set_view(&mut self, position: Vector3<f64>, rotation: Quaternion) {
+        let forward = rotation.rotate(Vector3::backward());
+        self.position = position;
+        self.yaw = f32::atan2(-forward.x, -forward.z);
+        self.pitch = forward.y.clamp(-1., 1.).asin().clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    pub fn get_position(&self) -> Vector3<f64> {
+        self.position
+    }
+
+    pub fn get_rotation(&self) -> Quaternion {
+        Quaternion::from_euler_angles(self.yaw, self.pitch, 0.)
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        place_camera(camera, self.position, self.get_rotation());
+    }
+}
+
+fn place_camera(camera: &mut Camera, position: Vector3<f64>, rotation: Quaternion) {
+    camera.transform.set_rotation(rotation.cast());
+    camera.transform.set_position(position);
+}
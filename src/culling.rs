@@ -0,0 +1,33 @@
+use std::fmt;
+use crate::camera::Camera;
+use crate::model::Model;
+
+/// How many models the last culling pass kept and dropped.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+impl fmt::Display for CullStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} drawn, {} culled", self.drawn, self.culled)
+    }
+}
+
+/// Indices of the models whose world bounds touch the camera's frustum. Conservative, so a few
+/// models just outside a corner of the view may still be drawn; empty models never are.
+pub fn cull(models: &[Model], camera: &Camera) -> (Vec<usize>, CullStats) {
+    let frustum = camera.get_frustum();
+    let origin = camera.get_position();
+    let visible: Vec<usize> = models.iter().enumerate()
+        .filter(|(_, model)| {
+            let bounds = model.get_world_bounds(origin);
+            !bounds.is_empty() && frustum.intersects_aabb(&bounds)
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    let stats = CullStats { drawn: visible.len(), culled: models.len() - visible.len() };
+    (visible, stats)
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+
+/// How models are rasterized by the viewer.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum DisplayMode {
+    #[default]
+    Solid,
+    Wireframe,
+    ShadedWireframe,
+    Points,
+    HiddenLine,
+}
+
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 5] = [
+        DisplayMode::Solid,
+        DisplayMode::Wireframe,
+        DisplayMode::ShadedWireframe,
+        DisplayMode::Points,
+        DisplayMode::HiddenLine,
+    ];
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|m| *m == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DisplayMode::Solid => "solid",
+            DisplayMode::Wireframe => "wireframe",
+            DisplayMode::ShadedWireframe => "wireframe over shaded",
+            DisplayMode::Points => "points",
+            DisplayMode::HiddenLine => "hidden line",
+        }
+    }
+
+    /// Whether the mode draws the shaded surface.
+    pub fn draws_surface(self) -> bool {
+        matches!(self, DisplayMode::Solid | DisplayMode::ShadedWireframe)
+    }
+
+    /// Whether the mode draws triangle edges.
+    pub fn draws_edges(self) -> bool {
+        matches!(self, DisplayMode::Wireframe | DisplayMode::ShadedWireframe | DisplayMode::HiddenLine)
+    }
+}
+
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Which triangle faces get discarded before rasterization.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum CullMode {
+    #[default]
+    Back,
+    Front,
+    None,
+}
+
+impl CullMode {
+    pub fn next(self) -> Self {
+        match self {
+            CullMode::Back => CullMode::Front,
+            CullMode::Front => CullMode::None,
+            CullMode::None => CullMode::Back,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CullMode::Back => "cull back",
+            CullMode::Front => "cull front",
+            CullMode::None => "no culling",
+        }
+    }
+
+    pub fn face(self) -> Option<wgpu::Face> {
+        match self {
+            CullMode::Back => Some(wgpu::Face::Back),
+            CullMode::Front => Some(wgpu::Face::Front),
+            CullMode::None => None,
+        }
+    }
+}
+
+impl fmt::Display for CullMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// How the surface of models is coloured in the solid display modes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum Shading {
+    /// Smooth Phong lighting with the model's material.
+    #[default]
+    Phong,
+    /// One colour per triangle from its face normal, showing the tessellation.
+    Flat,
+    /// World space normals as colours, for checking imported normals.
+    Normals,
+}
+
+impl Shading {
+    pub fn name(self) -> &'static str {
+        match self {
+            Shading::Phong => "phong",
+            Shading::Flat => "flat",
+            Shading::Normals => "normals",
+        }
+    }
+
+    /// Fragment entry point in phong.wgsl.
+    pub fn entry_point(self) -> &'static str {
+        match self {
+            Shading::Phong => "fs_main",
+            Shading::Flat => "fs_flat",
+            Shading::Normals => "fs_normals",
+        }
+    }
+}
+
+impl fmt::Display for Shading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
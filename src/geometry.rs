@@ -0,0 +1,350 @@
+use crate::matrix::Matrix4;
+use crate::vector::Vector3;
+
+/// Half-line from `origin` along the unit vector `direction`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+/// Where a ray meets a surface. `normal` is the outward unit normal at the hit point.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Hit {
+    pub distance: f32,
+    pub normal: Vector3<f32>,
+}
+
+/// Where a ray meets a triangle. `barycentric` holds the weights of the corners `a`, `b` and `c`,
+/// ready to interpolate vertex attributes; `normal` follows the winding, see [`Triangle::normal`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TriangleHit {
+    pub distance: f32,
+    pub barycentric: Vector3<f32>,
+    pub normal: Vector3<f32>,
+}
+
+/// The points `p` with `normal.dot(p) + distance == 0`, in front of it where that is positive.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+/// Axis-aligned box. The default box is empty and grows to fit whatever is added to it.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Sphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+/// Counter-clockwise triangle, matching the winding of the meshes.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Triangle {
+    pub a: Vector3<f32>,
+    pub b: Vector3<f32>,
+    pub c: Vector3<f32>,
+}
+
+/// The volume a camera sees, as six planes facing inwards.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Frustum {
+    pub planes: [Plane; 6],
+}
+
+impl Ray {
+    /// `direction` is normalized, so hit distances are in world units.
+    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Self {
+        Self {
+            origin,
+            direction: direction.normalized(),
+        }
+    }
+
+    /// The point `distance` along the ray.
+    pub fn at(&self, distance: f32) -> Vector3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// Möller–Trumbore. Both sides of the triangle are hit; degenerate triangles never are.
+    pub fn intersect_triangle(&self, triangle: &Triangle) -> Option<TriangleHit> {
+        let e1 = triangle.b - triangle.a;
+        let e2 = triangle.c - triangle.a;
+        let p = self.direction.cross(&e2);
+        let det = e1.dot(&p);
+        // relative to the edges, so tiny and huge triangles are treated alike
+        if det.abs() <= f32::EPSILON * e1.length() * e2.length() {
+            return None;
+        }
+
+        let inv_det = 1. / det;
+        let s = self.origin - triangle.a;
+        let u = s.dot(&p) * inv_det;
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+        let q = s.cross(&e1);
+        let v = self.direction.dot(&q) * inv_det;
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+        let distance = e2.dot(&q) * inv_det;
+        if distance < 0. {
+            return None;
+        }
+
+        Some(TriangleHit {
+            distance,
+            barycentric: Vector3::new(1. - u - v, u, v),
+            normal: e1.cross(&e2).normalized(),
+        })
+    }
+
+    /// Slab test. From inside the box the hit is where the ray leaves it.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<Hit> {
+        let (mut near, mut far) = (f32::NEG_INFINITY, f32::INFINITY);
+        let (mut near_axis, mut far_axis) = (0, 0);
+        for axis in 0..3 {
+            // a zero component divides to ±inf, which the comparisons below handle
+            let inv = 1. / self.direction[axis];
+            let t0 = (aabb.min[axis] - self.origin[axis]) * inv;
+            let t1 = (aabb.max[axis] - self.origin[axis]) * inv;
+            let (t0, t1) = if t0 <= t1 { (t0, t1) } else { (t1, t0) };
+            if t0 > near {
+                near = t0;
+                near_axis = axis;
+            }
+            if t1 < far {
+                far = t1;
+                far_axis = axis;
+            }
+        }
+        if near > far || far < 0. {
+            return None;
+        }
+
+        let (distance, axis, sign) = if near >= 0. { (near, near_axis, -1.) } else { (far, far_axis, 1.) };
+        let mut normal = Vector3::default();
+        normal[axis] = if self.direction[axis] > 0. { sign } else { -sign };
+        Some(Hit { distance, normal })
+    }
+
+    /// From inside the sphere the hit is where the ray leaves it.
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<Hit> {
+        let to_center = sphere.center - self.origin;
+        let along = to_center.dot(&self.direction);
+        let d2 = to_center.length_squared() - along * along;
+        let r2 = sphere.radius * sphere.radius;
+        if d2 > r2 {
+            return None;
+        }
+
+        let half_chord = (r2 - d2).sqrt();
+        let distance = if along - half_chord >= 0. { along - half_chord } else { along + half_chord };
+        if distance < 0. {
+            return None;
+        }
+        let normal = (self.at(distance) - sphere.center).normalized();
+        Some(Hit { distance, normal })
+    }
+
+    /// Hits the plane from either side; rays parallel to it miss.
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<Hit> {
+        let facing = plane.normal.dot(&self.direction);
+        if facing == 0. {
+            return None;
+        }
+        let distance = -plane.signed_distance(self.origin) / facing;
+        if distance < 0. {
+            return None;
+        }
+        Some(Hit { distance, normal: plane.normal })
+    }
+}
+
+impl Plane {
+    /// `normal` is normalized.
+    pub fn new(normal: Vector3<f32>, distance: f32) -> Self {
+        Self { normal, distance }.normalized()
+    }
+
+    pub fn from_point_normal(point: Vector3<f32>, normal: Vector3<f32>) -> Self {
+        let normal = normal.normalized();
+        Self { normal, distance: -normal.dot(&point) }
+    }
+
+    /// The plane through a counter-clockwise triangle, facing the side it is wound towards.
+    pub fn from_points(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> Self {
+        Self::from_point_normal(a, (b - a).cross(&(c - a)))
+    }
+
+    /// Scales the equation so the normal has unit length.
+    pub fn normalized(&self) -> Self {
+        let length = self.normal.length();
+        if length == 0. {
+            return *self;
+        }
+        Self { normal: self.normal / length, distance: self.distance / length }
+    }
+
+    /// Positive in front of the plane, negative behind it.
+    pub fn signed_distance(&self, point: Vector3<f32>) -> f32 {
+        self.normal.dot(&point) + self.distance
+    }
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// The smallest box around `points`, empty if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Vector3<f32>>) -> Self {
+        points.into_iter().fold(Self::default(), |aabb, p| aabb.extended(p))
+    }
+
+    /// Grown to also contain `point`.
+    pub fn extended(self, point: Vector3<f32>) -> Self {
+        Self { min: self.min.min(point), max: self.max.max(point) }
+    }
+
+    /// The smallest box containing both.
+    pub fn union(self, rhs: Self) -> Self {
+        Self { min: self.min.min(rhs.min), max: self.max.max(rhs.max) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    /// Zero for empty boxes, which keeps them out of surface area heuristics.
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.;
+        }
+        let s = self.size();
+        2. * (s.x * s.y + s.y * s.z + s.z * s.x)
+    }
+
+    pub fn contains(&self, point: Vector3<f32>) -> bool {
+        point.clamp(self.min, self.max) == point
+    }
+
+    /// The box around this one after transforming it with `m`, which stays tight for
+    /// translations and scales but grows under rotation.
+    pub fn transformed(&self, m: &Matrix4) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        let center = m.transform_point(self.center());
+        let half = self.size() * 0.5;
+        // each new half extent sums how far the old ones reach along that axis
+        let reach = |c: usize| (0..3).map(|r| m[(r, c)].abs() * half[r]).sum::<f32>();
+        let extent = Vector3::new(reach(0), reach(1), reach(2));
+        Self { min: center - extent, max: center + extent }
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self {
+            min: Vector3::splat(f32::INFINITY),
+            max: Vector3::splat(f32::NEG_INFINITY),
+        }
+    }
+}
+
+impl Sphere {
+    pub fn new(center: Vector3<f32>, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    pub fn contains(&self, point: Vector3<f32>) -> bool {
+        (point - self.center).length_squared() <= self.radius * self.radius
+    }
+}
+
+impl Triangle {
+    pub fn new(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> Self {
+        Self { a, b, c }
+    }
+
+    /// Unit normal on the side the triangle is wound counter-clockwise from; zero if degenerate.
+    pub fn normal(&self) -> Vector3<f32> {
+        (self.b - self.a).cross(&(self.c - self.a)).normalized()
+    }
+
+    pub fn area(&self) -> f32 {
+        (self.b - self.a).cross(&(self.c - self.a)).length() * 0.5
+    }
+
+    pub fn centroid(&self) -> Vector3<f32> {
+        (self.a + self.b + self.c) / 3.
+    }
+
+    /// The point with the given corner weights, e.g. from [`TriangleHit::barycentric`].
+    pub fn point_at(&self, barycentric: Vector3<f32>) -> Vector3<f32> {
+        self.a * barycentric.x + self.b * barycentric.y + self.c * barycentric.z
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        Aabb::from_points([self.a, self.b, self.c])
+    }
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix (`view * projection`, as vectors multiply
+    /// from the left), in the space the view matrix takes in. Depth runs from 0 to 1.
+    pub fn from_matrix(m: Matrix4) -> Self {
+        // clip coordinates are dot products with the columns; inside means -w <= x, y <= w, 0 <= z <= w
+        let column = |c: usize| (Vector3::new(m[(0, c)], m[(1, c)], m[(2, c)]), m[(3, c)]);
+        let (x, y, z, w) = (column(0), column(1), column(2), column(3));
+        Self {
+            planes: [
+                Plane::new(w.0 + x.0, w.1 + x.1),
+                Plane::new(w.0 - x.0, w.1 - x.1),
+                Plane::new(w.0 + y.0, w.1 + y.1),
+                Plane::new(w.0 - y.0, w.1 - y.1),
+                Plane::new(z.0, z.1),
+                Plane::new(w.0 - z.0, w.1 - z.1),
+            ],
+        }
+    }
+
+    pub fn contains_point(&self, point: Vector3<f32>) -> bool {
+        self.planes.iter().all(|p| p.signed_distance(point) >= 0.)
+    }
+
+    /// Conservative: false only when the box is entirely behind one of the planes, so some boxes
+    /// near the corners of the frustum pass without being visible.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|p| {
+            // the corner furthest along the normal
+            let corner = Vector3::new(
+                if p.normal.x >= 0. { aabb.max.x } else { aabb.min.x },
+                if p.normal.y >= 0. { aabb.max.y } else { aabb.min.y },
+                if p.normal.z >= 0. { aabb.max.z } else { aabb.min.z },
+            );
+            p.signed_distance(corner) >= 0.
+        })
+    }
+
+    /// Conservative in the same way as [`Frustum::intersects_aabb`].
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes.iter().all(|p| p.signed_distance(sphere.center) >= -sphere.radius)
+    }
+}
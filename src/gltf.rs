@@ -0,0 +1,542 @@
+//! glTF 2.0, the scene format of engines and the web: a JSON document describing nodes, meshes
+//! and materials, with the vertex data in binary buffers. Both share our conventions of Y up,
+//! metres and counter-clockwise triangles, only texture coordinates start from the top.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::Path;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::buffers::Vertex;
+use crate::matrix::Matrix4;
+use crate::mesh::{Attributes, Mesh, Submesh};
+use crate::quaternion::Quaternion;
+use crate::transform::Transform;
+use crate::vector::Vector3;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F534A;
+const CHUNK_BIN: u32 = 0x004E4942;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+const TRIANGLES: u32 = 4;
+const TRIANGLE_STRIP: u32 = 5;
+const TRIANGLE_FAN: u32 = 6;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.into())
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct Document {
+    scene: Option<usize>,
+    scenes: Vec<SceneDef>,
+    nodes: Vec<Node>,
+    meshes: Vec<MeshDef>,
+    materials: Vec<MaterialDef>,
+    accessors: Vec<Accessor>,
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SceneDef {
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Node {
+    mesh: Option<usize>,
+    children: Vec<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct MeshDef {
+    name: Option<String>,
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Deserialize)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    #[serde(default = "triangles")]
+    mode: u32,
+}
+
+fn triangles() -> u32 {
+    TRIANGLES
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct MaterialDef {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    uri: Option<String>,
+    byte_length: usize,
+}
+
+/// Reads a `.gltf` document. Buffers are embedded as base64 or files next to it in `dir`.
+pub fn read(data: &[u8], dir: &Path) -> io::Result<Mesh> {
+    read_document(data, None, dir)
+}
+
+/// Reads a binary `.glb` file: a JSON chunk followed by the first buffer.
+pub fn read_glb(data: &[u8], dir: &Path) -> io::Result<Mesh> {
+    let word = |at: usize| data.get(at..at + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+    if data.get(..4) != Some(GLB_MAGIC) {
+        return Err(invalid("not a GLB file: it does not start with glTF"));
+    }
+    if word(4) != Some(2) {
+        return Err(invalid("only glTF 2.0 is supported"));
+    }
+    let mut at = 12;
+    let (mut json, mut bin) = (None, None);
+    while let (Some(length), Some(kind)) = (word(at), word(at + 4)) {
+        let chunk = data.get(at + 8..at + 8 + length as usize).ok_or_else(|| invalid("a chunk runs past the end of the file"))?;
+        match kind {
+            CHUNK_JSON if json.is_none() => json = Some(chunk),
+            CHUNK_BIN if bin.is_none() => bin = Some(chunk),
+            _ => {}
+        }
+        at += 8 + length as usize;
+    }
+    read_document(json.ok_or_else(|| invalid("the file has no JSON chunk"))?, bin, dir)
+}
+
+fn read_document(json: &[u8], bin: Option<&[u8]>, dir: &Path) -> io::Result<Mesh> {
+    let document: Document = serde_json::from_slice(json).map_err(|e| invalid(e.to_string()))?;
+    let buffers = document.buffers.iter().enumerate().map(|(i, buffer)| {
+        let data = match (&buffer.uri, bin) {
+            (Some(uri), _) => load_uri(uri, dir)?,
+            (None, Some(bin)) if i == 0 => bin.to_vec(),
+            (None, _) => return Err(invalid(format!("buffer {} has no data", i))),
+        };
+        if data.len() < buffer.byte_length {
+            return Err(invalid(format!("buffer {} is shorter than its byteLength", i)));
+        }
+        Ok(data)
+    }).collect::<io::Result<Vec<Vec<u8>>>>()?;
+
+    // the nodes of the scene to show, or every mesh when there are no scenes
+    let mut stack: Vec<(usize, Matrix4)> = match document.scenes.get(document.scene.unwrap_or(0)) {
+        Some(scene) => scene.nodes.iter().rev().map(|&n| (n, Matrix4::identity())).collect(),
+        None => Vec::new(),
+    };
+    let mut placed: Vec<(usize, Matrix4)> = Vec::new();
+    let mut visited = vec![false; document.nodes.len()];
+    while let Some((n, parent)) = stack.pop() {
+        let node = document.nodes.get(n).ok_or_else(|| invalid(format!("node {} does not exist", n)))?;
+        if std::mem::replace(&mut visited[n], true) {
+            return Err(invalid(format!("node {} appears twice in the hierarchy", n)));
+        }
+        let world = node_matrix(node) * parent;
+        if let Some(mesh) = node.mesh {
+            placed.push((mesh, world));
+        }
+        stack.extend(node.children.iter().rev().map(|&c| (c, world)));
+    }
+    if document.scenes.is_empty() {
+        placed = (0..document.meshes.len()).map(|m| (m, Matrix4::identity())).collect();
+    }
+
+    let mut mesh = Mesh::default();
+    for (m, world) in placed {
+        let def = document.meshes.get(m).ok_or_else(|| invalid(format!("mesh {} does not exist", m)))?;
+        for primitive in &def.primitives {
+            let Some(mut part) = read_primitive(&document, &buffers, primitive)? else {
+                continue;
+            };
+            part.submeshes = vec![Submesh {
+                group: def.name.clone().unwrap_or_else(|| format!("mesh{}", m)),
+                material: primitive.material.map(|i| document.materials.get(i).and_then(|m| m.name.clone()).unwrap_or_else(|| format!("material{}", i))),
+                first_triangle: 0,
+                triangle_count: part.get_triangle_count(),
+            }];
+            part.transform(&world);
+            mesh.append(&part);
+        }
+    }
+    Ok(mesh)
+}
+
+fn node_matrix(node: &Node) -> Matrix4 {
+    if let Some(values) = node.matrix {
+        // column-major with vectors on the right is row-major with vectors on the left
+        let mut m = Matrix4::new();
+        for (i, value) in values.into_iter().enumerate() {
+            m[(i / 4, i % 4)] = value;
+        }
+        return m;
+    }
+    let mut transform = Transform::default();
+    if let Some([x, y, z]) = node.translation {
+        transform.set_position(Vector3::new(x, y, z));
+    }
+    if let Some([x, y, z, w]) = node.rotation {
+        transform.set_rotation(Quaternion::new(w, x, y, z));
+    }
+    if let Some([x, y, z]) = node.scale {
+        transform.set_scale(Vector3::new(x, y, z));
+    }
+    transform.as_matrix()
+}
+
+fn load_uri(uri: &str, dir: &Path) -> io::Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data.split_once(";base64,").ok_or_else(|| invalid("only base64 data URIs are supported"))?;
+        return base64_decode(encoded);
+    }
+    fs::read(dir.join(uri)).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", uri, e)))
+}
+
+/// The components of an accessor, `components` per element, each read from its bytes by `read`
+/// given the component type. An accessor without a buffer view is all default values.
+fn read_components<T: Clone + Default>(document: &Document, buffers: &[Vec<u8>], index: usize, read: impl Fn(u32, &[u8]) -> T) -> io::Result<(Vec<T>, usize)> {
+    let accessor = document.accessors.get(index).ok_or_else(|| invalid(format!("accessor {} does not exist", index)))?;
+    if accessor.sparse.is_some() {
+        return Err(invalid("sparse accessors are not supported"));
+    }
+    let components = match accessor.kind.as_str() {
+        "SCALAR" => 1,
+        "VEC2" => 2,
+        "VEC3" => 3,
+        "VEC4" => 4,
+        kind => return Err(invalid(format!("accessors of type {} are not supported", kind))),
+    };
+    let size = match accessor.component_type {
+        5120 | 5121 => 1,
+        5122 | 5123 => 2,
+        UNSIGNED_INT | FLOAT => 4,
+        other => return Err(invalid(format!("unknown component type {}", other))),
+    };
+    let Some(v) = accessor.buffer_view else {
+        return Ok((vec![T::default(); accessor.count * components], components));
+    };
+    let view = document.buffer_views.get(v).ok_or_else(|| invalid(format!("buffer view {} does not exist", v)))?;
+    let buffer = buffers.get(view.buffer).ok_or_else(|| invalid(format!("buffer {} does not exist", view.buffer)))?;
+    let data = buffer.get(view.byte_offset..view.byte_offset + view.byte_length)
+        .ok_or_else(|| invalid(format!("buffer view {} runs past its buffer", v)))?;
+    let stride = view.byte_stride.unwrap_or(size * components);
+
+    let mut values = Vec::with_capacity(accessor.count * components);
+    for e in 0..accessor.count {
+        for c in 0..components {
+            let at = accessor.byte_offset + e * stride + c * size;
+            let b = data.get(at..at + size).ok_or_else(|| invalid(format!("accessor {} runs past its buffer view", index)))?;
+            values.push(read(accessor.component_type, b));
+        }
+    }
+    Ok((values, components))
+}
+
+/// The values of an accessor as floats, `components` per element, normalized integers mapped to
+/// 0 to 1 or -1 to 1.
+fn read_accessor(document: &Document, buffers: &[Vec<u8>], index: usize) -> io::Result<(Vec<f32>, usize)> {
+    let (values, components) = read_components(document, buffers, index, |kind, b| match kind {
+        5120 => b[0] as i8 as f32,
+        5121 => b[0] as f32,
+        5122 => i16::from_le_bytes([b[0], b[1]]) as f32,
+        5123 => u16::from_le_bytes([b[0], b[1]]) as f32,
+        UNSIGNED_INT => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+        _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+    })?;
+    let accessor = &document.accessors[index];
+    if !accessor.normalized {
+        return Ok((values, components));
+    }
+    let scale = match accessor.component_type {
+        5120 => 127.,
+        5121 => 255.,
+        5122 => 32767.,
+        5123 => 65535.,
+        _ => 1.,
+    };
+    Ok((values.into_iter().map(|value| (value / scale).max(-1.)).collect(), components))
+}
+
+/// The values of an index accessor, which holds unsigned integers one per element, kept as
+/// integers so large indices are not rounded.
+fn read_indices(document: &Document, buffers: &[Vec<u8>], index: usize) -> io::Result<Vec<u32>> {
+    let (indices, components) = read_components(document, buffers, index, |kind, b| match kind {
+        5121 => b[0] as u32,
+        5123 => u16::from_le_bytes([b[0], b[1]]) as u32,
+        _ => u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+    })?;
+    if components != 1 || !matches!(document.accessors[index].component_type, 5121 | 5123 | UNSIGNED_INT) {
+        return Err(invalid(format!("index accessor {} does not hold unsigned integers", index)));
+    }
+    Ok(indices)
+}
+
+/// The values of attribute `name` of a primitive with `count` vertices, checked to have as many
+/// elements and one of the numbers of components `allowed`.
+fn read_attribute(document: &Document, buffers: &[Vec<u8>], primitive: &Primitive, name: &str, count: usize, allowed: &[usize]) -> io::Result<Option<(Vec<f32>, usize)>> {
+    let Some(&index) = primitive.attributes.get(name) else {
+        return Ok(None);
+    };
+    let (values, components) = read_accessor(document, buffers, index)?;
+    if !allowed.contains(&components) {
+        return Err(invalid(format!("{} has {} components per vertex", name, components)));
+    }
+    if values.len() != count * components {
+        return Err(invalid(format!("{} has {} values for {} vertices", name, values.len() / components, count)));
+    }
+    Ok(Some((values, components)))
+}
+
+/// The triangles of a primitive, or None for points and lines.
+fn read_primitive(document: &Document, buffers: &[Vec<u8>], primitive: &Primitive) -> io::Result<Option<Mesh>> {
+    if !matches!(primitive.mode, TRIANGLES | TRIANGLE_STRIP | TRIANGLE_FAN) {
+        return Ok(None);
+    }
+    let &position = primitive.attributes.get("POSITION").ok_or_else(|| invalid("a primitive has no positions"))?;
+    let count = document.accessors.get(position).map_or(0, |accessor| accessor.count);
+    let attribute = |name: &str, allowed: &[usize]| read_attribute(document, buffers, primitive, name, count, allowed);
+    let (positions, _) = attribute("POSITION", &[3])?.ok_or_else(|| invalid("a primitive has no positions"))?;
+    let normals = attribute("NORMAL", &[3])?;
+    let uvs = attribute("TEXCOORD_0", &[2])?;
+    let colors = attribute("COLOR_0", &[3, 4])?;
+
+    let mut mesh = Mesh::default();
+    for i in 0..count {
+        let normal = normals.as_ref().map_or([0.; 3], |(n, _)| [n[3 * i], n[3 * i + 1], n[3 * i + 2]]);
+        let uv = uvs.as_ref().map_or([0.; 2], |(t, _)| [t[2 * i], 1. - t[2 * i + 1]]);
+        let color = colors.as_ref().map_or([1., 0., 0., 1.], |(c, n)| {
+            let c = &c[n * i..n * i + n];
+            [c[0], c[1], c[2], if *n == 4 { c[3] } else { 1. }]
+        });
+        mesh.vb.add_vertex(Vertex::new([positions[3 * i], positions[3 * i + 1], positions[3 * i + 2]], normal, uv, color));
+    }
+
+    let indices: Vec<u32> = match primitive.indices {
+        Some(i) => read_indices(document, buffers, i)?,
+        None => (0..count as u32).collect(),
+    };
+    if let Some(&i) = indices.iter().find(|&&i| i as usize >= count) {
+        return Err(invalid(format!("vertex {} is out of range", i)));
+    }
+    let corners: Vec<[u32; 3]> = match primitive.mode {
+        TRIANGLE_STRIP => (2..indices.len()).map(|i| if i % 2 == 0 {
+            [indices[i - 2], indices[i - 1], indices[i]]
+        } else {
+            [indices[i - 1], indices[i - 2], indices[i]]
+        }).collect(),
+        TRIANGLE_FAN => (2..indices.len()).map(|i| [indices[0], indices[i - 1], indices[i]]).collect(),
+        _ => indices.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+    };
+    for triangle in corners {
+        for i in triangle {
+            mesh.ib.add_index(i);
+        }
+    }
+
+    if normals.is_none() {
+        mesh.recompute_normals(false);
+    }
+    let has_faces = mesh.get_triangle_count() > 0;
+    mesh.attributes = Attributes {
+        normals: has_faces && normals.is_some(),
+        uvs: has_faces && uvs.is_some(),
+        colors: has_faces && colors.is_some(),
+    };
+    Ok(Some(mesh))
+}
+
+/// Writes a `.gltf` document with the buffer embedded as base64, so it is a single file.
+pub fn write(mesh: &Mesh, out: &mut impl Write) -> io::Result<()> {
+    let (mut document, bin) = build(mesh);
+    if !bin.is_empty() {
+        document["buffers"] = json!([{
+            "byteLength": bin.len(),
+            "uri": format!("data:application/octet-stream;base64,{}", base64_encode(&bin)),
+        }]);
+    }
+    serde_json::to_writer_pretty(&mut *out, &document).map_err(|e| invalid(e.to_string()))?;
+    writeln!(out)
+}
+
+/// Writes a binary `.glb` file.
+pub fn write_glb(mesh: &Mesh, out: &mut impl Write) -> io::Result<()> {
+    let (mut document, mut bin) = build(mesh);
+    if !bin.is_empty() {
+        document["buffers"] = json!([{ "byteLength": bin.len() }]);
+    }
+    let mut json = serde_json::to_vec(&document).map_err(|e| invalid(e.to_string()))?;
+    // chunks are aligned to four bytes, JSON padded with spaces and binary with zeros
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+
+    let length = 12 + 8 + json.len() + if bin.is_empty() { 0 } else { 8 + bin.len() };
+    let length = u32::try_from(length).map_err(|_| invalid("the mesh is too large for GLB"))?;
+    out.write_all(GLB_MAGIC)?;
+    out.write_all(&2u32.to_le_bytes())?;
+    out.write_all(&length.to_le_bytes())?;
+    for (kind, chunk) in [(CHUNK_JSON, &json), (CHUNK_BIN, &bin)] {
+        if !chunk.is_empty() {
+            out.write_all(&(chunk.len() as u32).to_le_bytes())?;
+            out.write_all(&kind.to_le_bytes())?;
+            out.write_all(chunk)?;
+        }
+    }
+    Ok(())
+}
+
+/// The document without its buffer, and the buffer. Each group becomes a node with a mesh and
+/// each submesh a primitive, all sharing one set of vertex attributes.
+fn build(mesh: &Mesh) -> (Value, Vec<u8>) {
+    let mut document = json!({
+        "asset": { "version": "2.0", "generator": "triangle" },
+        "scene": 0,
+        "scenes": [{ "nodes": [] }],
+    });
+    let vertex_count = mesh.vb.get_vertex_count();
+    if mesh.get_triangle_count() == 0 {
+        return (document, Vec::new());
+    }
+
+    let mut bin: Vec<u8> = Vec::new();
+    let mut views: Vec<Value> = Vec::new();
+    let mut accessors: Vec<Value> = Vec::new();
+    let vertices: Vec<Vertex> = (0..vertex_count).map(|i| mesh.vb.get_vertex(i)).collect();
+    let mut add_attribute = |name: &str, kind: &str, values: Vec<f32>, attributes: &mut serde_json::Map<String, Value>| {
+        let offset = bin.len();
+        bin.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+        views.push(json!({ "buffer": 0, "byteOffset": offset, "byteLength": bin.len() - offset, "target": ARRAY_BUFFER }));
+        let mut accessor = json!({ "bufferView": views.len() - 1, "componentType": FLOAT, "count": vertex_count, "type": kind });
+        if name == "POSITION" {
+            // positions must give their bounds
+            let bounds = crate::geometry::Aabb::from_points(vertices.iter().map(Vertex::get_position));
+            accessor["min"] = json!([bounds.min.x, bounds.min.y, bounds.min.z]);
+            accessor["max"] = json!([bounds.max.x, bounds.max.y, bounds.max.z]);
+        }
+        accessors.push(accessor);
+        attributes.insert(name.to_string(), json!(accessors.len() - 1));
+    };
+
+    let mut attributes = serde_json::Map::new();
+    let positions = vertices.iter().flat_map(|v| { let p = v.get_position(); [p.x, p.y, p.z] }).collect();
+    add_attribute("POSITION", "VEC3", positions, &mut attributes);
+    let normals = vertices.iter().flat_map(|v| { let n = v.get_normal(); [n.x, n.y, n.z] }).collect();
+    add_attribute("NORMAL", "VEC3", normals, &mut attributes);
+    if mesh.attributes.uvs {
+        let uvs = vertices.iter().flat_map(|v| { let [u, t] = v.get_uv(); [u, 1. - t] }).collect();
+        add_attribute("TEXCOORD_0", "VEC2", uvs, &mut attributes);
+    }
+    if mesh.attributes.colors {
+        let colors = vertices.iter().flat_map(Vertex::get_color).collect();
+        add_attribute("COLOR_0", "VEC4", colors, &mut attributes);
+    }
+
+    let indices_offset = bin.len();
+    bin.extend(mesh.ib.get_indices().iter().flat_map(|i| i.to_le_bytes()));
+    views.push(json!({ "buffer": 0, "byteOffset": indices_offset, "byteLength": bin.len() - indices_offset, "target": ELEMENT_ARRAY_BUFFER }));
+    let indices_view = views.len() - 1;
+
+    let mut materials: Vec<String> = Vec::new();
+    let mut meshes: Vec<Value> = Vec::new();
+    for submesh in mesh.get_submeshes().into_iter().filter(|s| s.triangle_count > 0) {
+        accessors.push(json!({
+            "bufferView": indices_view,
+            "byteOffset": 12 * submesh.first_triangle,
+            "componentType": UNSIGNED_INT,
+            "count": 3 * submesh.triangle_count,
+            "type": "SCALAR",
+        }));
+        let mut primitive = json!({ "attributes": attributes, "indices": accessors.len() - 1, "mode": TRIANGLES });
+        if let Some(name) = &submesh.material {
+            let index = materials.iter().position(|m| m == name).unwrap_or_else(|| {
+                materials.push(name.clone());
+                materials.len() - 1
+            });
+            primitive["material"] = json!(index);
+        }
+        match meshes.last_mut() {
+            Some(last) if last["name"] == submesh.group.as_str() => last["primitives"].as_array_mut().unwrap().push(primitive),
+            _ => meshes.push(json!({ "name": submesh.group, "primitives": [primitive] })),
+        }
+    }
+
+    let nodes: Vec<Value> = meshes.iter().enumerate().map(|(i, m)| json!({ "name": m["name"], "mesh": i })).collect();
+    document["scenes"][0]["nodes"] = json!((0..nodes.len()).collect::<Vec<_>>());
+    document["nodes"] = json!(nodes);
+    document["meshes"] = json!(meshes);
+    if !materials.is_empty() {
+        document["materials"] = json!(materials.iter().map(|name| json!({ "name": name })).collect::<Vec<_>>());
+    }
+    document["accessors"] = json!(accessors);
+    document["bufferViews"] = json!(views);
+    (document, bin)
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            out.push(if i <= chunk.len() { BASE64[(bits >> (18 - 6 * i) & 63) as usize] as char } else { '=' });
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.bytes().filter(|&c| c != b'=' && !c.is_ascii_whitespace()) {
+        let value = BASE64.iter().position(|&b| b == c).ok_or_else(|| invalid("the data URI is not valid base64"))?;
+        bits = bits << 6 | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Ok(out)
+}
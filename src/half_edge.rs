@@ -0,0 +1,532 @@
+//! A half-edge view of a mesh's connectivity, for walking around vertices and faces and for
+//! editing edges.
+//!
+//! Vertices here are positions, as in [`Mesh::get_position_ids`], so triangles that do not share
+//! vertices in the vertex buffer are still connected. Each half-edge keeps the vertex buffer
+//! vertex of the corner it starts at, so normals, texture coordinates and colours, including
+//! seams where they change across an edge, come back out of [`HalfEdgeMesh::to_mesh`].
+
+use std::collections::HashMap;
+use crate::buffers::{IndexBuffer, Vertex, VertexBuffer};
+use crate::mesh::{Attributes, Mesh, Submesh};
+use crate::vector::Vector3;
+
+#[derive(Copy, Clone, Debug)]
+struct HalfEdge {
+    /// The vertex it starts at.
+    origin: usize,
+    /// The half-edge going the other way along the same edge.
+    twin: usize,
+    next: usize,
+    prev: usize,
+    /// None on a boundary, where the half-edges run around the hole.
+    face: Option<usize>,
+    /// The corner's vertex in the vertex buffer.
+    corner: usize,
+    removed: bool,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Node {
+    position: Vector3<f32>,
+    /// A half-edge starting here.
+    half_edge: usize,
+    removed: bool,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Face {
+    half_edge: usize,
+    /// Index into the submeshes of the mesh it was built from.
+    submesh: usize,
+    removed: bool,
+}
+
+/// Triangles joined by half-edges: each edge between two faces is a pair of half-edges going
+/// opposite ways, one per face, and edges with a face on one side only have a half-edge without
+/// a face on the other. Vertices, half-edges and faces are numbered; edits leave gaps in the
+/// numbering, which [`HalfEdgeMesh::to_mesh`] closes.
+///
+/// Edges shared by more than two triangles and triangles wound against their neighbours cannot
+/// be joined and are treated as boundaries.
+pub struct HalfEdgeMesh {
+    vertices: Vec<Node>,
+    half_edges: Vec<HalfEdge>,
+    faces: Vec<Face>,
+    corners: Vec<Vertex>,
+    submeshes: Vec<Submesh>,
+    /// Whether the mesh had submeshes, rather than the one made up by [`Mesh::get_submeshes`].
+    grouped: bool,
+    attributes: Attributes,
+}
+
+impl HalfEdgeMesh {
+    /// Joins up the triangles of `mesh`. Triangles with two corners at the same position are
+    /// left out.
+    pub fn new(mesh: &Mesh) -> Self {
+        let (ids, count) = mesh.get_position_ids();
+        let indices = mesh.ib.get_indices();
+        let mut vertices = vec![Node { position: Vector3::default(), half_edge: usize::MAX, removed: true }; count];
+        let mut half_edges: Vec<HalfEdge> = Vec::new();
+        let mut faces: Vec<Face> = Vec::new();
+
+        let submeshes = mesh.get_submeshes();
+        for (s, submesh) in submeshes.iter().enumerate() {
+            for t in submesh.first_triangle..submesh.first_triangle + submesh.triangle_count {
+                let corner = [3 * t, 3 * t + 1, 3 * t + 2];
+                if ids[corner[0]] == ids[corner[1]] || ids[corner[1]] == ids[corner[2]] || ids[corner[2]] == ids[corner[0]] {
+                    continue;
+                }
+                let first = half_edges.len();
+                for (c, &i) in corner.iter().enumerate() {
+                    let origin = ids[i] as usize;
+                    vertices[origin] = Node {
+                        position: mesh.vb.get_vertex(indices[i] as usize).get_position(),
+                        half_edge: first + c,
+                        removed: false,
+                    };
+                    half_edges.push(HalfEdge {
+                        origin,
+                        twin: usize::MAX,
+                        next: first + (c + 1) % 3,
+                        prev: first + (c + 2) % 3,
+                        face: Some(faces.len()),
+                        corner: indices[i] as usize,
+                        removed: false,
+                    });
+                }
+                faces.push(Face { half_edge: first, submesh: s, removed: false });
+            }
+        }
+
+        // pair each half-edge with the first free one going the other way
+        let mut unpaired: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for h in 0..half_edges.len() {
+            let (a, b) = (half_edges[h].origin, half_edges[half_edges[h].next].origin);
+            match unpaired.get_mut(&(b, a)).and_then(Vec::pop) {
+                Some(twin) => {
+                    half_edges[h].twin = twin;
+                    half_edges[twin].twin = h;
+                }
+                None => unpaired.entry((a, b)).or_default().push(h),
+            }
+        }
+
+        // the rest get a boundary half-edge, linked to the boundary half-edge starting where
+        // it ends
+        let mut boundary: HashMap<usize, Vec<usize>> = HashMap::new();
+        for h in 0..half_edges.len() {
+            if half_edges[h].twin == usize::MAX {
+                let b = half_edges[half_edges[h].next].origin;
+                let twin = half_edges.len();
+                half_edges[h].twin = twin;
+                half_edges.push(HalfEdge { origin: b, twin: h, next: usize::MAX, prev: usize::MAX, face: None, corner: half_edges[h].corner, removed: false });
+                boundary.entry(b).or_default().push(twin);
+            }
+        }
+        for h in 0..half_edges.len() {
+            if half_edges[h].face.is_none() {
+                let end = half_edges[half_edges[h].twin].origin;
+                if let Some(next) = boundary.get_mut(&end).and_then(Vec::pop) {
+                    half_edges[h].next = next;
+                    half_edges[next].prev = h;
+                }
+            }
+        }
+
+        Self {
+            vertices,
+            half_edges,
+            faces,
+            corners: (0..mesh.vb.get_vertex_count()).map(|i| mesh.vb.get_vertex(i)).collect(),
+            submeshes,
+            grouped: !mesh.submeshes.is_empty(),
+            attributes: mesh.attributes,
+        }
+    }
+
+    /// Back to triangles, in submesh order, with a vertex per distinct corner. Vertices take
+    /// their positions from the half-edge vertices, so moves and collapses show.
+    pub fn to_mesh(&self) -> Mesh {
+        let mut vb = VertexBuffer::new();
+        let mut ib = IndexBuffer::new();
+        let mut remap: HashMap<(usize, usize), u32> = HashMap::new();
+        let mut submeshes: Vec<Submesh> = Vec::new();
+        for (s, submesh) in self.submeshes.iter().enumerate() {
+            let first_triangle = ib.get_indices().len() / 3;
+            for f in self.get_faces().filter(|&f| self.faces[f].submesh == s) {
+                for h in self.get_face_half_edges(f) {
+                    let HalfEdge { origin, corner, .. } = self.half_edges[h];
+                    let index = *remap.entry((corner, origin)).or_insert_with(|| {
+                        let mut vertex = self.corners[corner];
+                        vertex.set_position(self.vertices[origin].position);
+                        vb.add_vertex(vertex);
+                        vb.get_vertex_count() as u32 - 1
+                    });
+                    ib.add_index(index);
+                }
+            }
+            let triangle_count = ib.get_indices().len() / 3 - first_triangle;
+            if triangle_count > 0 {
+                submeshes.push(Submesh { first_triangle, triangle_count, ..submesh.clone() });
+            }
+        }
+        let mut mesh = Mesh::new(vb, ib);
+        mesh.attributes = self.attributes;
+        if self.grouped {
+            mesh.submeshes = submeshes;
+        }
+        mesh
+    }
+
+    /// The vertex and index buffers of [`HalfEdgeMesh::to_mesh`].
+    pub fn to_buffers(&self) -> (VertexBuffer, IndexBuffer) {
+        let mesh = self.to_mesh();
+        (mesh.vb, mesh.ib)
+    }
+
+    /// Vertices that have not been removed.
+    pub fn get_vertices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.vertices.len()).filter(|&v| !self.vertices[v].removed)
+    }
+
+    /// Faces that have not been removed.
+    pub fn get_faces(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.faces.len()).filter(|&f| !self.faces[f].removed)
+    }
+
+    /// Half-edges that have not been removed, boundary ones included.
+    pub fn get_half_edges(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.half_edges.len()).filter(|&h| !self.half_edges[h].removed)
+    }
+
+    pub fn get_vertex_count(&self) -> usize {
+        self.get_vertices().count()
+    }
+
+    pub fn get_face_count(&self) -> usize {
+        self.get_faces().count()
+    }
+
+    /// Edges, each counted once for its pair of half-edges.
+    pub fn get_edge_count(&self) -> usize {
+        self.get_half_edges().count() / 2
+    }
+
+    pub fn get_position(&self, v: usize) -> Vector3<f32> {
+        self.vertices[v].position
+    }
+
+    pub fn set_position(&mut self, v: usize, position: Vector3<f32>) {
+        self.vertices[v].position = position;
+    }
+
+    pub fn get_origin(&self, h: usize) -> usize {
+        self.half_edges[h].origin
+    }
+
+    pub fn get_target(&self, h: usize) -> usize {
+        self.half_edges[self.half_edges[h].twin].origin
+    }
+
+    pub fn get_twin(&self, h: usize) -> usize {
+        self.half_edges[h].twin
+    }
+
+    pub fn get_next(&self, h: usize) -> usize {
+        self.half_edges[h].next
+    }
+
+    pub fn get_prev(&self, h: usize) -> usize {
+        self.half_edges[h].prev
+    }
+
+    /// The face on the left of `h`, None on a boundary.
+    pub fn get_face(&self, h: usize) -> Option<usize> {
+        self.half_edges[h].face
+    }
+
+    /// The vertex buffer vertex of the corner `h` starts at, with the corner's normal, texture
+    /// coordinates and colour.
+    pub fn get_corner(&self, h: usize) -> Vertex {
+        self.corners[self.half_edges[h].corner]
+    }
+
+    pub fn is_boundary(&self, h: usize) -> bool {
+        self.half_edges[h].face.is_none()
+    }
+
+    /// Whether the edge of `h` has a face on one side only.
+    pub fn is_boundary_edge(&self, h: usize) -> bool {
+        self.is_boundary(h) || self.is_boundary(self.get_twin(h))
+    }
+
+    pub fn is_boundary_vertex(&self, v: usize) -> bool {
+        self.get_outgoing(v).iter().any(|&h| self.is_boundary(h))
+    }
+
+    /// Whether the corners around `v` differ in texture coordinates or colour, as along the
+    /// edge of a texture. Normals do not count, as they follow the shape.
+    pub fn is_seam_vertex(&self, v: usize) -> bool {
+        let mut corners = self.get_outgoing(v).into_iter().filter(|&h| !self.is_boundary(h)).map(|h| {
+            let corner = self.get_corner(h);
+            (corner.get_uv().map(f32::to_bits), corner.get_color().map(f32::to_bits))
+        });
+        let first = corners.next();
+        !corners.all(|c| Some(c) == first)
+    }
+
+    /// Whether the corners either side of the edge of `h` differ in texture coordinates or
+    /// colour at either end.
+    pub fn is_seam_edge(&self, h: usize) -> bool {
+        let t = self.get_twin(h);
+        if self.is_boundary_edge(h) {
+            return false;
+        }
+        let differ = |a: usize, b: usize| {
+            let (a, b) = (self.get_corner(a), self.get_corner(b));
+            a.get_uv() != b.get_uv() || a.get_color() != b.get_color()
+        };
+        differ(h, self.get_next(t)) || differ(t, self.get_next(h))
+    }
+
+    /// The three half-edges around face `f`, in winding order.
+    pub fn get_face_half_edges(&self, f: usize) -> [usize; 3] {
+        let h = self.faces[f].half_edge;
+        [h, self.get_next(h), self.get_prev(h)]
+    }
+
+    pub fn get_face_vertices(&self, f: usize) -> [usize; 3] {
+        self.get_face_half_edges(f).map(|h| self.get_origin(h))
+    }
+
+    /// Faces across the edges of `f`.
+    pub fn get_face_neighbours(&self, f: usize) -> Vec<usize> {
+        self.get_face_half_edges(f).iter().filter_map(|&h| self.get_face(self.get_twin(h))).collect()
+    }
+
+    /// Half-edges starting at `v`, turning the opposite way to the winding. Where the triangles
+    /// around `v` form more than one fan, only the fan of one of them is found.
+    pub fn get_outgoing(&self, v: usize) -> Vec<usize> {
+        let start = self.vertices[v].half_edge;
+        let mut outgoing = vec![start];
+        let mut h = self.get_next(self.get_twin(start));
+        while h != start && h != usize::MAX && outgoing.len() < self.half_edges.len() {
+            outgoing.push(h);
+            h = self.get_next(self.get_twin(h));
+        }
+        outgoing
+    }
+
+    /// Vertices joined to `v` by an edge.
+    pub fn get_vertex_neighbours(&self, v: usize) -> Vec<usize> {
+        self.get_outgoing(v).iter().map(|&h| self.get_target(h)).collect()
+    }
+
+    /// Faces with a corner at `v`.
+    pub fn get_vertex_faces(&self, v: usize) -> Vec<usize> {
+        self.get_outgoing(v).iter().filter_map(|&h| self.get_face(h)).collect()
+    }
+
+    /// The half-edge from `a` to `b`, if they are joined.
+    pub fn find_half_edge(&self, a: usize, b: usize) -> Option<usize> {
+        self.get_outgoing(a).into_iter().find(|&h| self.get_target(h) == b)
+    }
+
+    /// The vertices around each hole or open border, in the order of the boundary half-edges,
+    /// which go against the winding of the faces beside them.
+    pub fn get_boundary_loops(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.half_edges.len()];
+        let mut loops = Vec::new();
+        for start in self.get_half_edges().filter(|&h| self.is_boundary(h)) {
+            if visited[start] {
+                continue;
+            }
+            let mut vertices = Vec::new();
+            let mut h = start;
+            while h != usize::MAX && !visited[h] {
+                visited[h] = true;
+                vertices.push(self.get_origin(h));
+                h = self.get_next(h);
+            }
+            loops.push(vertices);
+        }
+        loops
+    }
+
+    /// Turns the edge of `h` between its two faces to join the other two corners instead.
+    /// Returns false, changing nothing, on a boundary or where those corners are already joined.
+    pub fn flip_edge(&mut self, h: usize) -> bool {
+        let t = self.get_twin(h);
+        let (Some(f), Some(g)) = (self.get_face(h), self.get_face(t)) else {
+            return false;
+        };
+        let (hn, hp, tn, tp) = (self.get_next(h), self.get_prev(h), self.get_next(t), self.get_prev(t));
+        let (a, b) = (self.get_origin(hp), self.get_origin(tp));
+        if a == b || self.find_half_edge(a, b).is_some() {
+            return false;
+        }
+
+        // u v a and v u b become b a u and a b v
+        self.half_edges[h].origin = b;
+        self.half_edges[h].corner = self.half_edges[tp].corner;
+        self.half_edges[t].origin = a;
+        self.half_edges[t].corner = self.half_edges[hp].corner;
+        self.set_triangle(f, [h, hp, tn]);
+        self.set_triangle(g, [t, tp, hn]);
+        true
+    }
+
+    /// Splits the edge of `h` at its middle, splitting the faces beside it in two. The corners at
+    /// the new vertex get the attributes halfway along the edge in each face. Returns the new
+    /// vertex.
+    pub fn split_edge(&mut self, h: usize) -> usize {
+        let t = self.get_twin(h);
+        let (u, v) = (self.get_origin(h), self.get_origin(t));
+        let m = self.vertices.len();
+        let (h2, t2) = (self.half_edges.len(), self.half_edges.len() + 1);
+        self.vertices.push(Node { position: (self.get_position(u) + self.get_position(v)) * 0.5, half_edge: h2, removed: false });
+
+        // h becomes u to m and t v to m, with new half-edges from m to v and m to u
+        self.half_edges.push(HalfEdge { origin: m, twin: t, ..self.half_edges[h] });
+        self.half_edges.push(HalfEdge { origin: m, twin: h, ..self.half_edges[t] });
+        self.half_edges[h].twin = t2;
+        self.half_edges[t].twin = h2;
+        self.vertices[u].half_edge = h;
+        self.vertices[v].half_edge = t;
+
+        let mut made = Vec::new();
+        for (first, second) in [(h, h2), (t, t2)] {
+            let next = self.half_edges[first].next;
+            match self.half_edges[first].face {
+                Some(f) => {
+                    // first, next, prev around a becomes first, m to a, prev and second, next,
+                    // a to m
+                    let prev = self.half_edges[first].prev;
+                    let a = self.get_origin(prev);
+                    let corner = self.corners.len();
+                    self.corners.push(lerp(&self.corners[self.half_edges[first].corner], &self.corners[self.half_edges[next].corner]));
+                    self.half_edges[second].corner = corner;
+                    let (out, back) = (self.half_edges.len(), self.half_edges.len() + 1);
+                    let edge = |origin, twin, corner| HalfEdge { origin, twin, next: 0, prev: 0, face: None, corner, removed: false };
+                    self.half_edges.push(edge(m, back, corner));
+                    self.half_edges.push(edge(a, out, self.half_edges[prev].corner));
+                    let g = self.faces.len();
+                    self.faces.push(Face { half_edge: second, ..self.faces[f] });
+                    self.set_triangle(f, [first, out, prev]);
+                    self.set_triangle(g, [second, next, back]);
+                    made.push(corner);
+                }
+                None => {
+                    self.half_edges[first].next = second;
+                    self.half_edges[second].prev = first;
+                    self.half_edges[next].prev = second;
+                }
+            }
+        }
+        // share the corner between the two sides when it came out the same
+        if let [a, b] = made[..]
+            && self.corners[a].as_vec() == self.corners[b].as_vec() {
+            self.half_edges.iter_mut().filter(|e| e.corner == b && e.origin == m).for_each(|e| e.corner = a);
+        }
+        m
+    }
+
+    /// Joins the start of `h` onto its end, removing the start vertex and the faces beside the
+    /// edge. Unless the start is on a seam, the moved corners take the end's corner in the face
+    /// beside `h`, so texture coordinates stay put; on a seam they keep their own. Returns false,
+    /// changing nothing, where the result would not be manifold: the two ends share neighbours
+    /// other than the corners facing the edge, both lie on a boundary the edge is not part of, or
+    /// a face or edge would be left hanging.
+    pub fn collapse_edge(&mut self, h: usize) -> bool {
+        let t = self.get_twin(h);
+        let (u, v) = (self.get_origin(h), self.get_origin(t));
+        let mut facing = Vec::new();
+        for side in [h, t] {
+            if self.get_face(side).is_some() {
+                let (next, prev) = (self.get_next(side), self.get_prev(side));
+                // removing the face would leave its other two edges without faces
+                if self.is_boundary(self.get_twin(next)) && self.is_boundary(self.get_twin(prev)) {
+                    return false;
+                }
+                let a = self.get_origin(prev);
+                // a would be left with two neighbours, joined to both by doubled faces
+                if !self.is_boundary_vertex(a) && self.get_outgoing(a).len() <= 3 {
+                    return false;
+                }
+                facing.push(a);
+            }
+        }
+        let u_neighbours = self.get_vertex_neighbours(u);
+        let shared = self.get_vertex_neighbours(v).into_iter().filter(|n| u_neighbours.contains(n)).count();
+        if shared != facing.len() || (!self.is_boundary_edge(h) && self.is_boundary_vertex(u) && self.is_boundary_vertex(v)) {
+            return false;
+        }
+
+        let outgoing = self.get_outgoing(u);
+        let replacement = (!self.is_seam_vertex(u)).then(|| {
+            let end = if self.is_boundary(h) { t } else { self.get_next(h) };
+            self.half_edges[end].corner
+        });
+
+        for side in [h, t] {
+            let (next, prev) = (self.get_next(side), self.get_prev(side));
+            match self.get_face(side) {
+                Some(f) => {
+                    // the face goes and the edges either side of it become one
+                    let (a, b) = (self.get_twin(next), self.get_twin(prev));
+                    self.half_edges[a].twin = b;
+                    self.half_edges[b].twin = a;
+                    let corner = self.get_origin(prev);
+                    self.vertices[corner].half_edge = a;
+                    self.vertices[v].half_edge = b;
+                    for e in [side, next, prev] {
+                        self.half_edges[e].removed = true;
+                    }
+                    self.faces[f].removed = true;
+                }
+                None => {
+                    self.half_edges[prev].next = next;
+                    self.half_edges[next].prev = prev;
+                    self.half_edges[side].removed = true;
+                    self.vertices[v].half_edge = next;
+                }
+            }
+        }
+        for e in outgoing {
+            if !self.half_edges[e].removed {
+                self.half_edges[e].origin = v;
+                if let Some(corner) = replacement
+                    && !self.is_boundary(e) {
+                    self.half_edges[e].corner = corner;
+                }
+            }
+        }
+        self.vertices[u].removed = true;
+        true
+    }
+
+    /// Makes `half_edges` the sides of face `f`, in winding order.
+    fn set_triangle(&mut self, f: usize, half_edges: [usize; 3]) {
+        self.faces[f].half_edge = half_edges[0];
+        for i in 0..3 {
+            let e = &mut self.half_edges[half_edges[i]];
+            e.face = Some(f);
+            e.next = half_edges[(i + 1) % 3];
+            e.prev = half_edges[(i + 2) % 3];
+        }
+        for &e in &half_edges {
+            let origin = self.half_edges[e].origin;
+            self.vertices[origin].half_edge = e;
+        }
+    }
+}
+
+/// The vertex halfway between `a` and `b`.
+fn lerp(a: &Vertex, b: &Vertex) -> Vertex {
+    let mid = |a: &[f32], b: &[f32]| -> Vec<f32> { a.iter().zip(b).map(|(a, b)| (a + b) * 0.5).collect() };
+    let (p, n) = ((a.get_position() + b.get_position()) * 0.5, (a.get_normal() + b.get_normal()).normalized());
+    let uv = mid(&a.get_uv(), &b.get_uv());
+    let color = mid(&a.get_color(), &b.get_color());
+    Vertex::new([p.x, p.y, p.z], [n.x, n.y, n.z], [uv[0], uv[1]], [color[0], color[1], color[2], color[3]])
+}
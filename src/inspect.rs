@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fmt;
+use serde::Serialize;
+use crate::geometry::Aabb;
+use crate::mesh::{find_root, Attributes, Mesh};
+use crate::vector::Vector3;
+
+/// What a mesh contains and what is wrong with it, as printed by `triangle info`.
+///
+/// Connectivity is worked out on positions rather than vertex indices, since OBJ loading gives
+/// every face its own vertices.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct MeshReport {
+    pub vertices: usize,
+    pub indices: usize,
+    pub triangles: usize,
+    /// Distinct vertex positions.
+    pub unique_positions: usize,
+    /// Vertices equal in every attribute to an earlier one.
+    pub duplicate_vertices: usize,
+    /// None for an empty mesh.
+    pub bounds: Option<Bounds>,
+    pub attributes: Attributes,
+    /// Triangles with two corners at the same position or no area.
+    pub degenerate_triangles: usize,
+    /// Edges shared by more than two triangles.
+    pub non_manifold_edges: usize,
+    /// Positions where the triangles around them form more than one fan.
+    pub non_manifold_vertices: usize,
+    /// Edges used by only one triangle.
+    pub boundary_edges: usize,
+    /// Holes and open borders: connected runs of boundary edges.
+    pub open_boundaries: usize,
+    pub groups: Vec<Part>,
+    pub materials: Vec<Part>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize)]
+pub struct Bounds {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+    pub size: Vector3<f32>,
+}
+
+/// A named group or material and how many triangles use it.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Part {
+    pub name: String,
+    pub triangles: usize,
+}
+
+impl MeshReport {
+    pub fn new(mesh: &Mesh) -> Self {
+        let vertex_count = mesh.vb.get_vertex_count();
+        let indices = mesh.ib.get_indices();
+        let triangles = indices.len() / 3;
+        let (corners, unique_positions) = mesh.get_position_ids();
+
+        let mut seen: HashMap<Vec<u32>, usize> = HashMap::new();
+        for i in 0..vertex_count {
+            let bits = mesh.vb.get_vertex(i).as_vec().iter().map(|v| v.to_bits()).collect();
+            *seen.entry(bits).or_default() += 1;
+        }
+        let duplicate_vertices = vertex_count - seen.len();
+
+        let aabb = Aabb::from_points((0..vertex_count).map(|i| mesh.vb.get_vertex(i).get_position()));
+        let bounds = (!aabb.is_empty()).then(|| Bounds { min: aabb.min, max: aabb.max, size: aabb.size() });
+
+        let edges = Mesh::get_edge_triangles(&corners);
+        let non_manifold_edges = edges.values().filter(|t| t.len() > 2).count();
+
+        let boundary: Vec<(u32, u32)> = edges.iter().filter(|(_, t)| t.len() == 1).map(|(&e, _)| e).collect();
+        let mut parents: Vec<usize> = (0..unique_positions).collect();
+        for &(a, b) in &boundary {
+            let (a, b) = (find_root(&mut parents, a as usize), find_root(&mut parents, b as usize));
+            parents[a] = b;
+        }
+        let mut roots: Vec<usize> = boundary.iter().map(|&(a, _)| find_root(&mut parents, a as usize)).collect();
+        roots.sort_unstable();
+        roots.dedup();
+
+        let mut groups: Vec<Part> = Vec::new();
+        let mut materials: Vec<Part> = Vec::new();
+        for submesh in &mesh.submeshes {
+            add_part(&mut groups, &submesh.group, submesh.triangle_count);
+            if let Some(material) = &submesh.material {
+                add_part(&mut materials, material, submesh.triangle_count);
+            }
+        }
+
+        Self {
+            vertices: vertex_count,
+            indices: indices.len(),
+            triangles,
+            unique_positions,
+            duplicate_vertices,
+            bounds,
+            attributes: mesh.attributes,
+            degenerate_triangles: mesh.get_degenerate_triangles().len(),
+            non_manifold_edges,
+            non_manifold_vertices: mesh.get_non_manifold_vertices().len(),
+            boundary_edges: boundary.len(),
+            open_boundaries: roots.len(),
+            groups,
+            materials,
+        }
+    }
+
+    /// Closed and manifold, with no degenerate triangles: a surface that bounds a volume.
+    pub fn is_watertight(&self) -> bool {
+        self.triangles > 0
+            && self.boundary_edges == 0
+            && self.non_manifold_edges == 0
+            && self.non_manifold_vertices == 0
+            && self.degenerate_triangles == 0
+    }
+}
+
+fn add_part(parts: &mut Vec<Part>, name: &str, triangles: usize) {
+    match parts.iter_mut().find(|p| p.name == name) {
+        Some(part) => part.triangles += triangles,
+        None => parts.push(Part { name: name.to_string(), triangles }),
+    }
+}
+
+fn write_vector(f: &mut fmt::Formatter<'_>, v: Vector3<f32>) -> fmt::Result {
+    write!(f, "({}, {}, {})", v.x, v.y, v.z)
+}
+
+fn write_parts(f: &mut fmt::Formatter<'_>, label: &str, parts: &[Part]) -> fmt::Result {
+    write!(f, "{:<22}", label)?;
+    if parts.is_empty() {
+        return writeln!(f, "none");
+    }
+    let list: Vec<String> = parts.iter().map(|p| format!("{} ({} triangles)", p.name, p.triangles)).collect();
+    writeln!(f, "{}", list.join(", "))
+}
+
+impl fmt::Display for MeshReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        writeln!(f, "{:<22}{}", "vertices", self.vertices)?;
+        writeln!(f, "{:<22}{}", "indices", self.indices)?;
+        writeln!(f, "{:<22}{}", "triangles", self.triangles)?;
+        writeln!(f, "{:<22}{}", "unique positions", self.unique_positions)?;
+        write!(f, "{:<22}", "bounds")?;
+        match &self.bounds {
+            Some(bounds) => {
+                write_vector(f, bounds.min)?;
+                write!(f, " to ")?;
+                write_vector(f, bounds.max)?;
+                write!(f, ", size ")?;
+                write_vector(f, bounds.size)?;
+                writeln!(f)?;
+            }
+            None => writeln!(f, "empty")?,
+        }
+        writeln!(f, "{:<22}{}", "normals", yes_no(self.attributes.normals))?;
+        writeln!(f, "{:<22}{}", "texture coordinates", yes_no(self.attributes.uvs))?;
+        writeln!(f, "{:<22}{}", "colours", yes_no(self.attributes.colors))?;
+        write_parts(f, "groups", &self.groups)?;
+        write_parts(f, "materials", &self.materials)?;
+        writeln!(f, "{:<22}{}", "duplicate vertices", self.duplicate_vertices)?;
+        writeln!(f, "{:<22}{}", "degenerate triangles", self.degenerate_triangles)?;
+        writeln!(f, "{:<22}{}", "non-manifold edges", self.non_manifold_edges)?;
+        writeln!(f, "{:<22}{}", "non-manifold vertices", self.non_manifold_vertices)?;
+        writeln!(f, "{:<22}{} in {} open boundaries", "boundary edges", self.boundary_edges, self.open_boundaries)?;
+        write!(f, "{:<22}{}", "watertight", yes_no(self.is_watertight()))
+    }
+}
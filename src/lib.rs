@@ -0,0 +1,29 @@
+pub mod buffers;
+pub mod mesh;
+pub mod model;
+pub mod matrix;
+pub mod camera;
+pub mod vector;
+pub mod quaternion;
+pub mod simd;
+pub mod transform;
+pub mod geometry;
+pub mod bvh;
+pub mod inspect;
+pub mod repair;
+pub mod half_edge;
+pub mod simplify;
+pub mod picking;
+pub mod culling;
+pub mod scene;
+pub mod scene_file;
+pub mod mesh_file;
+pub mod stl;
+pub mod ply;
+pub mod gltf;
+pub mod color;
+pub mod shader;
+pub mod display;
+pub mod renderer;
+pub mod cli;
+pub mod controller;
@@ -1,50 +1,48 @@
-mod buffers;
-mod mesh;
-mod model;
-mod matrix;
-mod camera;
-mod vector;
-mod quaternion;
-mod transform;
-mod color;
-mod shader;
-
-use std::env;
-use std::f32::consts::PI;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::sync::Arc;
-use wgpu::{include_wgsl, BindGroupLayout, Face, FrontFace, PrimitiveTopology, RenderPipeline, ShaderModuleDescriptor, ShaderSource};
-use wgpu::Face::Back;
-use wgpu::PolygonMode::Fill;
-use wgpu::util::DeviceExt;
+use std::time::Instant;
+use clap::Parser;
 use winit::application::ApplicationHandler;
-use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
+use winit::event::{DeviceEvent, DeviceId, ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
 use winit::keyboard::{KeyCode, PhysicalKey};
-use winit::window::{Window, WindowId};
-use crate::model::Model;
-use crate::camera::Camera;
-use crate::color::Color;
-use crate::quaternion::Quaternion;
-use crate::shader::{AnyShader, BaseShader, PhongShader, FlatShader};
-use crate::vector::Vector3;
+use winit::window::{CursorGrabMode, Window, WindowId};
+use triangle::camera::{Camera, ViewPreset};
+use triangle::cli::{Cli, Command, ConvertArgs, Format, InfoArgs, RenderArgs, RepairArgs, SimplifyArgs, SceneArgs, ViewArgs};
+use triangle::color::Color;
+use triangle::controller::{FlyController, OrbitController};
+use triangle::culling::{cull, CullStats};
+use triangle::display::DisplayMode;
+use triangle::inspect::MeshReport;
+use triangle::mesh_file::{load_mesh, save_mesh, MeshFormat};
+use triangle::model::Model;
+use triangle::picking::{pick_screen, Pick};
+use triangle::quaternion::DQuaternion;
+use triangle::renderer::{render_image, Draw, Renderer};
+use triangle::scene::Scene;
+use triangle::scene_file::SceneFile;
+use triangle::shader::Light;
+use triangle::vector::Vector3;
 
 struct State<'a> {
     surface: wgpu::Surface<'a>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
-    render_pipeline: RenderPipeline,
+    renderer: Renderer,
     window: Arc<Window>,
-    uniform_bind_group_layout: BindGroupLayout,
 }
 
 impl<'a> State<'a> {
-    async fn new(event_loop: &ActiveEventLoop) -> Self {
+    async fn new(event_loop: &ActiveEventLoop, args: &ViewArgs) -> Self {
 
         let instance = wgpu::Instance::default();
 
-        let window_attributes = Window::default_attributes().with_title("resumed");
+        let window_attributes = Window::default_attributes()
+            .with_title("resumed")
+            .with_inner_size(LogicalSize::new(args.scene.size.width, args.scene.size.height));
 
         let window : Arc<Window> = Arc::new(event_loop.create_window(window_attributes).unwrap());
 
@@ -59,166 +57,51 @@ impl<'a> State<'a> {
             compatible_surface: Some(&surface),
             ..Default::default()
         }).await.unwrap();
-        
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default())
-            .await
-            .unwrap();        
+
+        let format = surface.get_capabilities(&adapter).formats[0];
+        let renderer = Renderer::new(&adapter, format, size.width, size.height, args.scene.shader).await.unwrap();
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_capabilities(&adapter).formats[0],
+            format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode: if args.vsync { wgpu::PresentMode::AutoVsync } else { wgpu::PresentMode::AutoNoVsync },
             desired_maximum_frame_latency: 1,
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
             view_formats: vec![],
         };
 
-        surface.configure(&device, &config);
-
-        let uniform_bind_group_layout = device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("uniform_bgl"),
-            }
-        );
-
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[&uniform_bind_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        let shader = device.create_shader_module(include_wgsl!("shaders/phong.wgsl"));
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Triangle Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                compilation_options: Default::default(),
-                buffers: &[buffers::VertexBuffer::LAYOUT],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                compilation_options: Default::default(),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: FrontFace::Ccw,
-                cull_mode: Some(Back),
-                unclipped_depth: false,
-                polygon_mode: Fill,
-                conservative: false
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
+        surface.configure(&renderer.device, &config);
 
         Self {
             surface,
-            device,
-            queue,
             config,
             size,
-            render_pipeline,
+            renderer,
             window,
-            uniform_bind_group_layout
         } 
     }
 
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        // a minimized window reports a zero size, which the surface cannot be configured with
+        if new_size.width == 0 || new_size.height == 0 {
+            return;
+        }
         self.size = new_size;
         self.config.width = new_size.width;
         self.config.height = new_size.height;
-        self.surface.configure(&self.device, &self.config);
+        self.surface.configure(&self.renderer.device, &self.config);
+        self.renderer.resize(new_size.width, new_size.height);
     }
 
-    fn render(&mut self, model: &Model, mut uniforms: Vec<f32>) -> Result<(), wgpu::SurfaceError> {
+    /// Draws the models into one frame, which is cleared to `background` first.
+    fn render(&mut self, draws: &[Draw], background: Color, mode: DisplayMode, show_back_faces: bool) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
-
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    depth_slice: None,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-
-            let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(&*model.get_vertices()),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-
-            let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(&*model.get_indices()),
-                usage: wgpu::BufferUsages::INDEX,
-            });
-
-            let uniform_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
-                label: Some("Uniform Buffer"),
-                contents: bytemuck::cast_slice(uniforms.as_mut_slice()),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            });
-
-            let uniform_bind_group = self.device.create_bind_group(
-                &wgpu::BindGroupDescriptor {
-                    layout: &self.uniform_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: uniform_buffer.as_entire_binding(),
-                    }],
-                    label: Some("uniform_bg"),
-                }
-            );
-
-            render_pass.set_bind_group(0, &uniform_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.draw_indexed(0..model.get_indices().len() as u32, 0, 0..1);
-        }
-
-        self.queue.submit(Some(encoder.finish()));
+        self.renderer.render(&view, draws, background, mode, show_back_faces);
         output.present();
 
         Ok(())
@@ -227,118 +110,338 @@ impl<'a> State<'a> {
 
 struct App<'a> {
     state: Option<State<'a>>,
-    models: Vec<Model>,
+    args: ViewArgs,
+    scene: Scene,
+    /// Where F5 saves the scene: the scene file it was loaded from, or scene.ron.
+    scene_path: PathBuf,
+    lights: Vec<Light>,
+    background: Color,
     camera: Camera,
-    key_event: Option<KeyEvent>,
+    controller: OrbitController,
+    fly: FlyController,
+    camera_mode: CameraMode,
+    key_events: Vec<KeyEvent>,
+    pressed_keys: HashSet<KeyCode>,
+    cursor: (f32, f32),
+    /// Where the left button went down, to tell clicks from drags.
+    press_cursor: (f32, f32),
+    drag_button: Option<MouseButton>,
+    selection: Option<Pick>,
+    last_update: Instant,
+    display_mode: DisplayMode,
+    show_back_faces: bool,
+    cull_stats: CullStats,
+    /// Triangles drawn last frame, fewer where levels of detail were used.
+    triangles: usize,
 }
 
 impl App<'_> {
-    fn handle_key_event(&mut self) {
-        match &self.key_event {
-            Some(event) => {
-                match (event.physical_key, event.state) {
-                    (PhysicalKey::Code(KeyCode::KeyD), ElementState::Pressed) => {
-                        let current_pos = self.camera.transform.get_position();
-                        self.camera.transform.set_position(current_pos + Vector3::new(-0.1,0.,0.));
-                    },
-                    (PhysicalKey::Code(KeyCode::KeyA), ElementState::Pressed) => {
-                        let current_pos = self.camera.transform.get_position();
-                        self.camera.transform.set_position(current_pos + Vector3::new(0.1,0.,0.));
-                    },
-                    (PhysicalKey::Code(KeyCode::KeyW), ElementState::Pressed) => {
-                        let current_pos = self.camera.transform.get_position();
-                        self.camera.transform.set_position(current_pos + Vector3::new(0.,0.,0.1));
-                    },
-                    (PhysicalKey::Code(KeyCode::KeyS), ElementState::Pressed) => {
-                        let current_pos = self.camera.transform.get_position();
-                        self.camera.transform.set_position(current_pos + Vector3::new(0.,0.,-0.1));
-                    },
-                    (PhysicalKey::Code(KeyCode::KeyQ), ElementState::Pressed) => {
-                        let current_pos = self.camera.transform.get_position();
-                        self.camera.transform.set_position(current_pos + Vector3::new(0.,-0.1,0.));
-                    },
-                    (PhysicalKey::Code(KeyCode::KeyE), ElementState::Pressed) => {
-                        let current_pos = self.camera.transform.get_position();
-                        self.camera.transform.set_position(current_pos + Vector3::new(0.,0.1,0.));
-                    },
-                    _ => {}
+    fn handle_key_events(&mut self) {
+        for event in std::mem::take(&mut self.key_events) {
+            // held keys are tracked in pressed_keys, toggles only react to the first press
+            if event.repeat {
+                continue;
+            }
+            match (event.physical_key, event.state) {
+                (PhysicalKey::Code(KeyCode::KeyV), ElementState::Pressed) => {
+                    let mode = if self.camera_mode == CameraMode::Orbit { CameraMode::Fly } else { CameraMode::Orbit };
+                    self.set_camera_mode(mode);
+                },
+                (PhysicalKey::Code(KeyCode::Escape), ElementState::Pressed) => self.set_camera_mode(CameraMode::Orbit),
+                (PhysicalKey::Code(KeyCode::Digit1), ElementState::Pressed) => self.set_display_mode(DisplayMode::Solid),
+                (PhysicalKey::Code(KeyCode::Digit2), ElementState::Pressed) => self.set_display_mode(DisplayMode::Wireframe),
+                (PhysicalKey::Code(KeyCode::Digit3), ElementState::Pressed) => self.set_display_mode(DisplayMode::ShadedWireframe),
+                (PhysicalKey::Code(KeyCode::Digit4), ElementState::Pressed) => self.set_display_mode(DisplayMode::Points),
+                (PhysicalKey::Code(KeyCode::Digit5), ElementState::Pressed) => self.set_display_mode(DisplayMode::HiddenLine),
+                (PhysicalKey::Code(KeyCode::Tab), ElementState::Pressed) => self.set_display_mode(self.display_mode.next()),
+                (PhysicalKey::Code(KeyCode::KeyP), ElementState::Pressed) => self.camera.toggle_projection(),
+                (PhysicalKey::Code(KeyCode::Numpad1), ElementState::Pressed) => self.set_view_preset(ViewPreset::Front),
+                (PhysicalKey::Code(KeyCode::Numpad3), ElementState::Pressed) => self.set_view_preset(ViewPreset::Right),
+                (PhysicalKey::Code(KeyCode::Numpad7), ElementState::Pressed) => self.set_view_preset(ViewPreset::Top),
+                (PhysicalKey::Code(KeyCode::KeyC), ElementState::Pressed) => {
+                    if let Some(state) = self.state.as_mut() {
+                        let cull_mode = state.renderer.get_cull_mode().next();
+                        state.renderer.set_cull_mode(cull_mode);
+                    }
+                    self.update_title();
+                },
+                (PhysicalKey::Code(KeyCode::KeyB), ElementState::Pressed) => {
+                    self.show_back_faces = !self.show_back_faces;
+                    self.update_title();
+                },
+                (PhysicalKey::Code(KeyCode::KeyF), ElementState::Pressed) => {
+                    for model in self.scene.get_models_mut() {
+                        model.flip_winding();
+                    }
+                },
+                (PhysicalKey::Code(KeyCode::KeyO), ElementState::Pressed) => {
+                    let flipped: usize = self.scene.get_models_mut().iter_mut().map(|model| model.orient_winding()).sum();
+                    println!("Flipped {} triangles", flipped);
+                },
+                (PhysicalKey::Code(KeyCode::F5), ElementState::Pressed) => self.save_scene(),
+                _ => {}
+            }
+        }
+    }
+
+    fn is_pressed(&self, key: KeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    /// Movement requested by the held keys, in camera space.
+    fn movement(&self) -> Vector3<f32> {
+        let axis = |positive: KeyCode, negative: KeyCode| {
+            (self.is_pressed(positive) as i32 - self.is_pressed(negative) as i32) as f32
+        };
+        Vector3::new(axis(KeyCode::KeyD, KeyCode::KeyA), axis(KeyCode::KeyE, KeyCode::KeyQ), axis(KeyCode::KeyS, KeyCode::KeyW))
+    }
+
+    fn speed_factor(&self) -> f32 {
+        let mut factor = 1.;
+        if self.is_pressed(KeyCode::ShiftLeft) || self.is_pressed(KeyCode::ShiftRight) {
+            factor *= FAST_FACTOR;
+        }
+        if self.is_pressed(KeyCode::ControlLeft) || self.is_pressed(KeyCode::ControlRight) {
+            factor *= SLOW_FACTOR;
+        }
+        factor
+    }
+
+    fn set_camera_mode(&mut self, mode: CameraMode) {
+        if mode == self.camera_mode {
+            return;
+        }
+        match mode {
+            CameraMode::Fly => self.fly.set_view(self.controller.get_position(), self.controller.get_rotation()),
+            CameraMode::Orbit => {
+                // orbit whatever is in front of the camera at the previous orbit distance
+                let rotation = self.fly.get_rotation();
+                let forward = rotation.rotate(Vector3::backward());
+                self.controller.set_rotation(rotation);
+                self.controller.set_target(self.fly.get_position() + (forward * self.controller.get_distance()).cast());
+            }
+        }
+        self.camera_mode = mode;
+        self.drag_button = None;
+
+        if let Some(state) = &self.state {
+            let grab = mode == CameraMode::Fly;
+            let result = if grab {
+                state.window.set_cursor_grab(CursorGrabMode::Locked)
+                    .or_else(|_| state.window.set_cursor_grab(CursorGrabMode::Confined))
+            } else {
+                state.window.set_cursor_grab(CursorGrabMode::None)
+            };
+            if let Err(e) = result {
+                eprintln!("Could not grab the cursor: {}", e);
+            }
+            state.window.set_cursor_visible(!grab);
+        }
+    }
+
+    fn set_view_preset(&mut self, preset: ViewPreset) {
+        self.set_camera_mode(CameraMode::Orbit);
+        self.controller.set_view(preset);
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        if let Some(state) = self.state.as_mut() {
+            state.resize(size);
+        }
+        self.camera.set_viewport(size.width, size.height);
+    }
+
+    fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.display_mode = mode;
+        self.update_title();
+    }
+
+    fn update_title(&self) {
+        if let Some(state) = &self.state {
+            let back_faces = if self.show_back_faces { ", back faces" } else { "" };
+            state.window.set_title(&format!("triangle - {}, {}{} - {}, {} triangles", self.display_mode, state.renderer.get_cull_mode(), back_faces, self.cull_stats, self.triangles));
+        }
+    }
+
+    fn start(&mut self, file: &SceneFile) {
+        self.lights = file.lights.clone();
+        self.background = file.background;
+
+        file.camera.apply(&mut self.camera);
+        self.controller.set_target(file.camera.target);
+        self.controller.set_distance(file.camera.get_distance());
+        self.controller.set_rotation(self.camera.transform.get_rotation().cast());
+    }
+
+    fn save_scene(&self) {
+        let base = self.scene_path.parent().unwrap_or(Path::new(""));
+        let file = SceneFile::capture(&self.scene, &self.camera, &self.lights, self.background, base);
+        match file.save(&self.scene_path) {
+            Ok(()) => println!("Saved the scene to {}", self.scene_path.display()),
+            Err(e) => eprintln!("Could not save the scene to {}: {}", self.scene_path.display(), e),
+        }
+    }
+
+    fn handle_cursor_moved(&mut self, position: PhysicalPosition<f64>) {
+        let cursor = (position.x as f32, position.y as f32);
+        if let (Some(button), Some(state), CameraMode::Orbit) = (self.drag_button, self.state.as_ref(), self.camera_mode) {
+            let (width, height) = (state.size.width as f32, state.size.height as f32);
+            match button {
+                MouseButton::Left => self.controller.rotate(self.cursor, cursor, width, height),
+                MouseButton::Right | MouseButton::Middle => {
+                    self.controller.pan(cursor.0 - self.cursor.0, cursor.1 - self.cursor.1, &self.camera, height)
                 }
+                _ => {}
             }
-            None => { }
         }
-        self.key_event = None;
+        self.cursor = cursor;
     }
 
-    fn load_model() -> Model {
-        let mut model: Model = Model::default();
-        let args: Vec<String> = env::args().collect();
-        if args.len() <= 1{
-                let _ = model.load_obj("assets/cube.obj");
+    fn handle_mouse_input(&mut self, button: MouseButton, state: ElementState) {
+        if button == MouseButton::Left && self.camera_mode == CameraMode::Orbit {
+            match state {
+                ElementState::Pressed => self.press_cursor = self.cursor,
+                ElementState::Released => {
+                    let (dx, dy) = (self.cursor.0 - self.press_cursor.0, self.cursor.1 - self.press_cursor.1);
+                    if dx * dx + dy * dy <= CLICK_TOLERANCE * CLICK_TOLERANCE {
+                        self.select();
+                    }
+                }
+            }
         }
-        else {
-            let _ = model.load_obj(&args[1]);
+        match state {
+            ElementState::Pressed => self.drag_button = Some(button),
+            ElementState::Released if self.drag_button == Some(button) => self.drag_button = None,
+            ElementState::Released => {}
         }
-        println!("{}", model);
-        model
     }
 
-    fn start(&mut self) {
-        self.models[0].transform.set_position(Vector3::new(0., 0., 0.));
-        self.camera.transform.set_position(Vector3::new(0., 0.,-5.));
+    /// Picks whatever is under the cursor and prints what was hit.
+    fn select(&mut self) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        let (width, height) = (state.size.width as f32, state.size.height as f32);
+        self.selection = pick_screen(self.scene.get_models(), &self.camera, self.cursor.0, self.cursor.1, width, height);
+        match &self.selection {
+            Some(pick) => println!(
+                "Picked model {}, triangle {} at ({:.4}, {:.4}, {:.4}), distance {:.4}, barycentric ({:.3}, {:.3}, {:.3}), normal ({:.3}, {:.3}, {:.3})",
+                pick.model, pick.triangle, pick.point.x, pick.point.y, pick.point.z, pick.distance,
+                pick.barycentric.x, pick.barycentric.y, pick.barycentric.z, pick.normal.x, pick.normal.y, pick.normal.z,
+            ),
+            None => println!("Nothing picked"),
+        }
+    }
+
+    fn handle_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+        };
+        self.controller.zoom(lines);
     }
 
     fn update(&mut self) {
-        self.handle_key_event();
-        let model: &mut Model = self.models.get_mut(0).unwrap();
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        self.handle_key_events();
+        let movement = self.movement();
+        let speed_factor = self.speed_factor();
+        match self.camera_mode {
+            CameraMode::Orbit => {
+                self.controller.translate(movement * (MOVE_SPEED * speed_factor * dt));
+                self.controller.update(dt);
+                self.controller.apply(&mut self.camera);
+            }
+            CameraMode::Fly => {
+                self.fly.update(dt, movement, speed_factor);
+                self.fly.apply(&mut self.camera);
+            }
+        }
 
-        let quat: Quaternion = model.transform.get_rotation();
-        let q: Quaternion = Quaternion::from_angle_axis(0.03, Vector3 { x: 0.0, y: 0.0, z: 1.0});
+        if let (false, Some(&root)) = (self.args.no_rotate, self.scene.get_roots().first()) {
+            let transform = self.scene.local_transform_mut(root);
 
-        model.transform.set_rotation(quat * q);
+            let quat: DQuaternion = transform.get_rotation();
+            // models are Y up once loaded, whatever their file used
+            let q: DQuaternion = DQuaternion::from_angle_axis(0.03, Vector3::up().cast());
 
-        //self.camera.transform.look_at(model.transform.get_position(), Vector3::up());
+            transform.set_rotation(quat * q);
+        }
+        self.scene.update();
     }
 
     fn draw(&mut self) {
         let s: &mut State = self.state.as_mut().unwrap();
 
-        let flat = FlatShader::default();
-
-        let mut phong = PhongShader::default();
-        phong.set_ambient(Color::new(1., 0., 0., 1.));
-        phong.set_diffuse(Color::new(1., 0., 0., 1.));
-        phong.set_specular(Color::new(1., 1., 1., 1.));
-        phong.set_eye_pos(self.camera.transform.get_position());
-        phong.set_light_dir(Vector3::new(-1., -1., 1.));
-
-        for i in 0..self.models.len(){
-            s.render(&self.models[i], phong.as_vec(&self.models[i], &self.camera)).expect("Render failed")
-        }
+        let light = self.lights.first().copied().unwrap_or_default();
+        let models = self.scene.get_models();
+        let (visible, stats) = cull(models, &self.camera);
+        let draws: Vec<Draw> = visible.iter().map(|&i| Draw {
+            highlight: self.selection.filter(|p| p.model == i).map(|p| models[i].get_triangle_vertices(p.triangle)),
+            ..Draw::new(&models[i], &self.camera, light)
+        }).collect();
+        s.render(&draws, self.background, self.display_mode, self.show_back_faces).expect("Render failed");
 
         s.window.request_redraw();
+        let triangles = draws.iter().map(|d| d.mesh.get_triangle_count()).sum();
+        if stats != self.cull_stats || triangles != self.triangles {
+            self.cull_stats = stats;
+            self.triangles = triangles;
+            self.update_title();
+        }
     }
 }
 
-const SCREEN_WIDTH: i32 = 600;
-const SCREEN_HEIGHT: i32 = 420;
-impl<'a> Default for App<'a> {
-    fn default() -> Self {
-        let fov = PI * 65. / 180.;
-        let aspect = SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32;
-        let near = 0.001;
-        let far = 1000.;
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum CameraMode {
+    Orbit,
+    Fly,
+}
+
+// units per second, scaled by FAST_FACTOR with shift and SLOW_FACTOR with ctrl held
+const MOVE_SPEED: f32 = 3.;
+const FAST_FACTOR: f32 = 4.;
+const SLOW_FACTOR: f32 = 0.25;
 
-        let mut models: Vec<Model> = Vec::new();
-        models.push(Self::load_model());
+// how far in pixels the cursor may move between press and release for a click to pick
+const CLICK_TOLERANCE: f32 = 3.;
 
-        let mut app: App = App {
+// touchpads report scrolling in pixels, wheels in lines
+const PIXELS_PER_LINE: f32 = 40.;
+
+impl App<'_> {
+    fn new(args: ViewArgs) -> Self {
+        let (file, scene_path, scene) = load_scene(&args.scene);
+        let aspect = args.scene.size.width as f32 / args.scene.size.height as f32;
+
+        let mut app = App {
             state: None,
-            models,
-            camera: Camera::new(fov, aspect, near, far),
-            key_event: None
+            args,
+            scene,
+            scene_path,
+            lights: Vec::new(),
+            background: Color::default(),
+            camera: Camera::new(file.camera.fov.to_radians(), aspect, file.camera.near, file.camera.far),
+            controller: OrbitController::new(Vector3::default(), 1.),
+            fly: FlyController::new(Vector3::default(), MOVE_SPEED),
+            camera_mode: CameraMode::Orbit,
+            key_events: Vec::new(),
+            pressed_keys: HashSet::new(),
+            cursor: (0., 0.),
+            press_cursor: (0., 0.),
+            drag_button: None,
+            selection: None,
+            last_update: Instant::now(),
+            display_mode: DisplayMode::default(),
+            show_back_faces: false,
+            cull_stats: CullStats::default(),
+            triangles: 0,
         };
 
-        app.start();
+        app.start(&file);
 
         app
     }
@@ -346,7 +449,16 @@ impl<'a> Default for App<'a> {
 
 impl<'a> ApplicationHandler for App<'a> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {   
-        self.state = Some(pollster::block_on(State::new(event_loop)));
+        let state = pollster::block_on(State::new(event_loop, &self.args));
+        self.camera.set_viewport(state.size.width, state.size.height);
+        self.state = Some(state);
+    }
+
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _id: DeviceId, event: DeviceEvent) {
+        // raw motion keeps coming while the cursor is grabbed
+        if let (DeviceEvent::MouseMotion { delta: (dx, dy) }, CameraMode::Fly) = (event, self.camera_mode) {
+            self.fly.look(dx as f32, dy as f32);
+        }
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
@@ -356,24 +468,189 @@ impl<'a> ApplicationHandler for App<'a> {
                 event_loop.exit();
             },
             WindowEvent::RedrawRequested => {
-                let _ = self.update();
-                let _ = self.draw();
+                self.update();
+                self.draw();
             },
-            WindowEvent::KeyboardInput {device_id, event, is_synthetic} => {
-                self.key_event = Some(event);
+            WindowEvent::Resized(size) => {
+                self.resize(size);
+            },
+            WindowEvent::ScaleFactorChanged { .. } => {
+                // the physical size changes with the scale factor even when the logical size does not
+                if let Some(size) = self.state.as_ref().map(|s| s.window.inner_size()) {
+                    self.resize(size);
+                }
+            },
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    match event.state {
+                        ElementState::Pressed => self.pressed_keys.insert(code),
+                        ElementState::Released => self.pressed_keys.remove(&code),
+                    };
+                }
+                self.key_events.push(event);
+            }
+            WindowEvent::Focused(false) => {
+                // key releases are not delivered while unfocused
+                self.pressed_keys.clear();
             }
+            WindowEvent::CursorMoved { position, .. } => self.handle_cursor_moved(position),
+            WindowEvent::MouseInput { state, button, .. } => self.handle_mouse_input(button, state),
+            WindowEvent::MouseWheel { delta, .. } => self.handle_mouse_wheel(delta),
             _ => (),
         }
     }
 }
 
-fn main() {
-    let event_loop = EventLoop::new().unwrap();
+/// Gathers the inputs into a scene, exiting with a message when something cannot be loaded.
+fn load_scene(args: &SceneArgs) -> (SceneFile, PathBuf, Scene) {
+    let loaded = args.load().and_then(|(file, path)| {
+        let mut scene = file.instantiate("")?;
+        if let Some(options) = args.get_lod_options() {
+            scene.get_models_mut().iter_mut().for_each(|model| model.generate_lods(&options));
+        }
+        Ok((file, path, scene))
+    });
+    match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => fail(&format!("Could not load the scene: {}", e)),
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn view(args: ViewArgs) {
+    let mut app = App::new(args);
+    let event_loop = EventLoop::new()
+        .unwrap_or_else(|e| fail(&format!("Could not open a window, `triangle render` works without a display: {}", e)));
 
     // ControlFlow::Poll continuously runs the event loop, even if the OS hasn't
     // dispatched any events. This is ideal for games and similar applications.
     event_loop.set_control_flow(ControlFlow::Poll);
 
-    let mut app = App::default();
     let _ = event_loop.run_app(&mut app);
 }
+
+fn render(args: RenderArgs) {
+    let (file, _, scene) = load_scene(&args.scene);
+    let size = args.scene.size;
+    let mut camera = Camera::new(file.camera.fov.to_radians(), size.width as f32 / size.height as f32, file.camera.near, file.camera.far);
+    file.camera.apply(&mut camera);
+
+    let light = file.lights.first().copied().unwrap_or_default();
+    let pixels = render_image(scene.get_models(), &camera, light, file.background, args.scene.shader, size.width, size.height)
+        .unwrap_or_else(|e| fail(&format!("Could not render: {}", e)));
+    match write_png(&args.output, size.width, size.height, &pixels) {
+        Ok(()) => println!("Wrote {}", args.output.display()),
+        Err(e) => fail(&format!("Could not write {}: {}", args.output.display(), e)),
+    }
+}
+
+fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> std::io::Result<()> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header().map_err(std::io::Error::other)?;
+    writer.write_image_data(rgba).map_err(std::io::Error::other)
+}
+
+fn load_model(path: &Path) -> Model {
+    let mut model = Model::default();
+    if let Err(e) = model.try_load(&path.to_string_lossy()) {
+        fail(&format!("Could not load {}: {}", path.display(), e));
+    }
+    model
+}
+
+fn info(args: InfoArgs) {
+    let reports = args.inputs.iter().map(|input| (input, MeshReport::new(load_model(input).get_mesh())));
+    match args.format {
+        Format::Text => {
+            for (i, (input, report)) in reports.enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                println!("{}", input.display());
+                println!("{}", report);
+            }
+        }
+        Format::Json => {
+            let reports: Vec<_> = reports.map(|(input, report)| serde_json::json!({ "path": input, "report": report })).collect();
+            match serde_json::to_string_pretty(&reports) {
+                Ok(json) => println!("{}", json),
+                Err(e) => fail(&format!("Could not write the report: {}", e)),
+            }
+        }
+    }
+}
+
+fn convert(args: ConvertArgs) {
+    // check the output format before doing any work
+    if let Err(e) = MeshFormat::from_path(&args.output) {
+        fail(&format!("Cannot convert: {}", e));
+    }
+    let mut mesh = load_mesh(&args.input).unwrap_or_else(|e| fail(&format!("Could not load {}: {}", args.input.display(), e)));
+    args.get_options().apply(&mut mesh);
+    if let Err(e) = save_mesh(&mesh, &args.output) {
+        fail(&format!("Could not write {}: {}", args.output.display(), e));
+    }
+}
+
+/// Prints at most ten of `items`, saying how many more there are.
+fn print_list<T>(label: &str, items: &[T], show: impl Fn(&T) -> String) {
+    println!("{:<30}{}", label, items.len());
+    for item in items.iter().take(10) {
+        println!("  {}", show(item));
+    }
+    if items.len() > 10 {
+        println!("  and {} more", items.len() - 10);
+    }
+}
+
+fn repair(args: RepairArgs) {
+    if let Some(output) = &args.output
+        && let Err(e) = MeshFormat::from_path(output) {
+        fail(&format!("Cannot repair: {}", e));
+    }
+    let mut mesh = load_mesh(&args.input).unwrap_or_else(|e| fail(&format!("Could not load {}: {}", args.input.display(), e)));
+    println!("{}", mesh.repair(&args.get_options()));
+
+    let point = |p: &Vector3<f32>| format!("({}, {}, {})", p.x, p.y, p.z);
+    print_list("non-manifold edges left", &mesh.get_non_manifold_edges(), |[a, b]| format!("{} to {}", point(a), point(b)));
+    print_list("non-manifold vertices left", &mesh.get_non_manifold_vertices(), point);
+    match &args.output {
+        Some(output) => {
+            if let Err(e) = save_mesh(&mesh, output) {
+                fail(&format!("Could not write {}: {}", output.display(), e));
+            }
+        }
+        None => println!("nothing written, as no output file was given"),
+    }
+}
+
+fn simplify(args: SimplifyArgs) {
+    if let Err(e) = MeshFormat::from_path(&args.output) {
+        fail(&format!("Cannot simplify: {}", e));
+    }
+    let mesh = load_mesh(&args.input).unwrap_or_else(|e| fail(&format!("Could not load {}: {}", args.input.display(), e)));
+    let (simplified, error) = mesh.simplify(&args.get_options(mesh.get_triangle_count()));
+    println!("{} triangles to {}, with a quadric error of up to {}", mesh.get_triangle_count(), simplified.get_triangle_count(), error);
+    if let Err(e) = save_mesh(&simplified, &args.output) {
+        fail(&format!("Could not write {}: {}", args.output.display(), e));
+    }
+}
+
+fn main() {
+    match Cli::parse().into_command() {
+        Command::View(args) => view(args),
+        Command::Render(args) => render(args),
+        Command::Info(args) => info(args),
+        Command::Convert(args) => convert(args),
+        Command::Repair(args) => repair(args),
+        Command::Simplify(args) => simplify(args),
+    }
+}
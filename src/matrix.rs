@@ -1,179 +1,359 @@
 use std::ops::{self, Index, IndexMut};
-use std::f32;
-use crate::vector::Vector3;
+use crate::quaternion::Quat;
+use crate::vector::{Float, Vector3, Vector4};
 
+/// 4x4 matrix of any float type, see [`Matrix4`] and [`DMatrix4`].
+///
+/// Stored as aligned rows, which is what the SIMD kernels in [`crate::simd`] load: with vectors
+/// multiplied from the left, each row of a product is a weighted sum of the right matrix's rows.
 #[derive(Copy, Clone)]
-pub struct Matrix4 {
-    m00: f32, m10: f32, m20: f32, m30: f32,
-    m01: f32, m11: f32, m21: f32, m31: f32,
-    m02: f32, m12: f32, m22: f32, m32: f32,
-    m03: f32, m13: f32, m23: f32, m33: f32,
+#[repr(C, align(16))]
+pub struct Mat4<T> {
+    rows: [[T; 4]; 4],
 }
 
-impl Index<(usize, usize)> for Matrix4 {
-    type Output = f32;
+pub type Matrix4 = Mat4<f32>;
+/// Double precision matrix, for placing things at large coordinates.
+pub type DMatrix4 = Mat4<f64>;
+
+impl<T> Index<(usize, usize)> for Mat4<T> {
+    type Output = T;
     fn index(&self, (r, c): (usize, usize)) -> &Self::Output {
-        match (r, c) {
-            (0, 0) => &self.m00, (1, 0) => &self.m10, (2, 0) => &self.m20, (3, 0) => &self.m30,
-            (0, 1) => &self.m01, (1, 1) => &self.m11, (2, 1) => &self.m21, (3, 1) => &self.m31,
-            (0, 2) => &self.m02, (1, 2) => &self.m12, (2, 2) => &self.m22, (3, 2) => &self.m32,
-            (0, 3) => &self.m03, (1, 3) => &self.m13, (2, 3) => &self.m23, (3, 3) => &self.m33,
-            _ => panic!("Index out of bounds"),
-        }
+        &self.rows[r][c]
     }
 }
 
-impl IndexMut<(usize, usize)> for Matrix4 {
-    fn index_mut(&mut self, idx: (usize, usize)) -> &mut Self::Output {
-        let (r, c) = idx;
-        match (r, c) {
-            (0, 0) => &mut self.m00, (1, 0) => &mut self.m10, (2, 0) => &mut self.m20, (3, 0) => &mut self.m30,
-            (0, 1) => &mut self.m01, (1, 1) => &mut self.m11, (2, 1) => &mut self.m21, (3, 1) => &mut self.m31,
-            (0, 2) => &mut self.m02, (1, 2) => &mut self.m12, (2, 2) => &mut self.m22, (3, 2) => &mut self.m32,
-            (0, 3) => &mut self.m03, (1, 3) => &mut self.m13, (2, 3) => &mut self.m23, (3, 3) => &mut self.m33,
-            _ => panic!("Index out of bounds"),
-        }
+impl<T> IndexMut<(usize, usize)> for Mat4<T> {
+    fn index_mut(&mut self, (r, c): (usize, usize)) -> &mut Self::Output {
+        &mut self.rows[r][c]
     }
 }
 
-impl ops::Mul<Matrix4> for Matrix4 {
-    type Output = Matrix4;
-    fn mul(self, rhs: Matrix4) -> Self::Output {
-        let mut result = Matrix4 {
-            m00: 0.0, m10: 0.0, m20: 0.0, m30: 0.0,
-            m01: 0.0, m11: 0.0, m21: 0.0, m31: 0.0,
-            m02: 0.0, m12: 0.0, m22: 0.0, m32: 0.0,
-            m03: 0.0, m13: 0.0, m23: 0.0, m33: 0.0,
-        };
-
-        // Column-major math: result = self * rhs
-        for c in 0..4 {          // column of result
-            for r in 0..4 {      // row of result
-                let mut sum = 0.0;
-                for k in 0..4 {  // dot product
-                    sum += self[(r, k)] * rhs[(k, c)];
-                }
-                result[(r, c)] = sum;
-            }
-        }
+impl<T: Float> ops::Mul<Mat4<T>> for Mat4<T> {
+    type Output = Mat4<T>;
+    fn mul(self, rhs: Mat4<T>) -> Self::Output {
+        Self { rows: T::mat4_mul(&self.rows, &rhs.rows) }
+    }
+}
 
-        result
+/// Transforms `v`; vectors multiply matrices from the left, see [`Matrix4::transform_point`].
+impl<T: Float> ops::Mul<Mat4<T>> for Vector4<T> {
+    type Output = Vector4<T>;
+    fn mul(self, rhs: Mat4<T>) -> Self::Output {
+        T::vec4_mul_mat4(self.as_array(), &rhs.rows).into()
     }
+}
 
+/// `v` as a column vector, the same as `v * m.transpose()`.
+impl<T: Float> ops::Mul<Vector4<T>> for Mat4<T> {
+    type Output = Vector4<T>;
+    fn mul(self, rhs: Vector4<T>) -> Self::Output {
+        rhs * self.transpose()
+    }
 }
 
-impl Matrix4{
-    pub fn new() -> Matrix4 {
-        Self {
-            m00: 0., m10: 0., m20: 0., m30: 0.,
-            m01: 0., m11: 0., m21: 0., m31: 0.,
-            m02: 0., m12: 0., m22: 0., m32: 0.,
-            m03: 0., m13: 0., m23: 0., m33: 0.,
-        }
+impl<T: Float> Default for Mat4<T> {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
+impl<T: Float> Mat4<T> {
+    pub fn new() -> Self {
+        Self { rows: [[T::from(0.); 4]; 4] }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn from_values(
-        m00: f32, m10: f32, m20: f32, m30: f32, 
-        m01: f32, m11: f32, m21: f32, m31: f32, 
-        m02: f32, m12: f32, m22: f32, m32: f32, 
-        m03: f32, m13: f32, m23: f32, m33: f32,
-        ) -> Matrix4 {
-
-        Self { 
-            m00, m10, m20, m30, 
-            m01, m11, m21, m31, 
-            m02, m12, m22, m32, 
-            m03, m13, m23, m33,
-        }
+        m00: T, m10: T, m20: T, m30: T, 
+        m01: T, m11: T, m21: T, m31: T, 
+        m02: T, m12: T, m22: T, m32: T, 
+        m03: T, m13: T, m23: T, m33: T,
+        ) -> Self {
+
+        Self::from_rows([
+            [m00, m01, m02, m03],
+            [m10, m11, m12, m13],
+            [m20, m21, m22, m23],
+            [m30, m31, m32, m33],
+        ])
     }
 
-    pub fn identity() -> Matrix4 {
-        Self {
-            m00: 1., m10: 0., m20: 0., m30: 0.,
-            m01: 0., m11: 1., m21: 0., m31: 0.,
-            m02: 0., m12: 0., m22: 1., m32: 0.,
-            m03: 0., m13: 0., m23: 0., m33: 1.,
-        }
+    pub fn identity() -> Self {
+        let (o, l) = (T::from(0.), T::from(1.));
+        Self::from_rows([
+            [l, o, o, o],
+            [o, l, o, o],
+            [o, o, l, o],
+            [o, o, o, l],
+        ])
     }
 
-    pub fn translate(vec: Vector3<f32>) -> Matrix4 {
-        let mut t: Matrix4 = Matrix4::identity();
-        t.m30 = vec.x;
-        t.m31 = vec.y;
-        t.m32 = vec.z;
+    pub fn translate(vec: Vector3<T>) -> Self {
+        let mut t = Self::identity();
+        t.rows[3] = [vec.x, vec.y, vec.z, T::from(1.)];
         t
     }
 
-    pub fn scale(vec: Vector3<f32>) -> Matrix4 {
-        let mut s: Matrix4 = Matrix4::identity();
-        s.m00 = vec.x;
-        s.m11 = vec.y;
-        s.m22 = vec.z;
+    pub fn scale(vec: Vector3<T>) -> Self {
+        let mut s = Self::identity();
+        s.rows[0][0] = vec.x;
+        s.rows[1][1] = vec.y;
+        s.rows[2][2] = vec.z;
         s
     }
 
-    pub fn rotate_euler(angles: Vector3<f32>) -> Matrix4 {
-        let a: Matrix4 = Matrix4::rotate_euler_x(angles.x);
-        let b: Matrix4 = Matrix4::rotate_euler_y(angles.y);
-        let c: Matrix4 = Matrix4::rotate_euler_z(angles.z);
+    pub fn rotate_euler(angles: Vector3<T>) -> Self {
+        let a = Self::rotate_euler_x(angles.x);
+        let b = Self::rotate_euler_y(angles.y);
+        let c = Self::rotate_euler_z(angles.z);
         c * b * a
     }
 
-    pub fn rotate_euler_x(x: f32) -> Matrix4 {
-        Self {
-            m00: 1., m10: 0.,          m20: 0.,          m30: 0.,
-            m01: 0., m11: f32::cos(x), m21: -f32::sin(x),m31: 0.,
-            m02: 0., m12: f32::sin(x), m22: f32::cos(x), m32: 0.,
-            m03: 0., m13: 0.,          m23: 0.,          m33: 1.,
-        }
+    pub fn rotate_euler_x(x: T) -> Self {
+        let (o, l, s, c) = (T::from(0.), T::from(1.), x.sin(), x.cos());
+        Self::from_rows([
+            [l, o,  o, o],
+            [o, c,  s, o],
+            [o, -s, c, o],
+            [o, o,  o, l],
+        ])
+    }
+
+    pub fn rotate_euler_y(y: T) -> Self {
+        let (o, l, s, c) = (T::from(0.), T::from(1.), y.sin(), y.cos());
+        Self::from_rows([
+            [c, o, -s, o],
+            [o, l, o,  o],
+            [s, o, c,  o],
+            [o, o, o,  l],
+        ])
+    }
+
+    pub fn rotate_euler_z(z: T) -> Self {
+        let (o, l, s, c) = (T::from(0.), T::from(1.), z.sin(), z.cos());
+        Self::from_rows([
+            [c,  s, o, o],
+            [-s, c, o, o],
+            [o,  o, l, o],
+            [o,  o, o, l],
+        ])
+    }
+
+    pub fn transpose(self) -> Self {
+        let r = self.rows;
+        Self::from_rows([0, 1, 2, 3].map(|c| [r[0][c], r[1][c], r[2][c], r[3][c]]))
+    }
+
+    /// Perspective projection looking down -z, mapping depth between the clip planes to 0..1.
+    pub fn project(fov: T, aspect: T, z_near: T, z_far: T) -> Self {
+        let (o, l) = (T::from(0.), T::from(1.));
+        let f = l / (fov / T::from(2.)).tan();
+        Self::from_rows([
+            [f / aspect, o, o,                                   o],
+            [o,          f, o,                                   o],
+            [o,          o, z_far / (z_near - z_far),            -l],
+            [o,          o, z_far * z_near / (z_near - z_far),   o],
+        ])
+    }
+
+    /// Orthographic projection of a view volume `height` units tall, with the same depth mapping as [`Mat4::project`].
+    pub fn orthographic(height: T, aspect: T, z_near: T, z_far: T) -> Self {
+        let (o, l) = (T::from(0.), T::from(1.));
+        let h = T::from(2.) / height;
+        Self::from_rows([
+            [h / aspect, o, o,                        o],
+            [o,          h, o,                        o],
+            [o,          o, l / (z_near - z_far),     o],
+            [o,          o, z_near / (z_near - z_far), l],
+        ])
+    }
+
+    /// Moves the point `p`, dividing by w when the matrix is a projection.
+    pub fn transform_point(&self, p: Vector3<T>) -> Vector3<T> {
+        let v = Vector4::point(p) * *self;
+        if v.w == T::from(1.) { v.xyz() } else { v.project() }
     }
 
-    pub fn rotate_euler_y(y: f32) -> Matrix4 {
-        Self {
-            m00: f32::cos(y),  m10: 0., m20: f32::sin(y), m30: 0.,
-            m01: 0.,           m11: 1., m21: 0.,          m31: 0.,
-            m02: -f32::sin(y), m12: 0., m22: f32::cos(y), m32: 0.,
-            m03: 0.,           m13: 0., m23: 0.,          m33: 1.,
+    /// Turns and scales the direction `d`, ignoring translation. Normals need [`Mat4::normal_matrix`].
+    pub fn transform_direction(&self, d: Vector3<T>) -> Vector3<T> {
+        (Vector4::direction(d) * *self).xyz()
+    }
+
+    pub fn determinant(&self) -> T {
+        let (s, c) = self.minors();
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    /// General inverse, `None` for singular matrices.
+    pub fn inverse(&self) -> Option<Self> {
+        let a = self.rows();
+        let (s, c) = self.minors();
+        let det = s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        if det == T::from(0.) || !det.is_finite() {
+            return None;
         }
+
+        let inv = [
+            [
+                a[1][1] * c[5] - a[1][2] * c[4] + a[1][3] * c[3],
+                -a[0][1] * c[5] + a[0][2] * c[4] - a[0][3] * c[3],
+                a[3][1] * s[5] - a[3][2] * s[4] + a[3][3] * s[3],
+                -a[2][1] * s[5] + a[2][2] * s[4] - a[2][3] * s[3],
+            ],
+            [
+                -a[1][0] * c[5] + a[1][2] * c[2] - a[1][3] * c[1],
+                a[0][0] * c[5] - a[0][2] * c[2] + a[0][3] * c[1],
+                -a[3][0] * s[5] + a[3][2] * s[2] - a[3][3] * s[1],
+                a[2][0] * s[5] - a[2][2] * s[2] + a[2][3] * s[1],
+            ],
+            [
+                a[1][0] * c[4] - a[1][1] * c[2] + a[1][3] * c[0],
+                -a[0][0] * c[4] + a[0][1] * c[2] - a[0][3] * c[0],
+                a[3][0] * s[4] - a[3][1] * s[2] + a[3][3] * s[0],
+                -a[2][0] * s[4] + a[2][1] * s[2] - a[2][3] * s[0],
+            ],
+            [
+                -a[1][0] * c[3] + a[1][1] * c[1] - a[1][2] * c[0],
+                a[0][0] * c[3] - a[0][1] * c[1] + a[0][2] * c[0],
+                -a[3][0] * s[3] + a[3][1] * s[1] - a[3][2] * s[0],
+                a[2][0] * s[3] - a[2][1] * s[1] + a[2][2] * s[0],
+            ],
+        ];
+        Some(Self::from_rows(inv.map(|row| row.map(|v| v / det))))
     }
 
-    pub fn rotate_euler_z(z: f32) -> Matrix4 {
-        Self {
-            m00: f32::cos(z), m10: -f32::sin(z), m20: 0., m30: 0.,
-            m01: f32::sin(z), m11: f32::cos(z),  m21: 0., m31: 0.,
-            m02: 0.,          m12: 0.,           m22: 1., m32: 0.,
-            m03: 0.,          m13: 0.,           m23: 0., m33: 1.,
-        }  
+    /// Inverse of a matrix made of rotation, scale and translation only, cheaper than [`Mat4::inverse`].
+    pub fn affine_inverse(&self) -> Option<Self> {
+        let a = self.rows();
+        let c = self.cofactors3();
+        let det = a[0][0] * c[0][0] + a[0][1] * c[0][1] + a[0][2] * c[0][2];
+        if det == T::from(0.) || !det.is_finite() {
+            return None;
+        }
+
+        let mut inv = [[T::from(0.); 4]; 4];
+        for r in 0..3 {
+            for col in 0..3 {
+                inv[r][col] = c[col][r] / det;
+            }
+        }
+        // the translation is undone in the rotated and scaled frame
+        let moved = [0, 1, 2].map(|col| -(a[3][0] * inv[0][col] + a[3][1] * inv[1][col] + a[3][2] * inv[2][col]));
+        inv[3] = [moved[0], moved[1], moved[2], T::from(1.)];
+        Some(Self::from_rows(inv))
     }
 
-    pub fn transpose(self) -> Matrix4 {
-        Matrix4 { 
-            m00: (self.m00), m10: (self.m01), m20: (self.m02), m30: (self.m03),
-            m01: (self.m10), m11: (self.m11), m21: (self.m12), m31: (self.m13),
-            m02: (self.m20), m12: (self.m21), m22: (self.m22), m32: (self.m23),
-            m03: (self.m30), m13: (self.m31), m23: (self.m32), m33: (self.m33),
+    /// Splits an affine matrix into translation, rotation and scale, so that
+    /// `scale(s) * r.as_mat4() * translate(t)` gives it back. Mirroring ends up as a negative x scale.
+    pub fn decompose(&self) -> (Vector3<T>, Quat<T>, Vector3<T>) {
+        let a = self.rows();
+        let translation = Vector3::new(a[3][0], a[3][1], a[3][2]);
+
+        let mut axes = [0, 1, 2].map(|r| Vector3::new(a[r][0], a[r][1], a[r][2]));
+        let mut scale = Vector3::new(axes[0].length(), axes[1].length(), axes[2].length());
+        let c = self.cofactors3();
+        if a[0][0] * c[0][0] + a[0][1] * c[0][1] + a[0][2] * c[0][2] < T::from(0.) {
+            scale.x = -scale.x;
+        }
+        for (axis, s) in axes.iter_mut().zip([scale.x, scale.y, scale.z]) {
+            if s != T::from(0.) {
+                *axis /= s;
+            }
         }
+
+        (translation, Quat::from_basis(axes[0], axes[1], axes[2]), scale)
     }
 
-    pub fn project(fov: f32, aspect: f32, z_near: f32, z_far: f32) -> Matrix4{
-        let f = 1./(fov/2.).tan();
-        Self{
-            m00: f / aspect,      m10: 0.,           m20: 0.,                                m30: 0.,
-            m01: 0.,              m11: f,            m21: 0.,                                m31: 0.,
-            m02: 0.,              m12: 0.,           m22: (z_far + z_near)/(z_near - z_far), m32: -2. * z_far * z_near /(z_near - z_far),
-            m03: 0.,              m13: 0.,           m23: -1., m33: 1.,
+    /// Matrix for transforming normals: the inverse transpose of the upper 3x3, without translation.
+    pub fn normal_matrix(&self) -> Self {
+        let a = self.rows();
+        let c = self.cofactors3();
+        let det = a[0][0] * c[0][0] + a[0][1] * c[0][1] + a[0][2] * c[0][2];
+        // the cofactors alone still point the right way for degenerate matrices
+        let l = T::from(1.);
+        let scale = if det == T::from(0.) || !det.is_finite() { l } else { l / det };
+
+        let mut n = [[T::from(0.); 4]; 4];
+        for r in 0..3 {
+            for col in 0..3 {
+                n[r][col] = c[r][col] * scale;
+            }
         }
+        n[3][3] = l;
+        Self::from_rows(n)
     }
 
+    /// Converts the elements, e.g. to f32 once large coordinates have been made relative.
+    pub fn cast<U: Float>(&self) -> Mat4<U> {
+        Mat4::from_rows(self.rows().map(|row| row.map(|v| U::from_f64(v.to_f64()))))
+    }
+
+    fn rows(&self) -> [[T; 4]; 4] {
+        self.rows
+    }
+
+    fn from_rows(rows: [[T; 4]; 4]) -> Self {
+        Self { rows }
+    }
+
+    // 2x2 determinants of the top two and bottom two rows, for Laplace expansion
+    fn minors(&self) -> ([T; 6], [T; 6]) {
+        let a = self.rows();
+        let s = [
+            a[0][0] * a[1][1] - a[1][0] * a[0][1],
+            a[0][0] * a[1][2] - a[1][0] * a[0][2],
+            a[0][0] * a[1][3] - a[1][0] * a[0][3],
+            a[0][1] * a[1][2] - a[1][1] * a[0][2],
+            a[0][1] * a[1][3] - a[1][1] * a[0][3],
+            a[0][2] * a[1][3] - a[1][2] * a[0][3],
+        ];
+        let c = [
+            a[2][0] * a[3][1] - a[3][0] * a[2][1],
+            a[2][0] * a[3][2] - a[3][0] * a[2][2],
+            a[2][0] * a[3][3] - a[3][0] * a[2][3],
+            a[2][1] * a[3][2] - a[3][1] * a[2][2],
+            a[2][1] * a[3][3] - a[3][1] * a[2][3],
+            a[2][2] * a[3][3] - a[3][2] * a[2][3],
+        ];
+        (s, c)
+    }
+
+    // cofactor matrix of the upper 3x3
+    fn cofactors3(&self) -> [[T; 3]; 3] {
+        let a = self.rows();
+        [
+            [
+                a[1][1] * a[2][2] - a[1][2] * a[2][1],
+                a[1][2] * a[2][0] - a[1][0] * a[2][2],
+                a[1][0] * a[2][1] - a[1][1] * a[2][0],
+            ],
+            [
+                a[0][2] * a[2][1] - a[0][1] * a[2][2],
+                a[0][0] * a[2][2] - a[0][2] * a[2][0],
+                a[0][1] * a[2][0] - a[0][0] * a[2][1],
+            ],
+            [
+                a[0][1] * a[1][2] - a[0][2] * a[1][1],
+                a[0][2] * a[1][0] - a[0][0] * a[1][2],
+                a[0][0] * a[1][1] - a[0][1] * a[1][0],
+            ],
+        ]
+    }
+
+    /// Column-major f32 elements for uniform buffers, see [`Mat4::to_cols_array`].
     pub fn as_vec(&self) -> Vec<f32> {
-        let m = self.transpose();
-        let a = vec![
-             m.m00, m.m10, m.m20, m.m30,
-             m.m01, m.m11, m.m21, m.m31,
-             m.m02, m.m12, m.m22, m.m32,
-             m.m03, m.m13, m.m23, m.m33];
+        self.to_cols_array().to_vec()
+    }
 
-        a
+    /// Column-major f32 elements, which is how WGSL reads our rows, without allocating.
+    pub fn to_cols_array(&self) -> [f32; 16] {
+        let mut out = [0.; 16];
+        for (chunk, row) in out.chunks_exact_mut(4).zip(self.rows) {
+            for (value, v) in chunk.iter_mut().zip(row) {
+                *value = v.to_f64() as f32;
+            }
+        }
+        out
     }
 }
-
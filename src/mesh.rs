@@ -1,19 +1,329 @@
-use crate::buffers;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::io::{self, Write};
+use serde::Serialize;
 use crate::buffers::{IndexBuffer, VertexBuffer};
+use crate::geometry::Triangle;
+use crate::matrix::Matrix4;
+use crate::vector::Vector3;
 
+#[derive(Default)]
 pub struct Mesh {
     pub vb: VertexBuffer,
     pub ib: IndexBuffer,
+    /// Which vertex attributes came from the source rather than being filled in.
+    pub attributes: Attributes,
+    /// Runs of triangles by group and material, in order. Empty for meshes built in code.
+    pub submeshes: Vec<Submesh>,
+}
+
+/// Which optional vertex attributes a mesh was given. Missing normals are filled in with face
+/// normals, missing texture coordinates with zero and missing colours with red.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize)]
+pub struct Attributes {
+    pub normals: bool,
+    pub uvs: bool,
+    pub colors: bool,
+}
+
+/// Consecutive triangles sharing a group and a material, as named by an OBJ file's `o` / `g`
+/// and `usemtl`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Submesh {
+    pub group: String,
+    pub material: Option<String>,
+    pub first_triangle: usize,
+    pub triangle_count: usize,
 }
 
 impl Mesh {
     pub fn new(vb: VertexBuffer, ib: IndexBuffer) -> Self {
         Self {
             vb,
-            ib
+            ib,
+            attributes: Attributes::default(),
+            submeshes: Vec::new(),
+        }
+    }
+
+    /// An id per index such that corners at exactly the same position share one, for
+    /// connectivity between triangles whose vertices are not shared. Returns the number of ids.
+    pub fn get_position_ids(&self) -> (Vec<u32>, usize) {
+        let mut ids: HashMap<[u32; 3], u32> = HashMap::new();
+        let corners = self.ib.get_indices().iter().map(|&i| {
+            let p = self.vb.get_vertex(i as usize).get_position();
+            let next = ids.len() as u32;
+            *ids.entry([p.x.to_bits(), p.y.to_bits(), p.z.to_bits()]).or_insert(next)
+        }).collect();
+        (corners, ids.len())
+    }
+
+    /// The triangles on each edge between two distinct positions, keyed by the position ids of
+    /// [`Mesh::get_position_ids`], lower first.
+    pub(crate) fn get_edge_triangles(corners: &[u32]) -> HashMap<(u32, u32), Vec<usize>> {
+        let mut edges: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+        for t in 0..corners.len() / 3 {
+            for e in 0..3 {
+                let (a, b) = (corners[3 * t + e], corners[3 * t + (e + 1) % 3]);
+                if a != b {
+                    edges.entry((a.min(b), a.max(b))).or_default().push(t);
+                }
+            }
+        }
+        edges
+    }
+
+    /// Vertices expanded through the index buffer, three per triangle.
+    pub fn get_unindexed_vertices(&self) -> Vec<f32> {
+        self.ib.get_indices().iter().flat_map(|&i| self.vb.get_vertex(i as usize).as_vec()).collect()
+    }
+
+    pub fn get_triangle_count(&self) -> usize {
+        self.ib.get_indices().len() / 3
+    }
+
+    /// Positions of triangle `t`'s corners.
+    pub fn get_triangle(&self, t: usize) -> Triangle {
+        let corner = |c: usize| self.vb.get_vertex(self.ib.get_index(3 * t + c) as usize).get_position();
+        Triangle::new(corner(0), corner(1), corner(2))
+    }
+
+    /// Vertices of triangle `t` in the vertex buffer layout, e.g. to draw it on its own.
+    pub fn get_triangle_vertices(&self, t: usize) -> Vec<f32> {
+        (0..3).flat_map(|c| self.vb.get_vertex(self.ib.get_index(3 * t + c) as usize).as_vec()).collect()
+    }
+
+    /// The submeshes, or a single unnamed one covering every triangle when there are none.
+    pub fn get_submeshes(&self) -> Vec<Submesh> {
+        if !self.submeshes.is_empty() {
+            return self.submeshes.clone();
+        }
+        vec![Submesh { group: String::from("default"), material: None, first_triangle: 0, triangle_count: self.get_triangle_count() }]
+    }
+
+    /// Writes the mesh as OBJ text, with a position, texture coordinate and normal per vertex and
+    /// the groups and material names of the submeshes.
+    pub fn write_obj(&self, out: &mut impl Write) -> io::Result<()> {
+        for i in 0..self.vb.get_vertex_count() {
+            let vertex = self.vb.get_vertex(i);
+            let (p, n, [u, v]) = (vertex.get_position(), vertex.get_normal(), vertex.get_uv());
+            writeln!(out, "v {} {} {}", p.x, p.y, p.z)?;
+            writeln!(out, "vt {} {}", u, v)?;
+            writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?;
+        }
+        let indices = self.ib.get_indices();
+        let (mut group, mut material) = (None, None);
+        for submesh in &self.submeshes {
+            if group != Some(&submesh.group) {
+                writeln!(out, "g {}", submesh.group)?;
+                group = Some(&submesh.group);
+            }
+            if let Some(name) = submesh.material.as_ref().filter(|_| material != Some(&submesh.material)) {
+                writeln!(out, "usemtl {}", name)?;
+            }
+            material = Some(&submesh.material);
+            let range = 3 * submesh.first_triangle..3 * (submesh.first_triangle + submesh.triangle_count);
+            Self::write_obj_faces(out, &indices[range])?;
+        }
+        if self.submeshes.is_empty() {
+            Self::write_obj_faces(out, &indices)?;
+        }
+        Ok(())
+    }
+
+    fn write_obj_faces(out: &mut impl Write, indices: &[u32]) -> io::Result<()> {
+        for corners in indices.chunks_exact(3) {
+            let [a, b, c] = [corners[0] + 1, corners[1] + 1, corners[2] + 1];
+            writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+        }
+        Ok(())
+    }
+
+    /// Moves the positions by `m` and turns the normals with it. A mirroring `m` also reverses
+    /// the winding, so triangles keep facing the same side.
+    pub fn transform(&mut self, m: &Matrix4) {
+        let normal_matrix = m.normal_matrix();
+        for i in 0..self.vb.get_vertex_count() {
+            let mut vertex = self.vb.get_vertex(i);
+            vertex.set_position(m.transform_point(vertex.get_position()));
+            vertex.set_normal(normal_matrix.transform_direction(vertex.get_normal()).normalized());
+            self.vb.set_vertex(i, vertex);
+        }
+        if m.determinant() < 0. {
+            self.flip_winding();
         }
     }
+
+    /// Merges vertices that are equal in every attribute, so triangles share them. Returns the
+    /// number of vertices removed.
+    pub fn weld(&mut self) -> usize {
+        let mut ids: HashMap<Vec<u32>, u32> = HashMap::new();
+        let mut vb = VertexBuffer::new();
+        let remap: Vec<u32> = (0..self.vb.get_vertex_count()).map(|i| {
+            let vertex = self.vb.get_vertex(i);
+            let key = vertex.as_vec().iter().map(|v| v.to_bits()).collect();
+            *ids.entry(key).or_insert_with(|| {
+                vb.add_vertex(vertex);
+                vb.get_vertex_count() as u32 - 1
+            })
+        }).collect();
+        let mut ib = IndexBuffer::new();
+        for i in self.ib.get_indices() {
+            ib.add_index(remap[i as usize]);
+        }
+        let removed = self.vb.get_vertex_count() - vb.get_vertex_count();
+        self.vb = vb;
+        self.ib = ib;
+        removed
+    }
+
+    /// Replaces the normals with face normals, or when `smooth` with the normals of the triangles
+    /// around each position averaged by area, which rounds off hard edges.
+    pub fn recompute_normals(&mut self, smooth: bool) {
+        let indices = self.ib.get_indices();
+        let face_normals: Vec<Vector3<f32>> = (0..self.get_triangle_count()).map(|t| {
+            let triangle = self.get_triangle(t);
+            (triangle.b - triangle.a).cross(&(triangle.c - triangle.a))
+        }).collect();
+        if smooth {
+            let (corners, count) = self.get_position_ids();
+            let mut sums = vec![Vector3::default(); count];
+            for (c, &id) in corners.iter().enumerate() {
+                sums[id as usize] += face_normals[c / 3];
+            }
+            for (c, &i) in indices.iter().enumerate() {
+                let mut vertex = self.vb.get_vertex(i as usize);
+                vertex.set_normal(sums[corners[c] as usize].normalized());
+                self.vb.set_vertex(i as usize, vertex);
+            }
+        } else {
+            // a vertex shared between triangles cannot carry the normal of each
+            let (mut vb, mut ib) = (VertexBuffer::new(), IndexBuffer::new());
+            for (c, &i) in indices.iter().enumerate() {
+                let mut vertex = self.vb.get_vertex(i as usize);
+                vertex.set_normal(face_normals[c / 3].normalized());
+                ib.add_index(vb.get_vertex_count() as u32);
+                vb.add_vertex(vertex);
+            }
+            self.vb = vb;
+            self.ib = ib;
+        }
+        self.attributes.normals = true;
+    }
+
+    /// Adds the triangles of `other` after the existing ones, keeping its submeshes. Attributes
+    /// are only kept where both meshes have them.
+    pub fn append(&mut self, other: &Mesh) {
+        let (first_vertex, first_triangle) = (self.vb.get_vertex_count() as u32, self.get_triangle_count());
+        if first_triangle == 0 {
+            self.attributes = other.attributes;
+        } else if other.get_triangle_count() > 0 {
+            self.attributes.normals &= other.attributes.normals;
+            self.attributes.uvs &= other.attributes.uvs;
+            self.attributes.colors &= other.attributes.colors;
+        }
+        if !other.submeshes.is_empty() && self.submeshes.is_empty() && first_triangle > 0 {
+            self.submeshes = self.get_submeshes();
+        }
+        for i in 0..other.vb.get_vertex_count() {
+            self.vb.add_vertex(other.vb.get_vertex(i));
+        }
+        for i in other.ib.get_indices() {
+            self.ib.add_index(first_vertex + i);
+        }
+        if !self.submeshes.is_empty() || !other.submeshes.is_empty() {
+            self.submeshes.extend(other.get_submeshes().into_iter()
+                .filter(|s| s.triangle_count > 0)
+                .map(|s| Submesh { first_triangle: first_triangle + s.first_triangle, ..s }));
+        }
+    }
+
+    /// Makes every triangle part of one unnamed submesh, dropping group and material names.
+    pub fn merge_submeshes(&mut self) {
+        self.submeshes.clear();
+    }
+
+    /// Reverses the winding of every triangle. Normals are left untouched.
+    pub fn flip_winding(&mut self) {
+        for t in 0..self.get_triangle_count() {
+            self.ib.swap(3 * t + 1, 3 * t + 2);
+        }
+    }
+
+    /// Makes neighbouring triangles agree on their winding and turns every connected
+    /// part so that its triangles face outwards. Returns the number of flipped triangles.
+    pub fn orient_winding(&mut self) -> usize {
+        let indices = self.ib.get_indices();
+        let triangle_count = indices.len() / 3;
+
+        // vertices are not shared between faces, so triangles are connected through equal positions
+        let positions: Vec<Vector3<f32>> = indices.iter().map(|&i| self.vb.get_vertex(i as usize).get_position()).collect();
+        let (corners, _) = self.get_position_ids();
+
+        let edge = |t: usize, e: usize| (corners[3 * t + e], corners[3 * t + (e + 1) % 3]);
+        let has_edge = |t: usize, a: u32, b: u32| (0..3).any(|e| edge(t, e) == (a, b));
+
+        let edges = Self::get_edge_triangles(&corners);
+
+        let mut flip: Vec<Option<bool>> = vec![None; triangle_count];
+        for seed in 0..triangle_count {
+            if flip[seed].is_some() {
+                continue;
+            }
+            flip[seed] = Some(false);
+            let mut component = vec![seed];
+            let mut queue = VecDeque::from([seed]);
+
+            while let Some(t) = queue.pop_front() {
+                let flip_t = flip[t].unwrap();
+                for e in 0..3 {
+                    let (a, b) = if flip_t { let (a, b) = edge(t, e); (b, a) } else { edge(t, e) };
+                    // collapsed edges join nothing
+                    let Some(neighbours) = edges.get(&(a.min(b), a.max(b))) else { continue };
+                    for &n in neighbours {
+                        if flip[n].is_some() {
+                            continue;
+                        }
+                        // a consistent neighbour walks the shared edge in the opposite direction
+                        let flip_n = if has_edge(n, b, a) {
+                            false
+                        } else if has_edge(n, a, b) {
+                            true
+                        } else {
+                            continue;
+                        };
+                        flip[n] = Some(flip_n);
+                        component.push(n);
+                        queue.push_back(n);
+                    }
+                }
+            }
+
+            // positive signed volume around the centroid means the part faces outwards
+            let centroid = component.iter()
+                .flat_map(|&t| &positions[3 * t..3 * t + 3])
+                .fold(Vector3::default(), |sum, &p| sum + p) / (3 * component.len()) as f32;
+            let volume: f32 = component.iter().map(|&t| {
+                let (p0, p1, p2) = (positions[3 * t] - centroid, positions[3 * t + 1] - centroid, positions[3 * t + 2] - centroid);
+                let v = p0.dot(&p1.cross(&p2));
+                if flip[t].unwrap() { -v } else { v }
+            }).sum();
+            if volume < 0. {
+                for &t in &component {
+                    flip[t] = flip[t].map(|f| !f);
+                }
+            }
+        }
+
+        let mut flipped = 0;
+        for (t, f) in flip.iter().enumerate() {
+            if *f == Some(true) {
+                self.ib.swap(3 * t + 1, 3 * t + 2);
+                flipped += 1;
+            }
+        }
+        flipped
+    }
 }
 
 impl fmt::Display for Mesh {
@@ -25,11 +335,12 @@ impl fmt::Display for Mesh {
     }
 }
 
-impl Default for Mesh {
-    fn default() -> Self {
-        Self {
-            vb: VertexBuffer::new(),
-            ib: IndexBuffer::new()
-        }
+/// The root of the set holding `i` in a union-find forest, where roots are their own parents.
+/// Halves the path on the way up.
+pub(crate) fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
     }
-}
\ No newline at end of file
+    i
+}
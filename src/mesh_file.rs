@@ -0,0 +1,207 @@
+use std::fs;
+use std::io::{self, BufWriter, ErrorKind, Write};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::matrix::Matrix4;
+use crate::mesh::Mesh;
+use crate::model::Model;
+use crate::vector::Vector3;
+use crate::{gltf, ply, stl};
+
+/// The mesh file formats that can be read and written, chosen by the file extension.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MeshFormat {
+    Obj,
+    Stl,
+    Ply,
+    Gltf,
+    Glb,
+}
+
+impl MeshFormat {
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+        match extension.as_str() {
+            "obj" => Ok(Self::Obj),
+            "stl" => Ok(Self::Stl),
+            "ply" => Ok(Self::Ply),
+            "gltf" => Ok(Self::Gltf),
+            "glb" => Ok(Self::Glb),
+            _ => Err(io::Error::new(ErrorKind::InvalidInput, format!("{} is not an .obj, .stl, .ply, .gltf or .glb file", path.display()))),
+        }
+    }
+}
+
+/// Reads a mesh in the format of the file's extension.
+pub fn load_mesh(path: impl AsRef<Path>) -> io::Result<Mesh> {
+    let path = path.as_ref();
+    let format = MeshFormat::from_path(path)?;
+    let data = fs::read(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    match format {
+        MeshFormat::Obj => Model::parse_obj(&String::from_utf8_lossy(&data)),
+        MeshFormat::Stl => stl::read(&data),
+        MeshFormat::Ply => ply::read(&data),
+        MeshFormat::Gltf => gltf::read(&data, dir),
+        MeshFormat::Glb => gltf::read_glb(&data, dir),
+    }
+}
+
+/// Writes a mesh in the format of the file's extension.
+pub fn save_mesh(mesh: &Mesh, path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    let format = MeshFormat::from_path(path)?;
+    let mut out = BufWriter::new(fs::File::create(path)?);
+    match format {
+        MeshFormat::Obj => mesh.write_obj(&mut out),
+        MeshFormat::Stl => stl::write(mesh, &mut out),
+        MeshFormat::Ply => ply::write(mesh, &mut out),
+        MeshFormat::Gltf => gltf::write(mesh, &mut out),
+        MeshFormat::Glb => gltf::write_glb(mesh, &mut out),
+    }?;
+    out.flush()
+}
+
+/// Which axis points up. We use Y, like glTF; CAD and 3D printing tools mostly use Z.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, clap::ValueEnum, Serialize, Deserialize)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    /// The rotation about x that turns this up axis into `to`, keeping the coordinates
+    /// right-handed.
+    pub fn rotation_to(self, to: UpAxis) -> Matrix4 {
+        let mut m = Matrix4::identity();
+        let sign = match (self, to) {
+            (UpAxis::Z, UpAxis::Y) => 1.,
+            (UpAxis::Y, UpAxis::Z) => -1.,
+            _ => return m,
+        };
+        // y goes to -z and z to y for Z up to Y up, the other way round for Y up to Z up
+        m[(1, 1)] = 0.;
+        m[(1, 2)] = -sign;
+        m[(2, 2)] = 0.;
+        m[(2, 1)] = sign;
+        m
+    }
+}
+
+/// Whether x, y and z follow the right hand, as ours and OpenGL's do, or the left, as
+/// Direct3D's, Unity's and Unreal's do.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, clap::ValueEnum, Serialize, Deserialize)]
+pub enum Handedness {
+    #[default]
+    Right,
+    Left,
+}
+
+/// How the coordinates of a file map onto ours: Y up, right-handed and in metres. Kept on the
+/// [`Model`] loaded with them.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportOptions {
+    pub up_axis: UpAxis,
+    pub handedness: Handedness,
+    /// Metres per unit of the file, e.g. 0.001 for millimetres.
+    pub unit_scale: f32,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            up_axis: UpAxis::Y,
+            handedness: Handedness::Right,
+            unit_scale: 1.,
+        }
+    }
+}
+
+impl ImportOptions {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fails unless the unit scale is a positive number, as anything else would flatten, mirror
+    /// or break the model.
+    pub fn validate(&self) -> io::Result<()> {
+        if self.unit_scale > 0. && self.unit_scale.is_finite() {
+            Ok(())
+        } else {
+            Err(io::Error::new(ErrorKind::InvalidData, format!("the unit scale must be positive, not {}", self.unit_scale)))
+        }
+    }
+
+    /// Takes file coordinates to ours: mirrors the depth axis of left-handed files, turns the up
+    /// axis onto Y and scales to metres. Mirroring also reverses the winding in
+    /// [`Mesh::transform`], so faces that were front faces stay so.
+    pub fn get_matrix(&self) -> Matrix4 {
+        let mirror = match (self.handedness, self.up_axis) {
+            (Handedness::Right, _) => Vector3::splat(1.),
+            (Handedness::Left, UpAxis::Y) => Vector3::new(1., 1., -1.),
+            (Handedness::Left, UpAxis::Z) => Vector3::new(1., -1., 1.),
+        };
+        Matrix4::scale(mirror) * self.up_axis.rotation_to(UpAxis::Y) * Matrix4::scale(Vector3::splat(self.unit_scale))
+    }
+
+    /// Converts the positions and normals of a mesh read from the file.
+    pub fn apply(&self, mesh: &mut Mesh) {
+        if !self.is_default() {
+            mesh.transform(&self.get_matrix());
+        }
+    }
+}
+
+/// How to recompute normals, see [`Mesh::recompute_normals`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum Normals {
+    Flat,
+    Smooth,
+}
+
+/// Changes made to a mesh between reading and writing it, in the order of the fields.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ConvertOptions {
+    /// How to read the input's coordinates.
+    pub import: ImportOptions,
+    /// Up axis to write, from our Y up.
+    pub output_up: UpAxis,
+    pub normals: Option<Normals>,
+    /// Share vertices that are equal in every attribute, see [`Mesh::weld`].
+    pub weld: bool,
+    /// Drop groups and materials, see [`Mesh::merge_submeshes`].
+    pub merge: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            import: ImportOptions::default(),
+            output_up: UpAxis::Y,
+            normals: None,
+            weld: false,
+            merge: false,
+        }
+    }
+}
+
+impl ConvertOptions {
+    pub fn apply(&self, mesh: &mut Mesh) {
+        self.import.apply(mesh);
+        if self.output_up != UpAxis::Y {
+            mesh.transform(&UpAxis::Y.rotation_to(self.output_up));
+        }
+        if let Some(normals) = self.normals {
+            mesh.recompute_normals(normals == Normals::Smooth);
+        }
+        if self.weld {
+            mesh.weld();
+        }
+        if self.merge {
+            mesh.merge_submeshes();
+        }
+    }
+}
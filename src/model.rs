@@ -1,19 +1,29 @@
-use crate::transform::Transform;
-use crate::mesh::Mesh;
-use crate::buffers;
-use std::{fmt, fs};
-use crate::buffers::{IndexBuffer, Vertex, VertexBuffer};
+use crate::bvh::Bvh;
+use crate::geometry::{Aabb, Ray, TriangleHit};
+use crate::transform::DTransform;
+use crate::mesh::{Attributes, Mesh, Submesh};
+use crate::mesh_file::{load_mesh, ImportOptions};
+use std::{fmt, fs, io};
+use std::io::BufWriter;
+use crate::buffers::Vertex;
 use crate::camera::Camera;
-use crate::color::Color;
-use crate::shader::{AnyShader, BaseShader, PhongShader};
+use crate::shader::{AnyShader, BaseShader, Material, PhongShader};
+use crate::simplify::{Lod, LodOptions};
 use crate::vector::Vector3;
 
 pub struct Model {
     mesh: Mesh,
-    pub transform: Transform,
+    /// Placement in world space, in double precision for models far from the origin.
+    pub transform: DTransform,
+    pub material: Material,
     shader: AnyShader,
+    bvh: Bvh,
+    /// File the mesh was loaded from, if any.
+    path: Option<String>,
+    import: ImportOptions,
+    /// Coarser meshes for drawing further away, nearest first.
+    lods: Vec<Lod>,
     //texture
-    //AABB
 }
 
 impl fmt::Display for Model {
@@ -25,7 +35,7 @@ impl fmt::Display for Model {
 }
 
 impl Model {
-    pub fn draw(&self, cam: &Camera) {
+    pub fn draw(&self, _cam: &Camera) {
         // bind shader + textures
         self.shader.activate();
         // bind buffers
@@ -33,75 +43,210 @@ impl Model {
         // draw call
     }
     pub fn load_obj(&mut self, filepath: &str) -> &mut Model{
-        let data: String = fs::read_to_string(filepath).unwrap();
-        let mut vb = VertexBuffer::new();
-        let mut ib = IndexBuffer::new();
-        let mut count: u32 = 0;
+        self.try_load_obj(filepath).unwrap_or_else(|e| panic!("Could not load {}: {}", filepath, e))
+    }
+
+    /// Like [`Model::load_obj`], with an error for unreadable or malformed files.
+    pub fn try_load_obj(&mut self, filepath: &str) -> io::Result<&mut Model> {
+        let data: String = fs::read_to_string(filepath)?;
+        self.mesh = Self::parse_obj(&data)?;
+        self.bvh = Bvh::build(&self.mesh);
+        self.lods.clear();
+        self.path = Some(filepath.to_string());
+        self.import = ImportOptions::default();
+
+        Ok(self)
+    }
+
+    /// Loads an OBJ, STL, PLY or glTF file, chosen by its extension, see [`crate::mesh_file`].
+    pub fn try_load(&mut self, filepath: &str) -> io::Result<&mut Model> {
+        self.try_load_with(filepath, ImportOptions::default())
+    }
+
+    /// Like [`Model::try_load`], converting the file's coordinates to ours as `options` say.
+    /// Fails on a unit scale that is not a positive number.
+    pub fn try_load_with(&mut self, filepath: &str, options: ImportOptions) -> io::Result<&mut Model> {
+        options.validate()?;
+        self.mesh = load_mesh(filepath)?;
+        options.apply(&mut self.mesh);
+        self.bvh = Bvh::build(&self.mesh);
+        self.lods.clear();
+        self.path = Some(filepath.to_string());
+        self.import = options;
+
+        Ok(self)
+    }
+
+    /// How the file's coordinates were read, see [`Model::try_load_with`].
+    pub fn get_import_options(&self) -> ImportOptions {
+        self.import
+    }
+
+    /// Writes the mesh to an OBJ file. The transform and material are not part of it.
+    pub fn save_obj(&self, filepath: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(fs::File::create(filepath)?);
+        self.mesh.write_obj(&mut out)
+    }
+
+    /// One model per object or group (`o` / `g`) of an OBJ file, with its name, for importing
+    /// into a [`crate::scene::Scene`]. Faces before the first name form a group called "default".
+    pub fn load_obj_groups(filepath: &str) -> io::Result<Vec<(String, Model)>> {
+        let data: String = fs::read_to_string(filepath)?;
+        let mesh = Self::parse_obj(&data)?;
+        Ok(Self::split_groups(&mesh).into_iter().map(|(name, mesh)| {
+            let bvh = Bvh::build(&mesh);
+            (name, Model { mesh, bvh, ..Model::default() })
+        }).collect())
+    }
 
+    /// A mesh per run of submeshes with the same group.
+    fn split_groups(mesh: &Mesh) -> Vec<(String, Mesh)> {
+        let mut groups: Vec<(String, Mesh)> = Vec::new();
+        for (i, submesh) in mesh.submeshes.iter().enumerate() {
+            if i == 0 || mesh.submeshes[i - 1].group != submesh.group {
+                let group = Mesh { attributes: mesh.attributes, ..Mesh::default() };
+                groups.push((submesh.group.clone(), group));
+            }
+            let (_, group) = groups.last_mut().unwrap();
+            let first_triangle = group.get_triangle_count();
+            for t in submesh.first_triangle..submesh.first_triangle + submesh.triangle_count {
+                for c in 0..3 {
+                    group.ib.add_index(group.vb.get_vertex_count() as u32);
+                    group.vb.add_vertex(mesh.vb.get_vertex(mesh.ib.get_index(3 * t + c) as usize));
+                }
+            }
+            group.submeshes.push(Submesh { first_triangle, ..submesh.clone() });
+        }
+        if groups.is_empty() {
+            groups.push((String::from("default"), Mesh::default()));
+        }
+        groups
+    }
+
+    /// Reads positions with optional colours, normals, texture coordinates, groups, materials and
+    /// faces, which may leave out texture coordinates and normals and may be polygons; polygons
+    /// are split into a fan of triangles. Every corner gets a vertex of its own.
+    pub(crate) fn parse_obj(data: &str) -> io::Result<Mesh> {
+        let mut mesh = Mesh::default();
         let mut positions: Vec<[f32;3]> = Vec::new();
+        let mut colors: Vec<Option<[f32;4]>> = Vec::new();
         let mut normals: Vec<[f32;3]> = Vec::new();
         let mut uvs: Vec<[f32;2]> = Vec::new();
+        let mut current = Submesh { group: String::from("default"), material: None, first_triangle: 0, triangle_count: 0 };
+        let (mut all_normals, mut all_uvs, mut all_colors) = (true, true, true);
 
-        for line in data.lines() {
-            if line.is_empty() {continue;}
-            let (pattern, values) = line.split_once(' ').unwrap();
-            println!("Pattern: {}, Values: {}", pattern, values);
+        for (number, line) in data.lines().enumerate() {
+            let error = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", number + 1, message));
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((pattern, values)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+            let values: Vec<&str> = values.split_whitespace().collect();
+            let floats = |count: usize| -> io::Result<Vec<f32>> {
+                if values.len() < count {
+                    return Err(error(format!("{} needs {} numbers", pattern, count)));
+                }
+                values.iter().map(|v| v.parse::<f32>().map_err(|_| error(format!("{} is not a number", v)))).collect()
+            };
             match pattern {
                 "v" => {
-                    let values: Vec<&str> = values.split(' ').collect();
-
-                    let pos= [Self::parse_f32(values[0]), Self::parse_f32(values[1]), Self::parse_f32(values[2])];
-
-                    positions.push(pos);
+                    let v = floats(3)?;
+                    positions.push([v[0], v[1], v[2]]);
+                    // a common extension puts a colour after the position
+                    colors.push((v.len() >= 6).then(|| [v[3], v[4], v[5], 1.]));
                 }
                 "vn" => {
-                    let values: Vec<&str> = values.split(' ').collect();
-
-                    let normal= [Self::parse_f32(values[0]), Self::parse_f32(values[1]), Self::parse_f32(values[2])];
-
-                    normals.push(normal);
+                    let v = floats(3)?;
+                    normals.push([v[0], v[1], v[2]]);
                 }
                 "vt" => {
-                    let values: Vec<&str> = values.split(' ').collect();
-
-                    let uv = [Self::parse_f32(values[0]), Self::parse_f32(values[1])];
-
-                    uvs.push(uv);
+                    let v = floats(1)?;
+                    uvs.push([v[0], v.get(1).copied().unwrap_or(0.)]);
+                }
+                "o" | "g" | "usemtl" => {
+                    let name = values.join(" ");
+                    let mut next = current.clone();
+                    match pattern {
+                        "usemtl" => next.material = Some(name),
+                        _ => next.group = if name.is_empty() { String::from("default") } else { name },
+                    }
+                    next.first_triangle = mesh.get_triangle_count();
+                    next.triangle_count = 0;
+                    let finished = std::mem::replace(&mut current, next);
+                    if finished.triangle_count > 0 {
+                        mesh.submeshes.push(finished);
+                    }
                 }
                 "f" => {
-                    let indices = values.split(' ');
-                    if indices.clone().count() != 3 {
-                        eprintln!("{}", "Faces are not triangulated, render will probably suck")
+                    if values.len() < 3 {
+                        return Err(error(String::from("a face needs at least three corners")));
+                    }
+                    let mut corners = Vec::with_capacity(values.len());
+                    for corner in &values {
+                        let mut fields = corner.split('/');
+                        let position = Self::parse_index(fields.next(), positions.len()).map_err(&error)?
+                            .ok_or_else(|| error(format!("{} has no position", corner)))?;
+                        let uv = Self::parse_index(fields.next(), uvs.len()).map_err(&error)?;
+                        let normal = Self::parse_index(fields.next(), normals.len()).map_err(&error)?;
+                        corners.push((position, uv, normal));
                     }
-                    for index in indices {
-                        let index: Vec<&str> = index.split('/').collect();
-                        let red = [1., 0., 0., 1.];
-                        let vertex = Vertex::new(
-                            positions[(Self::parse_u32(index[0]) - 1) as usize],
-                            normals[(Self::parse_u32(index[2]) - 1) as usize],
-                            uvs[(Self::parse_u32(index[1]) - 1) as usize],
-                            red);
-
-                        vb.add_vertex(vertex);
-                        //ib.add_index(Self::parse_u32(index[0]) - 1);
-                        ib.add_index(count);
-                        count+=1
+                    let face_normal = {
+                        let p = |i: usize| Vector3::new(positions[corners[i].0][0], positions[corners[i].0][1], positions[corners[i].0][2]);
+                        let mut n = (p(1) - p(0)).cross(&(p(2) - p(0)));
+                        if n.length_squared() > 0. { n.normalize(); }
+                        [n.x, n.y, n.z]
+                    };
+                    for i in 1..corners.len() - 1 {
+                        for &(position, uv, normal) in [&corners[0], &corners[i], &corners[i + 1]] {
+                            all_normals &= normal.is_some();
+                            all_uvs &= uv.is_some();
+                            all_colors &= colors[position].is_some();
+                            let vertex = Vertex::new(
+                                positions[position],
+                                normal.map_or(face_normal, |n| normals[n]),
+                                uv.map_or([0., 0.], |t| uvs[t]),
+                                colors[position].unwrap_or([1., 0., 0., 1.]));
+                            mesh.ib.add_index(mesh.vb.get_vertex_count() as u32);
+                            mesh.vb.add_vertex(vertex);
+                        }
+                        current.triangle_count += 1;
                     }
                 }
                 _ => {}
             }
         }
 
-        self.mesh = Mesh::new(vb, ib);
+        if current.triangle_count > 0 {
+            mesh.submeshes.push(current);
+        }
+        // a file without faces still loads, as an empty model
+        let has_faces = mesh.get_triangle_count() > 0;
+        mesh.attributes = Attributes { normals: has_faces && all_normals, uvs: has_faces && all_uvs, colors: has_faces && all_colors };
+        Ok(mesh)
+    }
 
-        self
+    /// A 1-based OBJ index, or counted back from the end when negative, into a list of `len`.
+    /// An empty field means the attribute is left out.
+    fn parse_index(field: Option<&str>, len: usize) -> Result<Option<usize>, String> {
+        let Some(field) = field.filter(|f| !f.is_empty()) else {
+            return Ok(None);
+        };
+        let index: i64 = field.parse().map_err(|_| format!("{} is not an index", field))?;
+        let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+        if resolved < 0 || resolved >= len as i64 {
+            return Err(format!("index {} is out of range", index));
+        }
+        Ok(Some(resolved as usize))
     }
 
-    fn parse_f32(value: &str) -> f32 {
-        value.parse::<f32>().unwrap()
+    /// The file given to [`Model::load_obj`] or [`Model::try_load`]. Models split off by
+    /// [`Model::load_obj_groups`] have none, as they are only part of their file.
+    pub fn get_path(&self) -> Option<&str> {
+        self.path.as_deref()
     }
-    fn parse_u32(value: &str) -> u32 {
-        value.parse::<u32>().unwrap()
+
+    pub fn get_mesh(&self) -> &Mesh {
+        &self.mesh
     }
 
     pub fn get_indices(&self) -> Vec<u32> {
@@ -112,6 +257,68 @@ impl Model {
         self.mesh.vb.get_vertices()
     }
 
+    pub fn get_unindexed_vertices(&self) -> Vec<f32> {
+        self.mesh.get_unindexed_vertices()
+    }
+
+    pub fn get_vertex_count(&self) -> usize {
+        self.mesh.vb.get_vertex_count()
+    }
+
+    /// The nearest triangle along `ray`, given in the model's own space, see [`crate::picking`].
+    pub fn raycast(&self, ray: &Ray) -> Option<(usize, TriangleHit)> {
+        self.bvh.closest_hit(ray)
+    }
+
+    /// Bounds of the mesh in the model's own space.
+    pub fn get_bounds(&self) -> Aabb {
+        self.bvh.get_bounds()
+    }
+
+    /// Bounds in world space relative to `origin`, e.g. the camera position.
+    pub fn get_world_bounds(&self, origin: Vector3<f64>) -> Aabb {
+        self.get_bounds().transformed(&self.transform.relative_to(origin).as_matrix())
+    }
+
+    /// Makes the levels of detail drawn in place of the mesh further from the camera. Picking
+    /// still uses the full mesh.
+    pub fn generate_lods(&mut self, options: &LodOptions) {
+        self.lods = self.mesh.generate_lods(options);
+    }
+
+    pub fn get_lods(&self) -> &[Lod] {
+        &self.lods
+    }
+
+    /// Distance from the camera to the nearest point of the world bounds, 0 from inside them.
+    pub fn get_camera_distance(&self, camera: &Camera) -> f64 {
+        let bounds = self.get_world_bounds(camera.get_position());
+        let nearest = Vector3::default().max(bounds.min).min(bounds.max);
+        nearest.length() as f64
+    }
+
+    /// The mesh to draw at `distance` from the camera: the coarsest level of detail drawn from
+    /// nearer than that, allowing for the scale of the transform, or the full mesh.
+    pub fn get_lod_mesh(&self, distance: f64) -> &Mesh {
+        let scale = self.transform.get_scale();
+        let scale = scale.x.abs().max(scale.y.abs()).max(scale.z.abs());
+        self.lods.iter().rev()
+            .find(|lod| lod.distance as f64 * scale <= distance)
+            .map_or(&self.mesh, |lod| &lod.mesh)
+    }
+
+    pub fn get_triangle_vertices(&self, t: usize) -> Vec<f32> {
+        self.mesh.get_triangle_vertices(t)
+    }
+
+    pub fn flip_winding(&mut self) {
+        self.mesh.flip_winding();
+    }
+
+    pub fn orient_winding(&mut self) -> usize {
+        self.mesh.orient_winding()
+    }
+
     pub fn update(&mut self) {
 
     }
@@ -121,8 +328,13 @@ impl Default for Model {
     fn default() -> Self {
         Self {
             mesh: Mesh::default(),
-            transform: Transform::default(),
-            shader: AnyShader::PhongShader(PhongShader::default())
+            transform: DTransform::default(),
+            material: Material::default(),
+            shader: AnyShader::PhongShader(PhongShader::default()),
+            bvh: Bvh::default(),
+            path: None,
+            import: ImportOptions::default(),
+            lods: Vec::new(),
         }
     }
 }
@@ -0,0 +1,56 @@
+use crate::camera::Camera;
+use crate::geometry::Ray;
+use crate::model::Model;
+use crate::vector::Vector3;
+
+/// What a ray hit: which model, which of its triangles and where.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Pick {
+    /// Index into the models that were searched.
+    pub model: usize,
+    pub triangle: usize,
+    /// World space hit point, in double precision like model placements.
+    pub point: Vector3<f64>,
+    /// Distance from the ray origin in world units.
+    pub distance: f32,
+    /// Weights of the triangle's corners at the hit point.
+    pub barycentric: Vector3<f32>,
+    /// World space normal of the triangle, following its winding.
+    pub normal: Vector3<f32>,
+}
+
+/// The model under a point of the viewport, see [`Camera::screen_ray`].
+pub fn pick_screen(models: &[Model], camera: &Camera, x: f32, y: f32, width: f32, height: f32) -> Option<Pick> {
+    pick(models, camera, &camera.screen_ray(x, y, width, height))
+}
+
+/// The nearest model along `ray`, which is in camera-relative world space.
+pub fn pick(models: &[Model], camera: &Camera, ray: &Ray) -> Option<Pick> {
+    let origin = camera.get_position();
+    let mut closest: Option<Pick> = None;
+
+    for (index, model) in models.iter().enumerate() {
+        let relative = model.transform.relative_to(origin);
+        let to_local = relative.inverse_matrix();
+        // not normalized, so distances along the local ray stay in world units
+        let local = Ray {
+            origin: to_local.transform_point(ray.origin),
+            direction: to_local.transform_direction(ray.direction),
+        };
+        let Some((triangle, hit)) = model.raycast(&local) else {
+            continue;
+        };
+        if closest.is_some_and(|c| c.distance <= hit.distance) {
+            continue;
+        }
+        closest = Some(Pick {
+            model: index,
+            triangle,
+            point: origin + ray.at(hit.distance).cast(),
+            distance: hit.distance,
+            barycentric: hit.barycentric,
+            normal: relative.as_matrix().normal_matrix().transform_direction(hit.normal).normalized(),
+        });
+    }
+    closest
+}
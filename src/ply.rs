@@ -0,0 +1,291 @@
+//! PLY, the polygon format of scanners and point cloud tools: a header declaring elements and
+//! their properties, followed by the data as text or binary.
+
+use std::io::{self, ErrorKind, Write};
+use crate::buffers::Vertex;
+use crate::mesh::{Attributes, Mesh};
+use crate::vector::Vector3;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.into())
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Encoding {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+#[derive(Clone, Debug)]
+struct Property {
+    name: String,
+    scalar: Scalar,
+    /// The type of the length in front of a list property.
+    list: Option<Scalar>,
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return Err(invalid(format!("unknown property type {}", name))),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// Colours stored as integers go from 0 to 255, as floats from 0 to 1.
+    fn is_integer(self) -> bool {
+        !matches!(self, Self::F32 | Self::F64)
+    }
+}
+
+/// Reads the values of the file body one at a time, whatever the encoding.
+struct Values<'a> {
+    encoding: Encoding,
+    data: &'a [u8],
+    words: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl Values<'_> {
+    fn next(&mut self, scalar: Scalar) -> io::Result<f64> {
+        if self.encoding == Encoding::Ascii {
+            let word = self.words.next().ok_or_else(|| invalid("the file ends early"))?;
+            return word.parse().map_err(|_| invalid(format!("{} is not a number", word)));
+        }
+        let size = scalar.size();
+        if self.data.len() < size {
+            return Err(invalid("the file ends early"));
+        }
+        let (bytes, rest) = self.data.split_at(size);
+        self.data = rest;
+        let mut b = [0u8; 8];
+        b[..size].copy_from_slice(bytes);
+        if self.encoding == Encoding::BigEndian {
+            b[..size].reverse();
+        }
+        Ok(match scalar {
+            Scalar::I8 => b[0] as i8 as f64,
+            Scalar::U8 => b[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(b),
+        })
+    }
+}
+
+fn read_header(data: &[u8]) -> io::Result<(Encoding, Vec<Element>, usize)> {
+    let end = data.windows(10).position(|w| w == b"end_header")
+        .ok_or_else(|| invalid("not a PLY file: the header has no end_header"))?;
+    let body = end + 10 + data[end + 10..].iter().position(|&b| b == b'\n').map_or(0, |p| p + 1);
+    let header = std::str::from_utf8(&data[..end]).map_err(|e| invalid(e.to_string()))?;
+
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err(invalid("not a PLY file: it does not start with ply"));
+    }
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["format", format, _] => encoding = Some(match format {
+                "ascii" => Encoding::Ascii,
+                "binary_little_endian" => Encoding::LittleEndian,
+                "binary_big_endian" => Encoding::BigEndian,
+                _ => return Err(invalid(format!("unknown format {}", format))),
+            }),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid(format!("{} is not a count", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", length, scalar, name] => {
+                let property = Property { name: name.to_string(), scalar: Scalar::parse(scalar)?, list: Some(Scalar::parse(length)?) };
+                elements.last_mut().ok_or_else(|| invalid("a property before any element"))?.properties.push(property);
+            }
+            ["property", scalar, name] => {
+                let property = Property { name: name.to_string(), scalar: Scalar::parse(scalar)?, list: None };
+                elements.last_mut().ok_or_else(|| invalid("a property before any element"))?.properties.push(property);
+            }
+            _ => {}
+        }
+    }
+    let encoding = encoding.ok_or_else(|| invalid("the header has no format"))?;
+    Ok((encoding, elements, body))
+}
+
+/// Reads text or binary PLY: vertex positions with optional normals (`nx`), texture coordinates
+/// (`s` / `u`) and colours (`red`), and faces as lists of vertex indices. Polygons are split into
+/// a fan of triangles. Other elements and properties are skipped.
+pub fn read(data: &[u8]) -> io::Result<Mesh> {
+    let (encoding, elements, body) = read_header(data)?;
+    let mut values = Values {
+        encoding,
+        data: &data[body..],
+        words: if encoding == Encoding::Ascii { std::str::from_utf8(&data[body..]).map_err(|e| invalid(e.to_string()))? } else { "" }
+            .split_ascii_whitespace(),
+    };
+
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut faces: Vec<Vec<usize>> = Vec::new();
+    let mut attributes = Attributes::default();
+    for element in &elements {
+        let find = |names: &[&str]| element.properties.iter().position(|p| names.contains(&p.name.as_str()));
+        let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+        let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+        let uv = [find(&["s", "u", "texture_u"]), find(&["t", "v", "texture_v"])];
+        let color = [find(&["red"]), find(&["green"]), find(&["blue"]), find(&["alpha"])];
+        if element.name == "vertex" {
+            attributes = Attributes {
+                normals: normal.iter().all(Option::is_some),
+                uvs: uv.iter().all(Option::is_some),
+                colors: color[..3].iter().all(Option::is_some),
+            };
+        }
+        for _ in 0..element.count {
+            let mut scalars = Vec::with_capacity(element.properties.len());
+            let mut indices = Vec::new();
+            for property in &element.properties {
+                match property.list {
+                    Some(length) => {
+                        let length = values.next(length)? as usize;
+                        let list = (0..length).map(|_| values.next(property.scalar)).collect::<io::Result<Vec<f64>>>()?;
+                        if matches!(property.name.as_str(), "vertex_indices" | "vertex_index") {
+                            indices = list.into_iter().map(|i| i as usize).collect();
+                        }
+                        scalars.push(0.);
+                    }
+                    None => scalars.push(values.next(property.scalar)?),
+                }
+            }
+            match element.name.as_str() {
+                "vertex" => {
+                    let get = |i: Option<usize>| i.map_or(0., |i| scalars[i] as f32);
+                    let channel = |i: Option<usize>| i.map_or(1., |i| {
+                        let value = scalars[i] as f32;
+                        if element.properties[i].scalar.is_integer() { value / 255. } else { value }
+                    });
+                    vertices.push(Vertex::new(
+                        position.map(get),
+                        normal.map(get),
+                        uv.map(get),
+                        if attributes.colors { color.map(channel) } else { [1., 0., 0., 1.] },
+                    ));
+                }
+                "face" => faces.push(indices),
+                _ => {}
+            }
+        }
+    }
+
+    let mut mesh = Mesh::default();
+    for face in &faces {
+        if let Some(&i) = face.iter().find(|&&i| i >= vertices.len()) {
+            return Err(invalid(format!("vertex {} is out of range", i)));
+        }
+        for i in 1..face.len().saturating_sub(1) {
+            let mut corners = [vertices[face[0]], vertices[face[i]], vertices[face[i + 1]]];
+            if !attributes.normals {
+                let p = corners.map(|c| c.get_position());
+                let n = (p[1] - p[0]).cross(&(p[2] - p[0])).normalized();
+                corners.iter_mut().for_each(|c| c.set_normal(n));
+            }
+            for corner in corners {
+                mesh.ib.add_index(mesh.vb.get_vertex_count() as u32);
+                mesh.vb.add_vertex(corner);
+            }
+        }
+    }
+    let has_faces = mesh.get_triangle_count() > 0;
+    mesh.attributes = Attributes {
+        normals: has_faces && attributes.normals,
+        uvs: has_faces && attributes.uvs,
+        colors: has_faces && attributes.colors,
+    };
+    Ok(mesh)
+}
+
+/// Writes binary little endian PLY with positions and normals, plus texture coordinates and
+/// colours when the mesh has them.
+pub fn write(mesh: &Mesh, out: &mut impl Write) -> io::Result<()> {
+    let vertex_count = mesh.vb.get_vertex_count();
+    writeln!(out, "ply")?;
+    writeln!(out, "format binary_little_endian 1.0")?;
+    writeln!(out, "comment written by triangle")?;
+    writeln!(out, "element vertex {}", vertex_count)?;
+    for name in ["x", "y", "z", "nx", "ny", "nz"] {
+        writeln!(out, "property float {}", name)?;
+    }
+    if mesh.attributes.uvs {
+        writeln!(out, "property float s")?;
+        writeln!(out, "property float t")?;
+    }
+    if mesh.attributes.colors {
+        for name in ["red", "green", "blue", "alpha"] {
+            writeln!(out, "property uchar {}", name)?;
+        }
+    }
+    writeln!(out, "element face {}", mesh.get_triangle_count())?;
+    writeln!(out, "property list uchar uint vertex_indices")?;
+    writeln!(out, "end_header")?;
+
+    for i in 0..vertex_count {
+        let vertex = mesh.vb.get_vertex(i);
+        let (p, n): (Vector3<f32>, Vector3<f32>) = (vertex.get_position(), vertex.get_normal());
+        for f in [p.x, p.y, p.z, n.x, n.y, n.z] {
+            out.write_all(&f.to_le_bytes())?;
+        }
+        if mesh.attributes.uvs {
+            for f in vertex.get_uv() {
+                out.write_all(&f.to_le_bytes())?;
+            }
+        }
+        if mesh.attributes.colors {
+            out.write_all(&vertex.get_color().map(|c| (c.clamp(0., 1.) * 255.).round() as u8))?;
+        }
+    }
+    for corners in mesh.ib.get_indices().chunks_exact(3) {
+        out.write_all(&[3])?;
+        for i in corners {
+            out.write_all(&i.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
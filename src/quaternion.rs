@@ -1,17 +1,24 @@
-use std::ops::{Div, DivAssign, Mul, MulAssign};
+use std::ops::{Div, DivAssign, Mul, MulAssign, Neg};
 
-use crate::{matrix::Matrix4, vector::Vector3};
+use crate::{matrix::Mat4, vector::{Float, Vector3}};
 
+/// Quaternion of any float type, see [`Quaternion`] and [`DQuaternion`].
+/// Aligned so the four components load as one SIMD register.
 #[derive(Clone, Copy)]
-pub struct Quaternion{
-    w: f32,
-    i: f32,
-    j: f32,
-    k: f32,
+#[repr(C, align(16))]
+pub struct Quat<T>{
+    w: T,
+    i: T,
+    j: T,
+    k: T,
 }
 
-impl Quaternion{
-    pub fn new(w: f32, i: f32, j: f32, k: f32) -> Self{
+pub type Quaternion = Quat<f32>;
+/// Double precision rotation, to go with [`crate::matrix::DMatrix4`].
+pub type DQuaternion = Quat<f64>;
+
+impl<T: Float> Quat<T>{
+    pub fn new(w: T, i: T, j: T, k: T) -> Self{
         Self{
             w,
             i,
@@ -20,38 +27,38 @@ impl Quaternion{
         }
     }
 
-    pub fn from_vector_3(v: Vector3<f32>) -> Self {
+    pub fn from_vector_3(v: Vector3<T>) -> Self {
         Self::from_vec(v.as_vec())
     }
 
-    pub fn from_vec(v: Vec<f32>) -> Self {
+    pub fn from_vec(v: Vec<T>) -> Self {
         match v.len() {
             0 => {
                 Self::default()
             },
             1 => {
-                Self::from_scalar(*v.get(0).unwrap())
+                Self::from_scalar(*v.first().unwrap())
             },
             2 => {
-                Self::new(*v.get(0).unwrap(), *v.get(1).unwrap(), *v.get(1).unwrap(), *v.get(1).unwrap())
+                Self::new(*v.first().unwrap(), *v.get(1).unwrap(), *v.get(1).unwrap(), *v.get(1).unwrap())
             },
             3 => {
-                Self::new(0.0, *v.get(0).unwrap(), *v.get(1).unwrap(), *v.get(2).unwrap())
+                Self::new(T::from(0.), *v.first().unwrap(), *v.get(1).unwrap(), *v.get(2).unwrap())
             },
             _ => {
-                Self::new(*v.get(0).unwrap(), *v.get(1).unwrap(), *v.get(2).unwrap(), *v.get(3).unwrap())
+                Self::new(*v.first().unwrap(), *v.get(1).unwrap(), *v.get(2).unwrap(), *v.get(3).unwrap())
             }
         }
     }
 
-    pub fn from_scalar(v: f32) -> Self {
+    pub fn from_scalar(v: T) -> Self {
         Self::new(v, v, v, v)
     }
 
-    pub fn from_angle_axis(angle: f32, mut axis: Vector3<f32>) -> Self {
-        let n: &Vector3<f32> = axis.normalize();
-        let half_sin = f32::sin(angle/2.0);
-        let half_cos = f32::cos(angle/2.0);
+    pub fn from_angle_axis(angle: T, mut axis: Vector3<T>) -> Self {
+        let n: &Vector3<T> = axis.normalize();
+        let half_sin = (angle / T::from(2.)).sin();
+        let half_cos = (angle / T::from(2.)).cos();
         Self{
             w: half_cos,
             i: half_sin * n.x,
@@ -60,33 +67,126 @@ impl Quaternion{
         }
     }
 
-    pub fn from_euler_angles(yaw: f32, pitch: f32, roll: f32) -> Self {
-        let half_yaw = 0.5 * yaw;
-        let half_pitch = 0.5 * pitch;
-        let half_roll = 0.5 * roll;
-        
-        let cx = f32::cos(half_pitch);
-        let cy = f32::cos(half_yaw);
-        let cz = f32::cos(half_roll);
+    /// Yaw about y, then pitch about x, then roll about z, each about the already turned axes,
+    /// the way a fly camera turns.
+    pub fn from_euler_angles(yaw: T, pitch: T, roll: T) -> Self {
+        let half = T::from(0.5);
+        let half_yaw = half * yaw;
+        let half_pitch = half * pitch;
+        let half_roll = half * roll;
+
+        let cx = half_pitch.cos();
+        let cy = half_yaw.cos();
+        let cz = half_roll.cos();
 
-        let sx = f32::sin(half_pitch);
-        let sy = f32::sin(half_yaw);
-        let sz = f32::sin(half_roll);
+        let sx = half_pitch.sin();
+        let sy = half_yaw.sin();
+        let sz = half_roll.sin();
 
         Self {
             w: cx * cy * cz + sx * sy * sz,
-            i: sx * cy * cz - cx * sy * sz,
-            j: cx * sy * cz + sx * cy * sz,
+            i: sx * cy * cz + cx * sy * sz,
+            j: cx * sy * cz - sx * cy * sz,
             k: cx * cy * sz - sx * sy * cz,
         }
     }
 
-    pub fn from_euler_vector(angles: Vector3<f32>) -> Self {
-        Quaternion::from_euler_angles(angles.x, angles.y, angles.z)
+    /// Rotation taking the x, y and z axes to `right`, `up` and `back`, which must be orthonormal.
+    pub fn from_basis(right: Vector3<T>, up: Vector3<T>, back: Vector3<T>) -> Self {
+        // r[row][column], the columns being the rotated axes
+        let r = [
+            [right.x, up.x, back.x],
+            [right.y, up.y, back.y],
+            [right.z, up.z, back.z],
+        ];
+        let trace = r[0][0] + r[1][1] + r[2][2];
+        let (l, two, quarter) = (T::from(1.), T::from(2.), T::from(0.25));
+
+        // pick the largest of w, i, j, k to divide by, which keeps the result stable
+        if trace > T::from(0.) {
+            let s = two * (trace + l).sqrt();
+            Self::new(quarter * s, (r[2][1] - r[1][2]) / s, (r[0][2] - r[2][0]) / s, (r[1][0] - r[0][1]) / s)
+        } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+            let s = two * (l + r[0][0] - r[1][1] - r[2][2]).sqrt();
+            Self::new((r[2][1] - r[1][2]) / s, quarter * s, (r[0][1] + r[1][0]) / s, (r[0][2] + r[2][0]) / s)
+        } else if r[1][1] > r[2][2] {
+            let s = two * (l + r[1][1] - r[0][0] - r[2][2]).sqrt();
+            Self::new((r[0][2] - r[2][0]) / s, (r[0][1] + r[1][0]) / s, quarter * s, (r[1][2] + r[2][1]) / s)
+        } else {
+            let s = two * (l + r[2][2] - r[0][0] - r[1][1]).sqrt();
+            Self::new((r[1][0] - r[0][1]) / s, (r[0][2] + r[2][0]) / s, (r[1][2] + r[2][1]) / s, quarter * s)
+        }
+    }
+
+    /// Inverse of [`Quat::as_mat4`]: the rotation part of `m`, which must not contain scale.
+    pub fn from_rotation_matrix(m: Mat4<T>) -> Self {
+        // rows hold the rotated axes, since vectors are multiplied from the left
+        Self::from_basis(
+            Vector3::new(m[(0, 0)], m[(0, 1)], m[(0, 2)]),
+            Vector3::new(m[(1, 0)], m[(1, 1)], m[(1, 2)]),
+            Vector3::new(m[(2, 0)], m[(2, 1)], m[(2, 2)]),
+        )
+    }
+
+    /// Euler angles by axis: pitch in x, yaw in y and roll in z, see [`Quat::from_euler_angles`].
+    pub fn from_euler_vector(angles: Vector3<T>) -> Self {
+        Self::from_euler_angles(angles.y, angles.x, angles.z)
+    }
+
+    /// Shortest rotation turning the direction `from` into the direction `to`.
+    pub fn from_to_rotation(mut from: Vector3<T>, mut to: Vector3<T>) -> Self {
+        from.normalize();
+        to.normalize();
+        let d = from.dot(&to);
+        let (o, l) = (T::from(0.), T::from(1.));
+        if d < -l + T::from(1e-6) {
+            // opposite directions, turn half way around any perpendicular axis
+            let other = if from.x.abs() < T::from(0.9) { Vector3::new(l, o, o) } else { Vector3::new(o, l, o) };
+            return Self::from_angle_axis(T::PI, from.cross(&other));
+        }
+        // (1 + cos, sin * axis) normalizes to (cos/2, sin/2 * axis)
+        let axis = from.cross(&to);
+        Self::new(l + d, axis.x, axis.y, axis.z).normalize()
+    }
+
+    /// Rotation that points -z along `forward`, with y leaning towards `up`, as cameras look.
+    pub fn look_rotation(forward: Vector3<T>, up: Vector3<T>) -> Self {
+        let mut back = -forward;
+        back.normalize();
+        let mut right = up.cross(&back);
+        if right.length_squared() < T::from(1e-12) {
+            // looking straight along up, any perpendicular will do
+            let (o, l) = (T::from(0.), T::from(1.));
+            let other = if back.x.abs() < T::from(0.9) { Vector3::new(l, o, o) } else { Vector3::new(o, o, l) };
+            right = other.cross(&back);
+        }
+        right.normalize();
+        let up = back.cross(&right);
+
+        Self::from_basis(right, up, back)
+    }
+
+    /// Inverse of [`Quat::from_euler_vector`], with pitch in -pi/2..=pi/2.
+    /// When pitched straight up or down, roll is folded into yaw.
+    pub fn to_euler_angles(self) -> Vector3<T> {
+        let q = self.normalized();
+        let (w, i, j, k) = (q.w, q.i, q.j, q.k);
+        let (l, two) = (T::from(1.), T::from(2.));
+        let mut sin_pitch = two * (w * i - j * k);
+        if sin_pitch > l { sin_pitch = l; }
+        if sin_pitch < -l { sin_pitch = -l; }
+        let pitch = sin_pitch.asin();
+        if sin_pitch.abs() > l - T::from(1e-6) {
+            let yaw = (-two * (i * k - w * j)).atan2(l - two * (j * j + k * k));
+            return Vector3::new(pitch, yaw, T::from(0.));
+        }
+        let yaw = (two * (i * k + w * j)).atan2(l - two * (i * i + j * j));
+        let roll = (two * (i * j + w * k)).atan2(l - two * (i * i + k * k));
+        Vector3::new(pitch, yaw, roll)
     }
 
     pub fn normalized(self) -> Self{
-        self.clone()/self.norm()
+        self/self.norm()
     }
 
     pub fn normalize(mut self) -> Self {
@@ -95,40 +195,87 @@ impl Quaternion{
     }
 
     pub fn conjugate(self) -> Self {
-        Self { 
-            w: self.w, 
-            i: -1.0 * self.i, 
-            j: -1.0 * self.j, 
-            k: -1.0 * self.k 
+        Self {
+            w: self.w,
+            i: -self.i,
+            j: -self.j,
+            k: -self.k
         }
     }
 
-    pub fn norm(self) -> f32 {
-        let norm = f32::sqrt(self.dot(self));
-        norm
+    pub fn norm(self) -> T {
+        self.dot(self).sqrt()
     }
 
     pub fn inverse(self) -> Self {
-        let inv = self.conjugate() / self.dot(self);
-        inv
+        self.conjugate() / self.dot(self)
+    }
+
+    pub fn dot(self, rhs: Self) -> T {
+        self.w * rhs.w + self.i * rhs.i + self.j * rhs.j + self.k * rhs.k
     }
 
-    pub fn dot(self, rhs: Self) -> f32 {
-        let dot = self.w * rhs.w + self.i * rhs.i + self.j * rhs.j + self.k * rhs.k;
-        dot
+    /// Normalized linear interpolation, cheap and close to [`Quat::slerp`] for small angles.
+    pub fn nlerp(self, rhs: Self, t: T) -> Self {
+        let rhs = if self.dot(rhs) < T::from(0.) { -rhs } else { rhs };
+        Self::new(
+            self.w + (rhs.w - self.w) * t,
+            self.i + (rhs.i - self.i) * t,
+            self.j + (rhs.j - self.j) * t,
+            self.k + (rhs.k - self.k) * t,
+        ).normalize()
+    }
+
+    /// Spherical interpolation at constant angular speed, along the shorter way round.
+    pub fn slerp(self, rhs: Self, t: T) -> Self {
+        let (a, mut b) = (self.normalized(), rhs.normalized());
+        let mut d = a.dot(b);
+        if d < T::from(0.) {
+            b = -b;
+            d = -d;
+        }
+        let l = T::from(1.);
+        if d > l - T::from(1e-6) {
+            // nearly the same rotation, sin(angle) would divide by zero
+            return a.nlerp(b, t);
+        }
+        let angle = d.acos();
+        let sin = angle.sin();
+        let wa = ((l - t) * angle).sin() / sin;
+        let wb = (t * angle).sin() / sin;
+        Self::new(
+            a.w * wa + b.w * wb,
+            a.i * wa + b.i * wb,
+            a.j * wa + b.j * wb,
+            a.k * wa + b.k * wb,
+        )
     }
 
-    pub fn as_mat4(self) -> Matrix4 {
-        let q: Quaternion = self.normalized();
+    /// Rotates `v` by this quaternion.
+    pub fn rotate(self, v: Vector3<T>) -> Vector3<T> {
+        let q = self.normalized();
+        let p = q * Self::new(T::from(0.), v.x, v.y, v.z) * q.conjugate();
+        Vector3::new(p.i, p.j, p.k)
+    }
+
+    /// Converts the components, e.g. to place an f32 rotation in a [`DQuaternion`].
+    pub fn cast<U: Float>(self) -> Quat<U> {
+        let c = |v: T| U::from_f64(v.to_f64());
+        Quat::new(c(self.w), c(self.i), c(self.j), c(self.k))
+    }
+
+    pub fn as_mat4(self) -> Mat4<T> {
+        let q = self.normalized();
         let (w, i, j, k) = (q.w, q.i, q.j, q.k);
-        let (i2, j2, k2) = (i*2.0, j*2.0, k*2.0);
+        let (o, l, two) = (T::from(0.), T::from(1.), T::from(2.));
+        let (i2, j2, k2) = (i*two, j*two, k*two);
         let (ii, ij, ik, jj, jk, kk, wi, wj, wk) = (i*i2, i*j2, i*k2, j*j2, j*k2, k*k2, w*i2, w*j2, w*k2);
 
-        let mat: Matrix4 = Matrix4::from_values(
-            1.0 - (jj + kk), ij - wk, ik + wj, 0.0,
-            ij + wk, 1.0 - (ii + kk), jk - wi, 0.0,
-            ik - wj, jk + wi, 1.0 - (ii + jj), 0.0,
-            0.0, 0.0, 0.0, 1.0);
+        let mat: Mat4<T> = Mat4::from_values(
+            l - (jj + kk), ij - wk, ik + wj, o,
+            ij + wk, l - (ii + kk), jk - wi, o,
+            ik - wj, jk + wi, l - (ii + jj), o,
+            o, o, o, l);
 
         mat
     }
@@ -138,47 +285,53 @@ impl Quaternion{
     }
 }
 
-impl Default for Quaternion{
+impl<T: Float> Default for Quat<T>{
     fn default() -> Self {
+        let o = T::from(0.);
         Self{
-            w: 1.,
-            i: 0.,
-            j: 0.,
-            k: 0.,
+            w: T::from(1.),
+            i: o,
+            j: o,
+            k: o,
         }
     }
 }
 
-impl Mul for Quaternion{
+impl<T: Float> Mul for Quat<T>{
     type Output = Self;
     fn mul(self, rhs: Self) -> Self::Output {
-        let mut tmp: Quaternion = self.clone();
+        let mut tmp = self;
         tmp *= rhs;
         tmp
     }
 }
 
-impl MulAssign for Quaternion {
-    fn mul_assign(&mut self, rhs: Self) { 
-        self.w = self.w * rhs.w - self.i * rhs.i - self.j * rhs.j - self.k * rhs.k;
-        self.i = self.w * rhs.i + self.i * rhs.w + self.j * rhs.k - self.k * rhs.j;
-        self.j = self.w * rhs.j - self.i * rhs.k + self.j * rhs.w + self.k * rhs.i;
-        self.k = self.w * rhs.k + self.i * rhs.j - self.j * rhs.i + self.k * rhs.w;
+impl<T: Float> MulAssign for Quat<T> {
+    fn mul_assign(&mut self, rhs: Self) {
+        let [w, i, j, k] = T::quat_mul([self.w, self.i, self.j, self.k], [rhs.w, rhs.i, rhs.j, rhs.k]);
+        *self = Self::new(w, i, j, k);
+    }
+}
+
+impl<T: Float> Neg for Quat<T> {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Self::new(-self.w, -self.i, -self.j, -self.k)
     }
 }
 
-impl Div<f32> for Quaternion{
+impl<T: Float> Div<T> for Quat<T>{
     type Output = Self;
 
-    fn div(self, rhs: f32) -> Self::Output {
-        let mut tmp: Quaternion = self.clone();
+    fn div(self, rhs: T) -> Self::Output {
+        let mut tmp = self;
         tmp /= rhs;
         tmp
     }
 }
 
-impl DivAssign<f32> for Quaternion {
-    fn div_assign(&mut self, rhs: f32) {
+impl<T: Float> DivAssign<T> for Quat<T> {
+    fn div_assign(&mut self, rhs: T) {
         self.w /= rhs;
         self.i /= rhs;
         self.j /= rhs;
@@ -0,0 +1,459 @@
+use std::io;
+use wgpu::{include_wgsl, BindGroupLayout, CompareFunction, DepthBiasState, DepthStencilState, Features, FrontFace, PipelineLayout, PrimitiveTopology, RenderPipeline, TextureFormat, TextureView};
+use wgpu::PolygonMode::{Fill, Line};
+use wgpu::util::DeviceExt;
+use crate::buffers::VertexBuffer;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::display::{CullMode, DisplayMode, Shading};
+use crate::mesh::Mesh;
+use crate::model::Model;
+use crate::shader::{BaseShader, Light, PhongShader};
+
+const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// Format of the images from [`render_image`].
+pub const IMAGE_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// One model to draw this frame.
+pub struct Draw<'m> {
+    pub model: &'m Model,
+    /// The model's mesh or level of detail to draw, see [`Model::get_lod_mesh`].
+    pub mesh: &'m Mesh,
+    pub uniforms: Vec<f32>,
+    /// Vertices of a selected triangle, whose model also gets its edges drawn.
+    pub highlight: Option<Vec<f32>>,
+}
+
+impl<'m> Draw<'m> {
+    /// Shades `model` with its material, lit by `light`.
+    pub fn new(model: &'m Model, camera: &Camera, light: Light) -> Self {
+        let mut phong = PhongShader::default();
+        let Light::Directional { direction } = light;
+        phong.set_light_dir(direction);
+        phong.set_material(&model.material);
+        Self {
+            model,
+            mesh: model.get_lod_mesh(model.get_camera_distance(camera)),
+            uniforms: phong.as_vec(model, camera),
+            highlight: None,
+        }
+    }
+}
+
+struct Pipelines {
+    surface: RenderPipeline,
+    winding: RenderPipeline,
+    background: RenderPipeline,
+    wireframe: RenderPipeline,
+    overlay: RenderPipeline,
+    points: RenderPipeline,
+    highlight: RenderPipeline,
+}
+
+impl Pipelines {
+    fn new(device: &wgpu::Device, layout: &PipelineLayout, format: TextureFormat, native_lines: bool, cull_mode: CullMode, shading: Shading) -> Self {
+        let shader = device.create_shader_module(include_wgsl!("shaders/phong.wgsl"));
+        let wire_shader = device.create_shader_module(include_wgsl!("shaders/wireframe.wgsl"));
+
+        let filled = wgpu::PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: cull_mode.face(),
+            unclipped_depth: false,
+            polygon_mode: Fill,
+            conservative: false
+        };
+        let (edges, edge_entry) = if native_lines {
+            (wgpu::PrimitiveState { polygon_mode: Line, ..filled }, "fs_line")
+        } else {
+            (filled, "fs_barycentric")
+        };
+        let points = wgpu::PrimitiveState {
+            topology: PrimitiveTopology::PointList,
+            cull_mode: None,
+            ..filled
+        };
+
+        let depth_tested = DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::Less,
+            stencil: Default::default(),
+            bias: Default::default(),
+        };
+        // edges are drawn on top of the filled pass and pulled slightly towards the camera to avoid z-fighting
+        let on_top = DepthStencilState {
+            depth_write_enabled: false,
+            depth_compare: CompareFunction::LessEqual,
+            bias: DepthBiasState { constant: -2, slope_scale: -1.0, clamp: 0.0 },
+            ..depth_tested.clone()
+        };
+        let ignore_depth = DepthStencilState {
+            depth_write_enabled: false,
+            depth_compare: CompareFunction::Always,
+            ..depth_tested.clone()
+        };
+
+        let create_pipeline = |label: &str, module: &wgpu::ShaderModule, fs_entry: &str, primitive: wgpu::PrimitiveState, depth_stencil: DepthStencilState| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[VertexBuffer::LAYOUT],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module,
+                    entry_point: Some(fs_entry),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive,
+                depth_stencil: Some(depth_stencil),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+
+        Self {
+            surface: create_pipeline("Triangle Pipeline", &shader, shading.entry_point(), filled, depth_tested.clone()),
+            winding: create_pipeline("Winding Pipeline", &shader, "fs_winding", wgpu::PrimitiveState { cull_mode: None, ..filled }, depth_tested.clone()),
+            background: create_pipeline("Background Pipeline", &wire_shader, "fs_background", filled, depth_tested),
+            wireframe: create_pipeline("Wireframe Pipeline", &wire_shader, edge_entry, wgpu::PrimitiveState { cull_mode: None, ..edges }, ignore_depth.clone()),
+            overlay: create_pipeline("Overlay Pipeline", &wire_shader, edge_entry, edges, on_top.clone()),
+            points: create_pipeline("Point Pipeline", &wire_shader, "fs_line", points, ignore_depth),
+            highlight: create_pipeline("Highlight Pipeline", &wire_shader, "fs_highlight", wgpu::PrimitiveState { cull_mode: None, ..filled }, on_top),
+        }
+    }
+}
+
+/// Draws models into colour targets of one format, with a depth buffer of the target's size.
+/// Used for the window and for rendering to images.
+pub struct Renderer {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    format: TextureFormat,
+    pipeline_layout: PipelineLayout,
+    pipelines: Pipelines,
+    native_lines: bool,
+    cull_mode: CullMode,
+    shading: Shading,
+    uniform_bind_group_layout: BindGroupLayout,
+    depth_view: TextureView,
+}
+
+impl Renderer {
+    /// Opens a device on `adapter` for targets of `width` x `height` in `format`.
+    pub async fn new(adapter: &wgpu::Adapter, format: TextureFormat, width: u32, height: u32, shading: Shading) -> Result<Self, wgpu::RequestDeviceError> {
+        // PolygonMode::Line is optional, wireframes fall back to a barycentric shader without it
+        let native_lines = adapter.features().contains(Features::POLYGON_MODE_LINE);
+        let required_features = if native_lines { Features::POLYGON_MODE_LINE } else { Features::empty() };
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                required_features,
+                ..Default::default()
+            })
+            .await?;
+
+        let depth_view = Self::create_depth_view(&device, width, height);
+
+        let uniform_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("uniform_bgl"),
+            }
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let cull_mode = CullMode::default();
+        let pipelines = Pipelines::new(&device, &pipeline_layout, format, native_lines, cull_mode, shading);
+
+        Ok(Self {
+            device,
+            queue,
+            format,
+            pipeline_layout,
+            pipelines,
+            native_lines,
+            cull_mode,
+            shading,
+            uniform_bind_group_layout,
+            depth_view,
+        })
+    }
+
+    pub fn get_cull_mode(&self) -> CullMode {
+        self.cull_mode
+    }
+
+    pub fn set_cull_mode(&mut self, cull_mode: CullMode) {
+        self.cull_mode = cull_mode;
+        self.rebuild_pipelines();
+    }
+
+    pub fn get_shading(&self) -> Shading {
+        self.shading
+    }
+
+    pub fn set_shading(&mut self, shading: Shading) {
+        self.shading = shading;
+        self.rebuild_pipelines();
+    }
+
+    fn rebuild_pipelines(&mut self) {
+        self.pipelines = Pipelines::new(&self.device, &self.pipeline_layout, self.format, self.native_lines, self.cull_mode, self.shading);
+    }
+
+    fn create_depth_view(device: &wgpu::Device, width: u32, height: u32) -> TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    /// Matches the depth buffer to a new target size.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.depth_view = Self::create_depth_view(&self.device, width, height);
+    }
+
+    /// Draws the models into `view`, which is cleared to `background` first.
+    pub fn render(&self, view: &TextureView, draws: &[Draw], background: Color, mode: DisplayMode, show_back_faces: bool) {
+        let encoder = self.encode(view, draws, background, mode, show_back_faces);
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    fn encode(&self, view: &TextureView, draws: &[Draw], background: Color, mode: DisplayMode, show_back_faces: bool) -> wgpu::CommandEncoder {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: background.r as f64,
+                            g: background.g as f64,
+                            b: background.b as f64,
+                            a: background.a as f64,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            for draw in draws {
+                self.draw_model(&mut render_pass, draw, background, mode, show_back_faces);
+            }
+        }
+        encoder
+    }
+
+    fn draw_model(&self, render_pass: &mut wgpu::RenderPass, draw: &Draw, background: Color, mode: DisplayMode, show_back_faces: bool) {
+        let mesh = draw.mesh;
+        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&mesh.vb.get_vertices()),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(&mesh.ib.get_indices()),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let draws_edges = mode.draws_edges() || draw.highlight.is_some();
+        // the barycentric fallback needs three vertices of its own per triangle
+        let edge_buffer = (draws_edges && !self.native_lines).then(|| {
+            self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+                label: Some("Edge Vertex Buffer"),
+                contents: bytemuck::cast_slice(&mesh.get_unindexed_vertices()),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        });
+
+        let uniform_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label: Some("Uniform Buffer"),
+            // hidden-line surfaces are filled with the background colour
+            contents: bytemuck::cast_slice(&[draw.uniforms.as_slice(), &background.as_vec()].concat()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_bind_group = self.device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout: &self.uniform_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }],
+                label: Some("uniform_bg"),
+            }
+        );
+
+        let index_count = mesh.ib.get_indices().len() as u32;
+
+        render_pass.set_bind_group(0, &uniform_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+        match mode {
+            DisplayMode::Solid | DisplayMode::ShadedWireframe => {
+                render_pass.set_pipeline(if show_back_faces { &self.pipelines.winding } else { &self.pipelines.surface });
+                render_pass.draw_indexed(0..index_count, 0, 0..1);
+            }
+            DisplayMode::HiddenLine => {
+                render_pass.set_pipeline(&self.pipelines.background);
+                render_pass.draw_indexed(0..index_count, 0, 0..1);
+            }
+            DisplayMode::Points => {
+                render_pass.set_pipeline(&self.pipelines.points);
+                render_pass.draw(0..mesh.vb.get_vertex_count() as u32, 0..1);
+            }
+            DisplayMode::Wireframe => {}
+        }
+
+        if draws_edges {
+            let pipeline = if mode == DisplayMode::Wireframe { &self.pipelines.wireframe } else { &self.pipelines.overlay };
+            render_pass.set_pipeline(pipeline);
+            match &edge_buffer {
+                Some(buffer) => {
+                    render_pass.set_vertex_buffer(0, buffer.slice(..));
+                    render_pass.draw(0..index_count, 0..1);
+                }
+                None => render_pass.draw_indexed(0..index_count, 0, 0..1),
+            }
+        }
+
+        if let Some(vertices) = &draw.highlight {
+            let highlight_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+                label: Some("Highlight Vertex Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            render_pass.set_pipeline(&self.pipelines.highlight);
+            render_pass.set_vertex_buffer(0, highlight_buffer.slice(..));
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+    /// Renders into a texture and reads it back as rows of RGBA8 pixels, top row first.
+    pub fn render_to_image(&self, width: u32, height: u32, draws: &[Draw], background: Color, mode: DisplayMode) -> io::Result<Vec<u8>> {
+        let size = wgpu::Extent3d { width, height, depth_or_array_layers: 1 };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Image Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // rows of a texture copy are padded to a fixed alignment
+        let row_bytes = width * 4;
+        let padded_row_bytes = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Image Buffer"),
+            size: (padded_row_bytes * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self.encode(&view, draws, background, mode, false);
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            size,
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| sender.send(result).unwrap());
+        self.device.poll(wgpu::PollType::wait_indefinitely()).map_err(io::Error::other)?;
+        receiver.recv().map_err(io::Error::other)?.map_err(io::Error::other)?;
+        let data = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((row_bytes * height) as usize);
+        for row in data.chunks(padded_row_bytes as usize) {
+            pixels.extend_from_slice(&row[..row_bytes as usize]);
+        }
+        Ok(pixels)
+    }
+}
+
+/// Renders without a window, on whatever adapter is available, e.g. a software one on a
+/// headless machine. Returns rows of RGBA8 pixels, top row first.
+pub fn render_image(models: &[Model], camera: &Camera, light: Light, background: Color, shading: Shading, width: u32, height: u32) -> io::Result<Vec<u8>> {
+    pollster::block_on(async {
+        let instance = wgpu::Instance::default();
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions::default()).await.map_err(io::Error::other)?;
+        let renderer = Renderer::new(&adapter, IMAGE_FORMAT, width, height, shading).await.map_err(io::Error::other)?;
+        let (visible, _) = crate::culling::cull(models, camera);
+        let draws: Vec<Draw> = visible.iter().map(|&i| Draw::new(&models[i], camera, light)).collect();
+        renderer.render_to_image(width, height, &draws, background, DisplayMode::Solid)
+    })
+}
@@ -0,0 +1,319 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::buffers::{IndexBuffer, VertexBuffer};
+use crate::mesh::{find_root, Mesh};
+use crate::vector::Vector3;
+
+/// Which repairs [`Mesh::repair`] makes. The default only removes what is certainly broken;
+/// merging vertices, fixing the winding and filling holes change the shape and are asked for.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RepairOptions {
+    /// Move vertices this close together onto one position, see [`Mesh::merge_close_vertices`].
+    pub merge_distance: Option<f32>,
+    pub degenerate: bool,
+    pub duplicates: bool,
+    /// Make neighbours agree on their winding and face outwards, see [`Mesh::orient_winding`].
+    pub winding: bool,
+    /// Fill holes with at most this many edges, see [`Mesh::fill_holes`].
+    pub fill_holes: Option<usize>,
+    pub unreferenced: bool,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self {
+            merge_distance: None,
+            degenerate: true,
+            duplicates: true,
+            winding: false,
+            fill_holes: None,
+            unreferenced: true,
+        }
+    }
+}
+
+/// How much each step of [`Mesh::repair`] changed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct RepairReport {
+    pub merged_vertices: usize,
+    pub degenerate_triangles: usize,
+    pub duplicate_triangles: usize,
+    pub flipped_triangles: usize,
+    pub filled_holes: usize,
+    pub unreferenced_vertices: usize,
+}
+
+impl fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<30}{}", "merged vertices", self.merged_vertices)?;
+        writeln!(f, "{:<30}{}", "removed degenerate triangles", self.degenerate_triangles)?;
+        writeln!(f, "{:<30}{}", "removed duplicate triangles", self.duplicate_triangles)?;
+        writeln!(f, "{:<30}{}", "flipped triangles", self.flipped_triangles)?;
+        writeln!(f, "{:<30}{}", "filled holes", self.filled_holes)?;
+        write!(f, "{:<30}{}", "removed unreferenced vertices", self.unreferenced_vertices)
+    }
+}
+
+/// Cell of a grid with cells `size` wide, for finding nearby points.
+fn cell(p: Vector3<f32>, size: f32) -> [i64; 3] {
+    [p.x, p.y, p.z].map(|v| (v / size).floor() as i64)
+}
+
+impl Mesh {
+    /// Runs the repairs `options` ask for: merging vertices first, as that joins triangles that
+    /// only nearly touch, and removing unreferenced vertices last.
+    pub fn repair(&mut self, options: &RepairOptions) -> RepairReport {
+        let mut report = RepairReport::default();
+        if let Some(distance) = options.merge_distance {
+            report.merged_vertices = self.merge_close_vertices(distance);
+        }
+        if options.degenerate {
+            report.degenerate_triangles = self.remove_degenerate_triangles();
+        }
+        if options.duplicates {
+            report.duplicate_triangles = self.remove_duplicate_triangles();
+        }
+        if options.winding {
+            report.flipped_triangles = self.orient_winding();
+        }
+        if let Some(max_edges) = options.fill_holes {
+            report.filled_holes = self.fill_holes(max_edges);
+        }
+        if options.unreferenced {
+            report.unreferenced_vertices = self.remove_unreferenced_vertices();
+        }
+        report
+    }
+
+    /// Whether triangle `t` has two corners at the same position, given the ids of
+    /// [`Mesh::get_position_ids`], or corners in a line.
+    fn is_degenerate(&self, t: usize, corners: &[u32]) -> bool {
+        let [a, b, c] = [corners[3 * t], corners[3 * t + 1], corners[3 * t + 2]];
+        if a == b || b == c || c == a {
+            return true;
+        }
+        // twice the area is tiny next to the longest edge
+        let triangle = self.get_triangle(t);
+        let (ab, bc, ca) = (triangle.b - triangle.a, triangle.c - triangle.b, triangle.a - triangle.c);
+        let longest = ab.length_squared().max(bc.length_squared()).max(ca.length_squared());
+        ab.cross(&-ca).length() <= f32::EPSILON * longest
+    }
+
+    /// Triangles without area: with two corners at the same position or all three in a line.
+    pub fn get_degenerate_triangles(&self) -> Vec<usize> {
+        let (corners, _) = self.get_position_ids();
+        (0..self.get_triangle_count()).filter(|&t| self.is_degenerate(t, &corners)).collect()
+    }
+
+    /// Position of each position id.
+    fn get_id_positions(&self, corners: &[u32], count: usize) -> Vec<Vector3<f32>> {
+        let mut positions = vec![Vector3::default(); count];
+        for (&i, &id) in self.ib.get_indices().iter().zip(corners) {
+            positions[id as usize] = self.vb.get_vertex(i as usize).get_position();
+        }
+        positions
+    }
+
+    /// Edges shared by more than two triangles, by the positions of their ends.
+    pub fn get_non_manifold_edges(&self) -> Vec<[Vector3<f32>; 2]> {
+        let (corners, count) = self.get_position_ids();
+        let positions = self.get_id_positions(&corners, count);
+        let mut edges: Vec<(u32, u32)> = Self::get_edge_triangles(&corners).into_iter()
+            .filter(|(_, triangles)| triangles.len() > 2)
+            .map(|(edge, _)| edge)
+            .collect();
+        edges.sort_unstable();
+        edges.into_iter().map(|(a, b)| [positions[a as usize], positions[b as usize]]).collect()
+    }
+
+    /// Positions where the surface pinches: the triangles around them form more than one fan,
+    /// like the shared corner of two cones tip to tip.
+    pub fn get_non_manifold_vertices(&self) -> Vec<Vector3<f32>> {
+        let (corners, count) = self.get_position_ids();
+        let positions = self.get_id_positions(&corners, count);
+
+        // corners of triangles around a position join up through the edges they share
+        let mut parents: Vec<usize> = (0..corners.len()).collect();
+        for ((a, b), triangles) in Self::get_edge_triangles(&corners) {
+            let corner = |t: usize, id: u32| (0..3).map(|c| 3 * t + c).find(|&c| corners[c] == id);
+            for pair in triangles.windows(2) {
+                for id in [a, b] {
+                    if let (Some(x), Some(y)) = (corner(pair[0], id), corner(pair[1], id)) {
+                        let (x, y) = (find_root(&mut parents, x), find_root(&mut parents, y));
+                        parents[x] = y;
+                    }
+                }
+            }
+        }
+        let mut fans: HashSet<(u32, usize)> = HashSet::new();
+        for (c, &id) in corners.iter().enumerate() {
+            if !self.is_degenerate(c / 3, &corners) {
+                fans.insert((id, find_root(&mut parents, c)));
+            }
+        }
+        let mut fan_counts = vec![0; count];
+        for (id, _) in fans {
+            fan_counts[id as usize] += 1;
+        }
+        (0..count).filter(|&id| fan_counts[id] > 1).map(|id| positions[id]).collect()
+    }
+
+    /// Keeps the triangles `keep` says, with the submeshes shrunk to match.
+    fn retain_triangles(&mut self, keep: &[bool]) -> usize {
+        let indices = self.ib.get_indices();
+        let mut ib = IndexBuffer::new();
+        for (t, corners) in indices.chunks_exact(3).enumerate() {
+            if keep[t] {
+                corners.iter().for_each(|&i| ib.add_index(i));
+            }
+        }
+        let mut first_triangle = 0;
+        for submesh in &mut self.submeshes {
+            let range = submesh.first_triangle..submesh.first_triangle + submesh.triangle_count;
+            submesh.first_triangle = first_triangle;
+            submesh.triangle_count = keep[range].iter().filter(|&&k| k).count();
+            first_triangle += submesh.triangle_count;
+        }
+        self.submeshes.retain(|s| s.triangle_count > 0);
+        self.ib = ib;
+        keep.iter().filter(|&&k| !k).count()
+    }
+
+    /// Removes triangles without area. Returns how many.
+    pub fn remove_degenerate_triangles(&mut self) -> usize {
+        let (corners, _) = self.get_position_ids();
+        let keep: Vec<bool> = (0..self.get_triangle_count()).map(|t| !self.is_degenerate(t, &corners)).collect();
+        self.retain_triangles(&keep)
+    }
+
+    /// Removes triangles with the same three corner positions as an earlier one, whichever way
+    /// round they go. Returns how many.
+    pub fn remove_duplicate_triangles(&mut self) -> usize {
+        let (corners, _) = self.get_position_ids();
+        let mut seen: HashSet<[u32; 3]> = HashSet::new();
+        let keep: Vec<bool> = corners.chunks_exact(3).map(|c| {
+            let mut key = [c[0], c[1], c[2]];
+            key.sort_unstable();
+            seen.insert(key)
+        }).collect();
+        self.retain_triangles(&keep)
+    }
+
+    /// Moves every vertex within `distance` of an earlier one onto its position, joining
+    /// triangles that nearly touch, then welds vertices that became equal in every attribute.
+    /// Returns the number of vertices moved.
+    pub fn merge_close_vertices(&mut self, distance: f32) -> usize {
+        if distance <= 0. {
+            return 0;
+        }
+        let mut grid: HashMap<[i64; 3], Vec<Vector3<f32>>> = HashMap::new();
+        let mut moved = 0;
+        for i in 0..self.vb.get_vertex_count() {
+            let mut vertex = self.vb.get_vertex(i);
+            let p = vertex.get_position();
+            let [x, y, z] = cell(p, distance);
+            let near = (-1..=1).flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| [x + dx, y + dy, z + dz])))
+                .filter_map(|c| grid.get(&c))
+                .flatten()
+                .find(|q| q.distance(&p) <= distance)
+                .copied();
+            match near {
+                Some(q) if q != p => {
+                    vertex.set_position(q);
+                    self.vb.set_vertex(i, vertex);
+                    moved += 1;
+                }
+                Some(_) => {}
+                None => grid.entry([x, y, z]).or_default().push(p),
+            }
+        }
+        self.weld();
+        moved
+    }
+
+    /// Removes vertices no triangle uses. Returns how many.
+    pub fn remove_unreferenced_vertices(&mut self) -> usize {
+        let count = self.vb.get_vertex_count();
+        let indices = self.ib.get_indices();
+        let mut used = vec![false; count];
+        indices.iter().for_each(|&i| used[i as usize] = true);
+
+        let mut vb = VertexBuffer::new();
+        let mut remap = vec![0; count];
+        for i in (0..count).filter(|&i| used[i]) {
+            remap[i] = vb.get_vertex_count() as u32;
+            vb.add_vertex(self.vb.get_vertex(i));
+        }
+        let mut ib = IndexBuffer::new();
+        indices.iter().for_each(|&i| ib.add_index(remap[i as usize]));
+        self.vb = vb;
+        self.ib = ib;
+        count - self.vb.get_vertex_count()
+    }
+
+    /// Closes holes bounded by at most `max_edges` edges with a fan of triangles facing the same
+    /// way as the triangles around them, so the winding should be consistent first. The fan
+    /// suits small, roughly flat holes. The new triangles join the last submesh. Returns the
+    /// number of holes filled.
+    pub fn fill_holes(&mut self, max_edges: usize) -> usize {
+        let (corners, _) = self.get_position_ids();
+        let indices = self.ib.get_indices();
+        let edges = Self::get_edge_triangles(&corners);
+
+        // open edges in the direction their triangle goes, from each start position
+        let mut open: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
+        for t in 0..corners.len() / 3 {
+            for e in 0..3 {
+                let (a, b) = (corners[3 * t + e], corners[3 * t + (e + 1) % 3]);
+                if a != b && edges[&(a.min(b), a.max(b))].len() == 1 {
+                    open.entry(a).or_default().push((b, indices[3 * t + e]));
+                }
+            }
+        }
+
+        let mut holes: Vec<Vec<u32>> = Vec::new();
+        let mut starts: Vec<u32> = open.keys().copied().collect();
+        starts.sort_unstable();
+        for start in starts {
+            while let Some((mut next, vertex)) = open.get_mut(&start).and_then(Vec::pop) {
+                let mut hole = vec![vertex];
+                while next != start && hole.len() <= max_edges {
+                    let Some((after, vertex)) = open.get_mut(&next).and_then(Vec::pop) else {
+                        break;
+                    };
+                    hole.push(vertex);
+                    next = after;
+                }
+                if next == start && (3..=max_edges).contains(&hole.len()) {
+                    holes.push(hole);
+                }
+            }
+        }
+
+        let mut added = 0;
+        for hole in &holes {
+            let positions: Vec<Vector3<f32>> = hole.iter().map(|&i| self.vb.get_vertex(i as usize).get_position()).collect();
+            // Newell's normal of the loop, which runs against the triangles filling it
+            let normal = -(0..positions.len()).fold(Vector3::default(), |n, i| {
+                let (p, q) = (positions[i], positions[(i + 1) % positions.len()]);
+                n + Vector3::new((p.y - q.y) * (p.z + q.z), (p.z - q.z) * (p.x + q.x), (p.x - q.x) * (p.y + q.y))
+            }).normalized();
+            let first = self.vb.get_vertex_count() as u32;
+            for &i in hole {
+                let mut vertex = self.vb.get_vertex(i as usize);
+                vertex.set_normal(normal);
+                self.vb.add_vertex(vertex);
+            }
+            for i in 1..hole.len() as u32 - 1 {
+                for corner in [0, i + 1, i] {
+                    self.ib.add_index(first + corner);
+                }
+                added += 1;
+            }
+        }
+        if let Some(last) = self.submeshes.last_mut() {
+            last.triangle_count += added;
+        }
+        holes.len()
+    }
+}
@@ -0,0 +1,205 @@
+use std::io;
+use std::path::Path;
+use crate::matrix::DMatrix4;
+use crate::model::Model;
+use crate::transform::DTransform;
+
+/// Handle to a node of a [`Scene`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId(usize);
+
+/// Hierarchy of named nodes, each placed relative to its parent and optionally carrying a model.
+///
+/// Moving a node moves everything below it. World transforms are cached and only recomputed by
+/// [`Scene::update`] for the nodes that changed and their descendants, which also places the
+/// models for rendering.
+#[derive(Default)]
+pub struct Scene {
+    nodes: Vec<Node>,
+    roots: Vec<NodeId>,
+    models: Vec<Model>,
+}
+
+struct Node {
+    name: String,
+    local: DTransform,
+    /// `local` followed by the parent's world matrix, valid while the node is not dirty.
+    world: DMatrix4,
+    dirty: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    model: Option<usize>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an empty node, e.g. to group an assembly, under `parent` or as a root.
+    pub fn add_node(&mut self, name: &str, parent: Option<NodeId>, local: DTransform) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name: name.to_string(),
+            local,
+            world: DMatrix4::identity(),
+            dirty: true,
+            parent,
+            children: Vec::new(),
+            model: None,
+        });
+        match parent {
+            Some(p) => self.nodes[p.0].children.push(id),
+            None => self.roots.push(id),
+        }
+        id
+    }
+
+    /// Adds a node carrying `model`, whose transform becomes the node's local transform.
+    pub fn add_model(&mut self, name: &str, parent: Option<NodeId>, model: Model) -> NodeId {
+        let id = self.add_node(name, parent, model.transform);
+        self.nodes[id.0].model = Some(self.models.len());
+        self.models.push(model);
+        id
+    }
+
+    /// Loads an OBJ file as a node named after the file, with a child per object or group. The
+    /// scene is left as it was if the file cannot be read.
+    pub fn import_obj(&mut self, filepath: &str, parent: Option<NodeId>) -> io::Result<NodeId> {
+        let groups = Model::load_obj_groups(filepath)?;
+        let name = Path::new(filepath).file_stem().map_or(filepath.into(), |s| s.to_string_lossy());
+        let root = self.add_node(&name, parent, DTransform::default());
+        for (name, model) in groups {
+            self.add_model(&name, Some(root), model);
+        }
+        Ok(root)
+    }
+
+    /// Moves a node and everything below it under another parent, keeping its local transform.
+    /// Returns false, changing nothing, if `parent` is the node itself or one of its descendants.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if parent.is_some_and(|p| self.is_ancestor(id, p)) {
+            return false;
+        }
+        match self.nodes[id.0].parent {
+            Some(old) => self.nodes[old.0].children.retain(|&c| c != id),
+            None => self.roots.retain(|&r| r != id),
+        }
+        match parent {
+            Some(p) => self.nodes[p.0].children.push(id),
+            None => self.roots.push(id),
+        }
+        self.nodes[id.0].parent = parent;
+        self.nodes[id.0].dirty = true;
+        true
+    }
+
+    /// Whether `ancestor` is `id` or above it.
+    pub fn is_ancestor(&self, ancestor: NodeId, mut id: NodeId) -> bool {
+        loop {
+            if id == ancestor {
+                return true;
+            }
+            match self.nodes[id.0].parent {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Recomputes the world transforms of changed nodes and their descendants, then moves their
+    /// models into place.
+    pub fn update(&mut self) {
+        let mut stack: Vec<(NodeId, bool)> = self.roots.iter().rev().map(|&r| (r, false)).collect();
+        while let Some((id, parent_changed)) = stack.pop() {
+            let node = &self.nodes[id.0];
+            let changed = parent_changed || node.dirty;
+            if changed {
+                let parent_world = node.parent.map_or(DMatrix4::identity(), |p| self.nodes[p.0].world);
+                let world = node.local.as_matrix() * parent_world;
+                let node = &mut self.nodes[id.0];
+                node.world = world;
+                node.dirty = false;
+                if let Some(m) = node.model {
+                    self.models[m].transform = DTransform::from_matrix(world);
+                }
+            }
+            stack.extend(self.nodes[id.0].children.iter().rev().map(|&c| (c, changed)));
+        }
+    }
+
+    pub fn get_local_transform(&self, id: NodeId) -> DTransform {
+        self.nodes[id.0].local
+    }
+
+    pub fn set_local_transform(&mut self, id: NodeId, local: DTransform) {
+        self.nodes[id.0].local = local;
+        self.nodes[id.0].dirty = true;
+    }
+
+    /// Marks the node as changed, so edit it in place e.g. with `set_rotation`.
+    pub fn local_transform_mut(&mut self, id: NodeId) -> &mut DTransform {
+        self.nodes[id.0].dirty = true;
+        &mut self.nodes[id.0].local
+    }
+
+    /// World matrix as of the last [`Scene::update`].
+    pub fn get_world_matrix(&self, id: NodeId) -> DMatrix4 {
+        self.nodes[id.0].world
+    }
+
+    /// World placement as of the last [`Scene::update`]. Non-uniform scales under rotated parents
+    /// shear, which a transform cannot hold; use [`Scene::get_world_matrix`] where that matters.
+    pub fn get_world_transform(&self, id: NodeId) -> DTransform {
+        DTransform::from_matrix(self.get_world_matrix(id))
+    }
+
+    pub fn get_name(&self, id: NodeId) -> &str {
+        &self.nodes[id.0].name
+    }
+
+    /// The first node called `name`.
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|n| n.name == name).map(NodeId)
+    }
+
+    pub fn get_parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn get_children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id.0].children
+    }
+
+    pub fn get_roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn get_node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Index of the node's model in [`Scene::get_models`].
+    pub fn get_model_index(&self, id: NodeId) -> Option<usize> {
+        self.nodes[id.0].model
+    }
+
+    /// The node carrying the model at `index` of [`Scene::get_models`].
+    pub fn get_model_node(&self, index: usize) -> Option<NodeId> {
+        self.nodes.iter().position(|n| n.model == Some(index)).map(NodeId)
+    }
+
+    pub fn get_model(&self, id: NodeId) -> Option<&Model> {
+        self.nodes[id.0].model.map(|m| &self.models[m])
+    }
+
+    /// Every model, placed in the world as of the last [`Scene::update`].
+    pub fn get_models(&self) -> &[Model] {
+        &self.models
+    }
+
+    /// For editing meshes. Model transforms belong to the nodes, set them there.
+    pub fn get_models_mut(&mut self) -> &mut [Model] {
+        &mut self.models
+    }
+}
@@ -0,0 +1,276 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::camera::{Camera, Projection};
+use crate::color::Color;
+use crate::mesh_file::ImportOptions;
+use crate::model::Model;
+use crate::quaternion::DQuaternion;
+use crate::scene::{NodeId, Scene};
+use crate::shader::{Light, Material};
+use crate::transform::DTransform;
+use crate::vector::Vector3;
+
+/// Everything needed to set up the viewer: models with their placement and materials, lights,
+/// camera and background. Stored as RON or JSON, chosen by the file extension.
+///
+/// Every field may be left out of a file and falls back to its default, so a scene can be as
+/// short as a list of models.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneFile {
+    pub background: Color,
+    pub camera: CameraDescription,
+    pub lights: Vec<Light>,
+    pub materials: Vec<Material>,
+    pub nodes: Vec<NodeDescription>,
+}
+
+/// Where the camera is and what it looks at. Angles are in degrees.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraDescription {
+    pub position: Vector3<f64>,
+    pub target: Vector3<f64>,
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    pub projection: Projection,
+}
+
+/// A node of the scene graph, placed relative to its parent. Angles are in degrees, applied as
+/// in [`crate::quaternion::Quat::from_euler_vector`]: x pitch, y yaw, z roll.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeDescription {
+    pub name: String,
+    /// OBJ, STL, PLY or glTF file, relative to the scene file.
+    pub model: Option<String>,
+    /// Name of one of the scene's materials, or the default material.
+    pub material: Option<String>,
+    /// How to read the model's coordinates, when they are not Y up, right-handed metres.
+    #[serde(skip_serializing_if = "ImportOptions::is_default")]
+    pub import: ImportOptions,
+    pub position: Vector3<f64>,
+    pub rotation: Vector3<f64>,
+    pub scale: Vector3<f64>,
+    pub children: Vec<NodeDescription>,
+}
+
+impl Default for SceneFile {
+    fn default() -> Self {
+        Self {
+            background: Color::new(0., 0., 0., 1.),
+            camera: CameraDescription::default(),
+            lights: vec![Light::default()],
+            materials: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+impl Default for CameraDescription {
+    fn default() -> Self {
+        Self {
+            position: Vector3::new(0., 0., 5.),
+            target: Vector3::default(),
+            fov: 65.,
+            near: 0.001,
+            far: 1000.,
+            projection: Projection::Perspective,
+        }
+    }
+}
+
+impl Default for NodeDescription {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            model: None,
+            material: None,
+            import: ImportOptions::default(),
+            position: Vector3::default(),
+            rotation: Vector3::default(),
+            scale: Vector3::new(1., 1., 1.),
+            children: Vec::new(),
+        }
+    }
+}
+
+fn invalid_data(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, e)
+}
+
+fn is_json(path: &Path) -> io::Result<bool> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("ron") => Ok(false),
+        Some(e) if e.eq_ignore_ascii_case("json") => Ok(true),
+        _ => Err(io::Error::new(ErrorKind::InvalidInput, format!("{} is neither .ron nor .json", path.display()))),
+    }
+}
+
+impl SceneFile {
+    /// Reads a `.ron` or `.json` scene.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let json = is_json(path)?;
+        let text = fs::read_to_string(path)?;
+        if json { Self::from_json(&text) } else { Self::from_ron(&text) }
+    }
+
+    /// Writes a `.ron` or `.json` scene.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let text = if is_json(path)? { self.to_json()? } else { self.to_ron()? };
+        fs::write(path, text)
+    }
+
+    pub fn from_ron(text: &str) -> io::Result<Self> {
+        ron::from_str(text).map_err(invalid_data)
+    }
+
+    pub fn to_ron(&self) -> io::Result<String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(invalid_data)
+    }
+
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        serde_json::from_str(text).map_err(invalid_data)
+    }
+
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(invalid_data)
+    }
+
+    pub fn get_material(&self, name: &str) -> Option<&Material> {
+        self.materials.iter().find(|m| m.name == name)
+    }
+
+    /// The first light, which is the one the viewer shades with.
+    pub fn get_light(&self) -> Light {
+        self.lights.first().copied().unwrap_or_default()
+    }
+
+    /// Builds the scene graph, loading models from paths relative to `base`, usually the
+    /// directory of the scene file. Fails on missing models and unknown materials.
+    pub fn instantiate(&self, base: impl AsRef<Path>) -> io::Result<Scene> {
+        let mut scene = Scene::new();
+        let mut stack: Vec<(&NodeDescription, Option<NodeId>)> = self.nodes.iter().rev().map(|n| (n, None)).collect();
+        while let Some((node, parent)) = stack.pop() {
+            let id = self.add_node(&mut scene, node, parent, base.as_ref())?;
+            stack.extend(node.children.iter().rev().map(|c| (c, Some(id))));
+        }
+        scene.update();
+        Ok(scene)
+    }
+
+    fn add_node(&self, scene: &mut Scene, node: &NodeDescription, parent: Option<NodeId>, base: &Path) -> io::Result<NodeId> {
+        let local = node.get_transform();
+        let Some(model_path) = &node.model else {
+            return Ok(scene.add_node(&node.name, parent, local));
+        };
+        let path = base.join(model_path);
+        if !path.is_file() {
+            return Err(io::Error::new(ErrorKind::NotFound, format!("model {} not found", path.display())));
+        }
+        let material = match &node.material {
+            Some(name) => self.get_material(name).cloned()
+                .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, format!("unknown material {}", name)))?,
+            None => Material::default(),
+        };
+        let mut model = Model::default();
+        model.try_load_with(&path.to_string_lossy(), node.import)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        model.transform = local;
+        model.material = material;
+        Ok(scene.add_model(&node.name, parent, model))
+    }
+
+    /// Describes the current state, for saving. Model paths are made relative to `base` where
+    /// they lie below it. Models that were not loaded from a whole file are left out, keeping
+    /// their node.
+    pub fn capture(scene: &Scene, camera: &Camera, lights: &[Light], background: Color, base: impl AsRef<Path>) -> Self {
+        let mut file = Self {
+            background,
+            camera: CameraDescription::from_camera(camera),
+            lights: lights.to_vec(),
+            materials: Vec::new(),
+            nodes: Vec::new(),
+        };
+        for &root in scene.get_roots() {
+            let node = file.describe(scene, root, base.as_ref());
+            file.nodes.push(node);
+        }
+        file
+    }
+
+    fn describe(&mut self, scene: &Scene, id: NodeId, base: &Path) -> NodeDescription {
+        let mut node = NodeDescription::from_transform(scene.get_name(id), scene.get_local_transform(id));
+        if let Some(model) = scene.get_model(id).filter(|m| m.get_path().is_some()) {
+            let path = Path::new(model.get_path().unwrap_or_default());
+            node.model = Some(path.strip_prefix(base).unwrap_or(path).to_string_lossy().into_owned());
+            node.import = model.get_import_options();
+            if model.material != Material::default() {
+                if self.get_material(&model.material.name).is_none() {
+                    self.materials.push(model.material.clone());
+                }
+                node.material = Some(model.material.name.clone());
+            }
+        }
+        node.children = scene.get_children(id).iter().map(|&c| self.describe(scene, c, base)).collect();
+        node
+    }
+}
+
+impl CameraDescription {
+    /// Looks from where the camera is at the point it focuses on.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let position = camera.get_position();
+        let forward = camera.transform.get_rotation().rotate(Vector3::backward().cast());
+        Self {
+            position,
+            target: position + forward * camera.get_focus_distance() as f64,
+            fov: camera.get_fov().to_degrees(),
+            near: camera.get_near(),
+            far: camera.get_far(),
+            projection: camera.get_projection(),
+        }
+    }
+
+    /// Places the camera and sets up its projection, focused on the target.
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.transform.set_position(self.position);
+        camera.transform.set_rotation(DQuaternion::look_rotation(self.target - self.position, Vector3::up().cast()));
+        camera.set_fov(self.fov.to_radians());
+        camera.set_clip_planes(self.near, self.far);
+        camera.set_projection(self.projection);
+        camera.set_focus_distance(self.get_distance());
+        if self.projection == Projection::Orthographic {
+            camera.set_ortho_height(2. * self.get_distance() * (camera.get_fov() / 2.).tan());
+        }
+    }
+
+    pub fn get_distance(&self) -> f32 {
+        self.position.distance(&self.target) as f32
+    }
+}
+
+impl NodeDescription {
+    fn from_transform(name: &str, transform: DTransform) -> Self {
+        Self {
+            name: name.to_string(),
+            position: transform.get_position(),
+            rotation: transform.get_rotation().to_euler_angles().map(f64::to_degrees),
+            scale: transform.get_scale(),
+            ..Self::default()
+        }
+    }
+
+    pub fn get_transform(&self) -> DTransform {
+        let mut transform = DTransform::default();
+        transform.set_position(self.position);
+        transform.set_rotation(DQuaternion::from_euler_vector(self.rotation.map(f64::to_radians)));
+        transform.set_scale(self.scale);
+        transform
+    }
+}
@@ -2,6 +2,7 @@ use crate::camera::Camera;
 use crate::color::Color;
 use crate::model::Model;
 use crate::vector::Vector3;
+use serde::{Deserialize, Serialize};
 
 pub enum AnyShader {
     FlatShader(FlatShader),
@@ -40,36 +41,38 @@ impl BaseShader for AnyShader {
     }
 }
 
+#[derive(Default)]
 pub struct PhongShader {
     ambient: Color,
     diffuse: Color,
     specular: Color,
     light_direction: Vector3<f32>,
-    eye_pos: Vector3<f32>,
 }
 
 impl BaseShader for PhongShader {
     fn activate(&self) {
-        println!("{}", "Activate PhongShader")
+        println!("Activate PhongShader")
     }
 
     fn deactivate(&self) {
-        println!("{}", "Deactivate PhongShader")
+        println!("Deactivate PhongShader")
     }
 
     fn as_vec(&self, model: &Model, cam: &Camera) -> Vec<f32> {
-        let v = [
+        let model_matrix = model.transform.relative_to(cam.get_position()).as_matrix();
+        [
             self.ambient.as_vec(),
             self.diffuse.as_vec(),
             self.specular.as_vec(),
-            model.transform.as_matrix().as_vec(), 
-            cam.transform.as_matrix().as_vec(), 
+            model_matrix.as_vec(),
+            cam.relative_view_matrix().as_vec(),
             cam.get_projection_matrix().as_vec(),
+            model_matrix.normal_matrix().as_vec(),
             self.light_direction.as_vec(),
             vec![0.],
-            self.eye_pos.as_vec(),
-            vec![0.]].concat();
-        v
+            // positions are camera-relative, so the eye is at the origin
+            vec![0., 0., 0.],
+            vec![0.]].concat()
     }
 }
 
@@ -86,44 +89,64 @@ impl PhongShader {
     pub fn set_light_dir(&mut self, dir: Vector3<f32>) {
         self.light_direction = dir;
     }
-    pub fn set_eye_pos(&mut self, pos: Vector3<f32>) {
-        self.eye_pos = pos;
+    pub fn set_material(&mut self, material: &Material) {
+        self.ambient = material.ambient;
+        self.diffuse = material.diffuse;
+        self.specular = material.specular;
     }
 }
 
-impl Default for PhongShader {
+/// Surface colours a model is shaded with.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Material {
+    pub name: String,
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+}
+
+impl Default for Material {
+    /// The viewer's red with white highlights.
     fn default() -> Self {
+        let red = Color::new(1., 0., 0., 1.);
         Self {
-            ambient: Color::default(),
-            diffuse: Color::default(),
-            specular: Color::default(),
-            light_direction: Vector3::default(),
-            eye_pos: Vector3::default(),
+            name: String::from("default"),
+            ambient: red,
+            diffuse: red,
+            specular: Color::new(1., 1., 1., 1.),
         }
     }
 }
 
+/// Light source of a scene. The Phong shader lights with one directional light.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Light {
+    /// Light travelling along `direction`, as from the sun.
+    Directional { direction: Vector3<f32> },
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Light::Directional { direction: Vector3::new(-1., -1., 1.) }
+    }
+}
+
+#[derive(Default)]
 pub struct FlatShader {
 }
 
 impl BaseShader for FlatShader {
     fn activate(&self) {
-        println!("{}", "Activate FlatShader")
+        println!("Activate FlatShader")
     }
 
     fn deactivate(&self) {
-        println!("{}", "Deactivate FlatShader")
+        println!("Deactivate FlatShader")
     }
 
     fn as_vec(&self, model: &Model, cam: &Camera) -> Vec<f32> {
-        let v = [model.transform.as_matrix().as_vec(), cam.transform.as_matrix().as_vec(), cam.get_projection_matrix().as_vec()].concat();
-        v
-    }
-}
-
-impl Default for FlatShader {
-    fn default() -> Self {
-        Self {
-        }
+        let model_matrix = model.transform.relative_to(cam.get_position()).as_matrix();
+        [model_matrix.as_vec(), cam.relative_view_matrix().as_vec(), cam.get_projection_matrix().as_vec()].concat()
     }
 }
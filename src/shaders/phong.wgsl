@@ -8,6 +8,8 @@ struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) normal: vec4<f32>,
     @location(1) frag_color: vec4<f32>,
+    // camera-relative world space, like eye_pos
+    @location(2) world_position: vec3<f32>,
 };
 
 struct Uniforms {
@@ -18,6 +20,7 @@ struct Uniforms {
     m_model: mat4x4<f32>,
     m_view: mat4x4<f32>,
     m_projection: mat4x4<f32>,
+    m_normal: mat4x4<f32>,
 
     light_dir: vec3<f32>,
     eye_pos: vec3<f32>,
@@ -30,21 +33,54 @@ fn vs_main(input: VertexInput) -> VertexOutput {
     let m = uniforms.m_model;
     let v = uniforms.m_view;
     let p = uniforms.m_projection;
-    var pos = p*v*m*vec4<f32>(input.v_pos.xyz, 1);
+    let world = m*vec4<f32>(input.v_pos.xyz, 1);
+    var pos = p*v*world;
 
     var out: VertexOutput;
     out.position = pos;
-    out.normal = m * vec4<f32>(input.v_normal, 1);
+    out.normal = uniforms.m_normal * vec4<f32>(input.v_normal, 0);
     out.frag_color = vec4<f32>(input.v_pos.xyz,1);
+    out.world_position = world.xyz;
     return out;
 }
 
+const BACK_FACE_COLOR = vec4<f32>(0.0, 0.6, 1.0, 1.0);
+
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(input);
+}
+
+// faceted: the face normal comes from how the position changes across the triangle
+@fragment
+fn fs_flat(input: VertexOutput) -> @location(0) vec4<f32> {
+    var n = normalize(cross(dpdx(input.world_position), dpdy(input.world_position)));
+    if dot(n, uniforms.eye_pos - input.world_position) < 0 {
+        n = -n;
+    }
+    let d = max(0, dot(n, normalize(-uniforms.light_dir)));
+    return vec4<f32>((0.2 * uniforms.ambient + d * uniforms.diffuse).rgb, 1.0);
+}
+
+@fragment
+fn fs_normals(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(normalize(input.normal.xyz) * 0.5 + 0.5, 1.0);
+}
+
+// diagnostic: back-facing triangles are painted flat so inverted winding stands out
+@fragment
+fn fs_winding(input: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    if !front_facing {
+        return BACK_FACE_COLOR;
+    }
+    return shade(input);
+}
+
+fn shade(input: VertexOutput) -> vec4<f32> {
     //diffuse
     var d = max(0, dot(normalize(input.normal.xyz), normalize(-uniforms.light_dir)));
     //specular
-    let e = uniforms.eye_pos - input.position.xyz;
+    let e = uniforms.eye_pos - input.world_position;
     let h = 0.5 * (e - uniforms.light_dir);
     let s = pow(max(0, dot(normalize(input.normal.xyz), normalize(h))), 15);
 
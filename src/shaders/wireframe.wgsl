@@ -0,0 +1,74 @@
+struct VertexInput {
+    @location(0) v_pos: vec3<f32>,
+    @location(1) v_normal: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) barycentric: vec3<f32>,
+};
+
+// Shares the phong layout so both pipelines can be fed the same uniform buffer, which ends with the
+// colour the frame is cleared to.
+struct Uniforms {
+    ambient: vec4<f32>,
+    diffuse: vec4<f32>,
+    specular: vec4<f32>,
+
+    m_model: mat4x4<f32>,
+    m_view: mat4x4<f32>,
+    m_projection: mat4x4<f32>,
+    m_normal: mat4x4<f32>,
+
+    light_dir: vec3<f32>,
+    eye_pos: vec3<f32>,
+
+    background: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+const LINE_COLOR = vec4<f32>(1.0, 1.0, 1.0, 1.0);
+const HIGHLIGHT_COLOR = vec4<f32>(1.0, 0.8, 0.0, 1.0);
+const LINE_WIDTH = 1.0;
+
+@vertex
+fn vs_main(input: VertexInput, @builtin(vertex_index) index: u32) -> VertexOutput {
+    let m = uniforms.m_model;
+    let v = uniforms.m_view;
+    let p = uniforms.m_projection;
+
+    var out: VertexOutput;
+    out.position = p*v*m*vec4<f32>(input.v_pos.xyz, 1);
+    // only meaningful for unindexed draws, where every triangle owns three consecutive vertices
+    let corner = index % 3u;
+    out.barycentric = vec3<f32>(f32(corner == 0u), f32(corner == 1u), f32(corner == 2u));
+    return out;
+}
+
+@fragment
+fn fs_line(input: VertexOutput) -> @location(0) vec4<f32> {
+    return LINE_COLOR;
+}
+
+@fragment
+fn fs_background(input: VertexOutput) -> @location(0) vec4<f32> {
+    return uniforms.background;
+}
+
+// the picked triangle
+@fragment
+fn fs_highlight(input: VertexOutput) -> @location(0) vec4<f32> {
+    return HIGHLIGHT_COLOR;
+}
+
+// fallback for adapters without PolygonMode::Line: keep only fragments close to a triangle edge
+@fragment
+fn fs_barycentric(input: VertexOutput) -> @location(0) vec4<f32> {
+    let pixels = input.barycentric / fwidth(input.barycentric);
+    let distance = min(min(pixels.x, pixels.y), pixels.z);
+    if distance > LINE_WIDTH {
+        discard;
+    }
+    return LINE_COLOR;
+}
@@ -0,0 +1,214 @@
+//! Kernels behind the f32 math types: NEON on aarch64, SSE2 for the quaternion product on x86_64,
+//! and the scalar versions everything else falls back to. Matrices are rows of four, as
+//! [`crate::matrix::Mat4`] stores them, and quaternions are `[w, i, j, k]`.
+//!
+//! Everything here is `#[inline]`, since the math types of other crates call it for every product.
+
+pub type Rows<T> = [[T; 4]; 4];
+
+/// Plain loops over the elements, for f64, other targets and as the baseline in benchmarks.
+pub mod scalar {
+    use super::Rows;
+    use crate::vector::Float;
+
+    /// `a * b`, with vectors multiplied from the left.
+    #[inline]
+    pub fn mat4_mul<T: Float>(a: &Rows<T>, b: &Rows<T>) -> Rows<T> {
+        let mut result = [[T::from(0.); 4]; 4];
+        for (r, row) in result.iter_mut().enumerate() {
+            *row = vec4_mul_mat4(a[r], b);
+        }
+        result
+    }
+
+    #[inline]
+    pub fn vec4_mul_mat4<T: Float>(v: [T; 4], m: &Rows<T>) -> [T; 4] {
+        let mut result = [T::from(0.); 4];
+        for (c, value) in result.iter_mut().enumerate() {
+            *value = v[0] * m[0][c] + v[1] * m[1][c] + v[2] * m[2][c] + v[3] * m[3][c];
+        }
+        result
+    }
+
+    /// Hamilton product.
+    #[inline]
+    pub fn quat_mul<T: Float>(a: [T; 4], b: [T; 4]) -> [T; 4] {
+        let [aw, ai, aj, ak] = a;
+        let [bw, bi, bj, bk] = b;
+        [
+            aw * bw - ai * bi - aj * bj - ak * bk,
+            aw * bi + ai * bw + aj * bk - ak * bj,
+            aw * bj - ai * bk + aj * bw + ak * bi,
+            aw * bk + ai * bj - aj * bi + ak * bw,
+        ]
+    }
+}
+
+// The Hamilton product grouped by the components of `a`:
+//   aw * ( bw,  bi,  bj,  bk)
+//   ai * (-bi,  bw, -bk,  bj)
+//   aj * (-bj,  bk,  bw, -bi)
+//   ak * (-bk, -bj,  bi,  bw)
+#[cfg(feature = "simd")]
+const QUAT_SIGN_I: [f32; 4] = [-1., 1., -1., 1.];
+#[cfg(feature = "simd")]
+const QUAT_SIGN_J: [f32; 4] = [-1., 1., 1., -1.];
+#[cfg(feature = "simd")]
+const QUAT_SIGN_K: [f32; 4] = [-1., -1., 1., 1.];
+
+/// Only the quaternion product: the compiler already turns the scalar matrix loops into the same
+/// broadcast, multiply and add SSE2 code a kernel would use, and without FMA there is nothing
+/// left to gain over them.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod sse2 {
+    use std::arch::x86_64::*;
+    use super::{QUAT_SIGN_I, QUAT_SIGN_J, QUAT_SIGN_K};
+
+    #[inline]
+    fn load(v: &[f32; 4]) -> __m128 {
+        // SAFETY: reads exactly the four floats of `v`; unaligned loads have no alignment requirement
+        unsafe { _mm_loadu_ps(v.as_ptr()) }
+    }
+
+    #[inline]
+    fn store(v: __m128) -> [f32; 4] {
+        let mut out = [0.; 4];
+        // SAFETY: writes exactly the four floats of `out`
+        unsafe { _mm_storeu_ps(out.as_mut_ptr(), v) };
+        out
+    }
+
+    #[inline]
+    pub fn quat_mul(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+        let q = load(&b);
+        let (si, sj, sk) = (load(&QUAT_SIGN_I), load(&QUAT_SIGN_J), load(&QUAT_SIGN_K));
+        // SAFETY: SSE2 is part of the x86_64 baseline, so these register-only intrinsics are always available
+        let r = unsafe {
+            // lanes 1, 0, 3, 2 / 2, 3, 0, 1 / 3, 2, 1, 0 of b
+            let bi = _mm_shuffle_ps::<0b10_11_00_01>(q, q);
+            let bj = _mm_shuffle_ps::<0b01_00_11_10>(q, q);
+            let bk = _mm_shuffle_ps::<0b00_01_10_11>(q, q);
+            let w = _mm_mul_ps(_mm_set1_ps(a[0]), q);
+            let i = _mm_mul_ps(_mm_set1_ps(a[1]), _mm_mul_ps(bi, si));
+            let j = _mm_mul_ps(_mm_set1_ps(a[2]), _mm_mul_ps(bj, sj));
+            let k = _mm_mul_ps(_mm_set1_ps(a[3]), _mm_mul_ps(bk, sk));
+            _mm_add_ps(_mm_add_ps(w, i), _mm_add_ps(j, k))
+        };
+        store(r)
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+mod neon {
+    use std::arch::aarch64::*;
+    use super::{Rows, QUAT_SIGN_I, QUAT_SIGN_J, QUAT_SIGN_K};
+
+    #[inline]
+    fn load(v: &[f32; 4]) -> float32x4_t {
+        // SAFETY: reads exactly the four floats of `v`
+        unsafe { vld1q_f32(v.as_ptr()) }
+    }
+
+    #[inline]
+    fn store(v: float32x4_t) -> [f32; 4] {
+        let mut out = [0.; 4];
+        // SAFETY: writes exactly the four floats of `out`
+        unsafe { vst1q_f32(out.as_mut_ptr(), v) };
+        out
+    }
+
+    #[inline]
+    fn row_times(v: &[f32; 4], b: &[float32x4_t; 4]) -> float32x4_t {
+        // SAFETY: NEON is part of the aarch64 baseline, so these register-only intrinsics are always available
+        unsafe {
+            let r = vmulq_n_f32(b[0], v[0]);
+            let r = vfmaq_n_f32(r, b[1], v[1]);
+            let r = vfmaq_n_f32(r, b[2], v[2]);
+            vfmaq_n_f32(r, b[3], v[3])
+        }
+    }
+
+    #[inline]
+    pub fn mat4_mul(a: &Rows<f32>, b: &Rows<f32>) -> Rows<f32> {
+        let b = [load(&b[0]), load(&b[1]), load(&b[2]), load(&b[3])];
+        a.map(|row| store(row_times(&row, &b)))
+    }
+
+    #[inline]
+    pub fn vec4_mul_mat4(v: [f32; 4], m: &Rows<f32>) -> [f32; 4] {
+        let m = [load(&m[0]), load(&m[1]), load(&m[2]), load(&m[3])];
+        store(row_times(&v, &m))
+    }
+
+    #[inline]
+    pub fn quat_mul(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+        let q = load(&b);
+        let (si, sj, sk) = (load(&QUAT_SIGN_I), load(&QUAT_SIGN_J), load(&QUAT_SIGN_K));
+        // SAFETY: NEON is part of the aarch64 baseline, so these register-only intrinsics are always available
+        let r = unsafe {
+            // lanes 1, 0, 3, 2 / 2, 3, 0, 1 / 3, 2, 1, 0 of b
+            let bi = vrev64q_f32(q);
+            let bj = vextq_f32::<2>(q, q);
+            let bk = vrev64q_f32(bj);
+            let r = vmulq_n_f32(q, a[0]);
+            let r = vfmaq_n_f32(r, vmulq_f32(bi, si), a[1]);
+            let r = vfmaq_n_f32(r, vmulq_f32(bj, sj), a[2]);
+            vfmaq_n_f32(r, vmulq_f32(bk, sk), a[3])
+        };
+        store(r)
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod arch {
+    pub use super::scalar::{mat4_mul, vec4_mul_mat4};
+    pub use super::sse2::quat_mul;
+}
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+use neon as arch;
+#[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+use scalar as arch;
+
+/// The fastest `a * b` for this target.
+#[inline]
+pub fn mat4_mul(a: &Rows<f32>, b: &Rows<f32>) -> Rows<f32> {
+    arch::mat4_mul(a, b)
+}
+
+/// The fastest `v * m` for this target.
+#[inline]
+pub fn vec4_mul_mat4(v: [f32; 4], m: &Rows<f32>) -> [f32; 4] {
+    arch::vec4_mul_mat4(v, m)
+}
+
+/// The fastest Hamilton product for this target.
+#[inline]
+pub fn quat_mul(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    arch::quat_mul(a, b)
+}
+
+/// Picks the kernels for a scalar type: SIMD for f32, plain loops for everything else.
+pub trait Kernels: Sized {
+    fn mat4_mul(a: &Rows<Self>, b: &Rows<Self>) -> Rows<Self>;
+    fn vec4_mul_mat4(v: [Self; 4], m: &Rows<Self>) -> [Self; 4];
+    fn quat_mul(a: [Self; 4], b: [Self; 4]) -> [Self; 4];
+}
+
+impl Kernels for f32 {
+    #[inline]
+    fn mat4_mul(a: &Rows<f32>, b: &Rows<f32>) -> Rows<f32> { mat4_mul(a, b) }
+    #[inline]
+    fn vec4_mul_mat4(v: [f32; 4], m: &Rows<f32>) -> [f32; 4] { vec4_mul_mat4(v, m) }
+    #[inline]
+    fn quat_mul(a: [f32; 4], b: [f32; 4]) -> [f32; 4] { quat_mul(a, b) }
+}
+
+impl Kernels for f64 {
+    #[inline]
+    fn mat4_mul(a: &Rows<f64>, b: &Rows<f64>) -> Rows<f64> { scalar::mat4_mul(a, b) }
+    #[inline]
+    fn vec4_mul_mat4(v: [f64; 4], m: &Rows<f64>) -> [f64; 4] { scalar::vec4_mul_mat4(v, m) }
+    #[inline]
+    fn quat_mul(a: [f64; 4], b: [f64; 4]) -> [f64; 4] { scalar::quat_mul(a, b) }
+}
+
@@ -0,0 +1,279 @@
+//! Simplification by edge collapses ordered by quadric error, after Garland and Heckbert, and
+//! chains of simplified meshes to draw far from the camera.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use crate::half_edge::HalfEdgeMesh;
+use crate::mesh::Mesh;
+use crate::vector::Vector3;
+
+/// Weight of the planes that hold boundaries and seams in place, next to the planes of the faces.
+const BOUNDARY_WEIGHT: f64 = 10.;
+
+/// When [`Mesh::simplify`] stops: at whichever limit comes first. Without either it goes on
+/// until no edge can be collapsed.
+///
+/// Errors are quadric errors: the square root of the summed squared distances from a vertex to
+/// the planes of the original faces around it, with the planes holding boundaries and seams
+/// counted ten times. They are in the mesh's units and grow with how far vertices leave those
+/// planes, but are not a bound on how far the surface moved.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct SimplifyOptions {
+    /// Stop once the mesh has this many triangles or fewer.
+    pub target_triangles: Option<usize>,
+    /// Stop before a collapse whose quadric error is larger than this.
+    pub max_error: Option<f32>,
+}
+
+/// How [`Mesh::generate_lods`] makes levels of detail.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LodOptions {
+    /// Levels besides the full mesh.
+    pub levels: usize,
+    /// Triangles kept from one level to the next.
+    pub ratio: f32,
+    /// Error allowed per unit of distance from the camera. A level is drawn from the distance
+    /// where its error is this small; 0.001 is about a pixel across a 1000 pixel, 60° view.
+    pub tolerance: f32,
+}
+
+impl Default for LodOptions {
+    fn default() -> Self {
+        Self {
+            levels: 3,
+            ratio: 0.25,
+            tolerance: 0.001,
+        }
+    }
+}
+
+/// A simplified mesh and from how far away to draw it.
+pub struct Lod {
+    pub mesh: Mesh,
+    /// Largest quadric error of the collapses that made it, see [`SimplifyOptions`].
+    pub error: f32,
+    /// Distance from the camera, in the mesh's units, from which this level is drawn.
+    pub distance: f32,
+}
+
+/// The sum of squared distances to a set of planes, as the symmetric matrix of the plane
+/// equations, upper triangle by rows.
+#[derive(Copy, Clone, Debug, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    /// The plane through `point` facing `normal`, which must be of unit length.
+    fn plane(normal: Vector3<f64>, point: Vector3<f64>, weight: f64) -> Self {
+        let [a, b, c, d] = [normal.x, normal.y, normal.z, -normal.dot(&point)];
+        Self([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|v| v * weight))
+    }
+
+    fn add(&self, rhs: &Self) -> Self {
+        let mut sum = self.0;
+        sum.iter_mut().zip(rhs.0).for_each(|(s, r)| *s += r);
+        Self(sum)
+    }
+
+    fn error(&self, p: Vector3<f64>) -> f64 {
+        let [aa, ab, ac, ad, bb, bc, bd, cc, cd, dd] = self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        let e = aa * x * x + 2. * ab * x * y + 2. * ac * x * z + 2. * ad * x
+            + bb * y * y + 2. * bc * y * z + 2. * bd * y
+            + cc * z * z + 2. * cd * z
+            + dd;
+        e.max(0.)
+    }
+
+    /// The point of least error, None where the planes do not pin one down, e.g. on a flat
+    /// part of the surface.
+    fn minimum(&self) -> Option<Vector3<f64>> {
+        let [aa, ab, ac, ad, bb, bc, bd, cc, cd, _] = self.0;
+        // Cramer's rule on the gradient being zero
+        let det3 = |m: [[f64; 3]; 3]| {
+            m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+                - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+                + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+        };
+        let a = [[aa, ab, ac], [ab, bb, bc], [ac, bc, cc]];
+        let det = det3(a);
+        let scale = aa.abs().max(bb.abs()).max(cc.abs());
+        if det.abs() <= 1e-9 * scale * scale * scale {
+            return None;
+        }
+        let rhs = [-ad, -bd, -cd];
+        let solve = |column: usize| {
+            let mut m = a;
+            (0..3).for_each(|row| m[row][column] = rhs[row]);
+            det3(m) / det
+        };
+        Some(Vector3::new(solve(0), solve(1), solve(2)))
+    }
+}
+
+/// A collapse waiting in the queue. The cost goes first as the bits of a non-negative f64,
+/// which order the same way as the numbers.
+type Candidate = Reverse<(u64, usize, usize, usize, u32, u32)>;
+
+struct Simplifier {
+    mesh: HalfEdgeMesh,
+    quadrics: Vec<Quadric>,
+    /// Bumped whenever a vertex changes, making queued collapses that touch it stale.
+    versions: Vec<u32>,
+    queue: BinaryHeap<Candidate>,
+}
+
+impl Simplifier {
+    fn position(&self, v: usize) -> Vector3<f64> {
+        self.mesh.get_position(v).cast()
+    }
+
+    /// Where collapsing `h` would put its end and at what cost, None where it must not be
+    /// collapsed: seams stay, and boundaries only shorten along themselves.
+    fn evaluate(&self, h: usize) -> Option<(Vector3<f64>, f64)> {
+        let (u, v) = (self.mesh.get_origin(h), self.mesh.get_target(h));
+        if self.mesh.is_seam_vertex(u) {
+            return None;
+        }
+        let u_boundary = self.mesh.is_boundary_vertex(u);
+        if u_boundary && !self.mesh.is_boundary_edge(h) {
+            return None;
+        }
+        let quadric = self.quadrics[u].add(&self.quadrics[v]);
+        let fixed = u_boundary || self.mesh.is_boundary_vertex(v) || self.mesh.is_seam_vertex(v);
+        let (pu, pv) = (self.position(u), self.position(v));
+        let position = match quadric.minimum() {
+            Some(p) if !fixed => p,
+            // staying at the end keeps its texture coordinates right
+            _ if fixed => pv,
+            _ => [pv, pu, (pu + pv) * 0.5].into_iter()
+                .fold((pv, f64::INFINITY), |best, p| if quadric.error(p) < best.1 { (p, quadric.error(p)) } else { best }).0,
+        };
+        Some((position, quadric.error(position)))
+    }
+
+    fn push(&mut self, h: usize) {
+        if let Some((_, cost)) = self.evaluate(h) {
+            let (u, v) = (self.mesh.get_origin(h), self.mesh.get_target(h));
+            self.queue.push(Reverse((cost.to_bits(), h, u, v, self.versions[u], self.versions[v])));
+        }
+    }
+
+    /// Whether moving the ends of `h` to `position` would turn any face that stays more than
+    /// about 80° or squash it flat.
+    fn folds(&self, h: usize, position: Vector3<f64>) -> bool {
+        let (u, v) = (self.mesh.get_origin(h), self.mesh.get_target(h));
+        for f in self.mesh.get_vertex_faces(u).into_iter().chain(self.mesh.get_vertex_faces(v)) {
+            let corners = self.mesh.get_face_vertices(f);
+            if corners.contains(&u) && corners.contains(&v) {
+                continue;
+            }
+            let before = corners.map(|c| self.position(c));
+            let after = corners.map(|c| if c == u || c == v { position } else { self.position(c) });
+            let normal = |p: [Vector3<f64>; 3]| (p[1] - p[0]).cross(&(p[2] - p[0]));
+            let (n0, n1) = (normal(before), normal(after));
+            if n1.dot(&n0) <= 0.2 * n0.length() * n1.length() || n1.length() <= 1e-12 * n0.length() {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl Mesh {
+    /// Collapses edges, cheapest first, until `options` says stop, keeping the shape as close
+    /// as it can. Boundaries keep their shape and vertices where texture coordinates or colours
+    /// change across an edge stay where they are, so textures do not tear. Normals are
+    /// recomputed, smooth unless every triangle had one normal. Returns the simplified mesh and
+    /// the largest quadric error of its collapses, see [`SimplifyOptions`].
+    pub fn simplify(&self, options: &SimplifyOptions) -> (Mesh, f32) {
+        let mesh = HalfEdgeMesh::new(self);
+        let mut quadrics = vec![Quadric::default(); mesh.get_vertices().max().map_or(0, |v| v + 1)];
+        for f in mesh.get_faces() {
+            let p = mesh.get_face_vertices(f).map(|v| mesh.get_position(v).cast::<f64>());
+            let normal = (p[1] - p[0]).cross(&(p[2] - p[0])).normalized();
+            let plane = Quadric::plane(normal, p[0], 1.);
+            for h in mesh.get_face_half_edges(f) {
+                let v = mesh.get_origin(h);
+                quadrics[v] = quadrics[v].add(&plane);
+                // planes upright on boundary and seam edges hold them in place
+                if mesh.is_boundary_edge(h) || mesh.is_seam_edge(h) {
+                    let (a, b) = (mesh.get_position(v).cast::<f64>(), mesh.get_position(mesh.get_target(h)).cast::<f64>());
+                    let upright = (b - a).cross(&normal).normalized();
+                    let edge = Quadric::plane(upright, a, BOUNDARY_WEIGHT);
+                    let w = mesh.get_target(h);
+                    quadrics[v] = quadrics[v].add(&edge);
+                    quadrics[w] = quadrics[w].add(&edge);
+                }
+            }
+        }
+
+        let versions = vec![0; quadrics.len()];
+        let mut simplifier = Simplifier { mesh, quadrics, versions, queue: BinaryHeap::new() };
+        for h in simplifier.mesh.get_half_edges().collect::<Vec<_>>() {
+            simplifier.push(h);
+        }
+
+        let target = options.target_triangles.unwrap_or(0);
+        let max_cost = options.max_error.map_or(f64::INFINITY, |e| (e as f64) * (e as f64));
+        let mut triangles = simplifier.mesh.get_face_count();
+        let mut worst: f64 = 0.;
+        while triangles > target {
+            let Some(Reverse((cost, h, u, v, u_version, v_version))) = simplifier.queue.pop() else {
+                break;
+            };
+            let s = &mut simplifier;
+            if s.versions[u] != u_version || s.versions[v] != v_version || s.mesh.get_origin(h) != u || s.mesh.get_target(h) != v {
+                continue;
+            }
+            let cost = f64::from_bits(cost);
+            if cost > max_cost {
+                break;
+            }
+            let Some((position, _)) = s.evaluate(h) else {
+                continue;
+            };
+            let removed = [h, s.mesh.get_twin(h)].iter().filter(|&&e| s.mesh.get_face(e).is_some()).count();
+            if s.folds(h, position) || !s.mesh.collapse_edge(h) {
+                continue;
+            }
+            s.mesh.set_position(v, position.cast());
+            s.quadrics[v] = s.quadrics[v].add(&s.quadrics[u]);
+            s.versions[u] += 1;
+            s.versions[v] += 1;
+            triangles -= removed;
+            worst = worst.max(cost);
+            for e in s.mesh.get_outgoing(v) {
+                let twin = s.mesh.get_twin(e);
+                s.push(e);
+                s.push(twin);
+            }
+        }
+
+        let mut simplified = simplifier.mesh.to_mesh();
+        let flat = (0..self.get_triangle_count()).all(|t| {
+            let normals = [0, 1, 2].map(|c| self.vb.get_vertex(self.ib.get_index(3 * t + c) as usize).get_normal());
+            normals[0] == normals[1] && normals[1] == normals[2]
+        });
+        simplified.recompute_normals(self.attributes.normals && !flat);
+        simplified.attributes.normals = self.attributes.normals;
+        (simplified, worst.sqrt() as f32)
+    }
+
+    /// Simplifies the mesh to fewer triangles level by level, stopping early once a level would
+    /// hardly be smaller than the one before.
+    pub fn generate_lods(&self, options: &LodOptions) -> Vec<Lod> {
+        let mut lods: Vec<Lod> = Vec::new();
+        let mut triangles = self.get_triangle_count();
+        for level in 1..=options.levels {
+            let target = (self.get_triangle_count() as f64 * (options.ratio as f64).powi(level as i32)).ceil() as usize;
+            let (mesh, error) = self.simplify(&SimplifyOptions { target_triangles: Some(target), max_error: None });
+            if mesh.get_triangle_count() as f64 > 0.9 * triangles as f64 {
+                break;
+            }
+            triangles = mesh.get_triangle_count();
+            let previous = lods.last().map_or(0., |lod| lod.distance);
+            lods.push(Lod { mesh, error, distance: (error / options.tolerance).max(previous) });
+        }
+        lods
+    }
+}
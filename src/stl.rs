@@ -0,0 +1,115 @@
+//! STL, the triangle soup most 3D printing tools exchange: a face normal and three positions per
+//! triangle, as text or binary.
+
+use std::io::{self, ErrorKind, Write};
+use crate::buffers::Vertex;
+use crate::mesh::{Mesh, Submesh};
+use crate::vector::Vector3;
+
+const HEADER_SIZE: usize = 80;
+const TRIANGLE_SIZE: usize = 50;
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Reads binary or text STL. Binary files are recognised by their size matching the triangle
+/// count in the header, as some binary headers also start with `solid`. Each `solid` of a text
+/// file becomes a submesh named after it.
+pub fn read(data: &[u8]) -> io::Result<Mesh> {
+    if data.len() >= HEADER_SIZE + 4 {
+        let count = u32::from_le_bytes(data[HEADER_SIZE..HEADER_SIZE + 4].try_into().unwrap()) as usize;
+        if data.len() == HEADER_SIZE + 4 + count * TRIANGLE_SIZE {
+            return Ok(read_binary(&data[HEADER_SIZE + 4..], count));
+        }
+    }
+    if !data.trim_ascii_start().starts_with(b"solid") {
+        return Err(invalid(String::from("neither text STL starting with solid nor binary STL of the right size")));
+    }
+    read_text(std::str::from_utf8(data).map_err(|e| invalid(e.to_string()))?)
+}
+
+fn add_triangle(mesh: &mut Mesh, normal: Vector3<f32>, corners: [Vector3<f32>; 3]) {
+    // files often leave the normal zero, or let it disagree with the winding
+    let face = (corners[1] - corners[0]).cross(&(corners[2] - corners[0])).normalized();
+    let normal = if normal.length_squared() > 0. && normal.dot(&face) >= 0. { normal.normalized() } else { face };
+    for p in corners {
+        mesh.ib.add_index(mesh.vb.get_vertex_count() as u32);
+        mesh.vb.add_vertex(Vertex::new([p.x, p.y, p.z], [normal.x, normal.y, normal.z], [0., 0.], [1., 0., 0., 1.]));
+    }
+}
+
+fn read_binary(data: &[u8], count: usize) -> Mesh {
+    let mut mesh = Mesh::default();
+    for triangle in data.chunks_exact(TRIANGLE_SIZE).take(count) {
+        let v = |i: usize| {
+            let f = |j: usize| f32::from_le_bytes(triangle[12 * i + 4 * j..12 * i + 4 * j + 4].try_into().unwrap());
+            Vector3::new(f(0), f(1), f(2))
+        };
+        add_triangle(&mut mesh, v(0), [v(1), v(2), v(3)]);
+    }
+    mesh
+}
+
+fn read_text(text: &str) -> io::Result<Mesh> {
+    let mut mesh = Mesh::default();
+    let mut normal = Vector3::default();
+    let mut corners = Vec::with_capacity(3);
+    for (number, line) in text.lines().enumerate() {
+        let error = |message: &str| invalid(format!("line {}: {}", number + 1, message));
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let vector = |values: &[&str]| -> io::Result<Vector3<f32>> {
+            let v: Vec<f32> = values.iter().map(|v| v.parse::<f32>().map_err(|_| error(&format!("{} is not a number", v))))
+                .collect::<io::Result<_>>()?;
+            match v[..] {
+                [x, y, z] => Ok(Vector3::new(x, y, z)),
+                _ => Err(error("expected three numbers")),
+            }
+        };
+        match words[..] {
+            ["solid", ref name @ ..] => mesh.submeshes.push(Submesh {
+                group: if name.is_empty() { String::from("default") } else { name.join(" ") },
+                material: None,
+                first_triangle: mesh.get_triangle_count(),
+                triangle_count: 0,
+            }),
+            ["facet", "normal", ref values @ ..] => {
+                normal = vector(values)?;
+                corners.clear();
+            }
+            ["vertex", ref values @ ..] => corners.push(vector(values)?),
+            ["endfacet"] => {
+                let [a, b, c] = corners[..] else {
+                    return Err(error("a facet needs three vertices"));
+                };
+                add_triangle(&mut mesh, normal, [a, b, c]);
+                if let Some(submesh) = mesh.submeshes.last_mut() {
+                    submesh.triangle_count += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    mesh.submeshes.retain(|s| s.triangle_count > 0);
+    Ok(mesh)
+}
+
+/// Writes binary STL. Only positions are kept, with the face normal of each triangle.
+pub fn write(mesh: &Mesh, out: &mut impl Write) -> io::Result<()> {
+    let mut header = [0u8; HEADER_SIZE];
+    header[..8].copy_from_slice(b"triangle");
+    out.write_all(&header)?;
+    let count = u32::try_from(mesh.get_triangle_count()).map_err(|_| invalid(String::from("too many triangles for STL")))?;
+    out.write_all(&count.to_le_bytes())?;
+    for t in 0..mesh.get_triangle_count() {
+        let triangle = mesh.get_triangle(t);
+        let normal = (triangle.b - triangle.a).cross(&(triangle.c - triangle.a)).normalized();
+        for v in [normal, triangle.a, triangle.b, triangle.c] {
+            for f in [v.x, v.y, v.z] {
+                out.write_all(&f.to_le_bytes())?;
+            }
+        }
+        out.write_all(&[0, 0])?;
+    }
+    Ok(())
+}
@@ -1,72 +1,108 @@
-use crate::matrix::Matrix4;
-use crate::vector::Vector3;
-use crate::quaternion::Quaternion;
-
-pub struct Transform {
-    position: Vector3<f32>,
-    rotation: Quaternion,
-    scale:    Vector3<f32>
+use crate::matrix::Mat4;
+use crate::vector::{Float, Vector3};
+use crate::quaternion::Quat;
+
+/// Translation, rotation and scale of any float type, see [`Transform`] and [`DTransform`].
+#[derive(Clone, Copy)]
+pub struct Trs<T> {
+    position: Vector3<T>,
+    rotation: Quat<T>,
+    scale:    Vector3<T>
 }
 
-impl Transform {
-    pub fn as_matrix(&self) -> Matrix4 {
-        let t = Matrix4::translate(self.position);
+pub type Transform = Trs<f32>;
+/// Double precision placement, for models and cameras far from the origin.
+pub type DTransform = Trs<f64>;
+
+impl<T: Float> Trs<T> {
+    /// Builds a transform from an affine matrix, see [`Mat4::decompose`].
+    pub fn from_matrix(m: Mat4<T>) -> Self {
+        let (position, rotation, scale) = m.decompose();
+        Self {
+            position,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Scales along the local axes, then rotates, then translates.
+    pub fn as_matrix(&self) -> Mat4<T> {
+        let t = Mat4::translate(self.position);
         let r = self.rotation.as_mat4();
-        let s = Matrix4::scale(self.scale);
+        let s = Mat4::scale(self.scale);
+
+        s*r*t
+    }
 
-        r*s*t
+    /// Inverse of [`Trs::as_matrix`], e.g. the view matrix of a camera.
+    pub fn inverse_matrix(&self) -> Mat4<T> {
+        let t = Mat4::translate(-self.position);
+        let r = self.rotation.conjugate().as_mat4();
+        let l = T::from(1.);
+        let s = Mat4::scale(Vector3::new(l / self.scale.x, l / self.scale.y, l / self.scale.z));
+
+        t*r*s
+    }
+
+    /// The same placement as seen from `origin`, in single precision. Subtracting before narrowing
+    /// keeps things near `origin` exact even when both sit at huge coordinates.
+    pub fn relative_to(&self, origin: Vector3<T>) -> Transform {
+        Trs {
+            position: (self.position - origin).cast(),
+            rotation: self.rotation.cast(),
+            scale: self.scale.cast(),
+        }
+    }
+
+    /// Converts the components, e.g. to move an f32 transform into a [`DTransform`].
+    pub fn cast<U: Float>(&self) -> Trs<U> {
+        Trs {
+            position: self.position.cast(),
+            rotation: self.rotation.cast(),
+            scale: self.scale.cast(),
+        }
     }
 
-    pub fn look_at(&mut self, target: Vector3<f32>, up: Vector3<f32>){
-        let pos = self.position;
-        let mut f = target - pos;
-        if f.length_squared() == 0. {
-            println!("Already looking at target");
+    /// Turns the transform so that its -z axis points at `target` and its y axis leans towards `up`.
+    /// A target at the transform's own position gives no direction, so the rotation is kept.
+    pub fn look_at(&mut self, target: Vector3<T>, up: Vector3<T>){
+        if (self.position - target).length_squared() == T::from(0.) {
             return
         }
-        f.normalize();
-        let mut u = up.clone();
-        u.normalize();
-        let mut r = f.cross(&u);
-        r.normalize();
-        u = r.cross(&f);
-
-        self.set_position(Vector3::new(-r.dot(&pos), -u.dot(&pos), f.dot(&pos)));   
-        self.set_rotation(Quaternion::from_euler_angles(f32::asin(-f.y), f32::asin(r.z), f32::asin(u.x)));
-        self.set_scale(Vector3::new(r.x, u.y, -f.z));
+        self.set_rotation(Quat::look_rotation(target - self.position, up));
     }
 
-    pub fn set_position(&mut self, pos: Vector3<f32>){
+    pub fn set_position(&mut self, pos: Vector3<T>){
         self.position = pos;
     }
 
-    pub fn set_scale(&mut self, scale: Vector3<f32>){
+    pub fn set_scale(&mut self, scale: Vector3<T>){
         self.scale = scale;
     }
 
-    pub fn set_rotation(&mut self, rot: Quaternion){
+    pub fn set_rotation(&mut self, rot: Quat<T>){
         self.rotation = rot;
     }
 
-    pub fn get_position(&self) -> Vector3<f32> {
+    pub fn get_position(&self) -> Vector3<T> {
         self.position
     }
 
-    pub fn get_scale(&self) -> Vector3<f32>{
+    pub fn get_scale(&self) -> Vector3<T>{
         self.scale
     }
 
-    pub fn get_rotation(&self) -> Quaternion {
+    pub fn get_rotation(&self) -> Quat<T> {
         self.rotation
     }
 }
 
-impl Default for Transform {
+impl<T: Float> Default for Trs<T> {
     fn default() -> Self {
         Self {
             position: Vector3::default(),
-            rotation: Quaternion::default(),
-            scale: Vector3::new(1., 1., 1.),
+            rotation: Quat::default(),
+            scale: Vector3::splat(T::from(1.)),
         }
     }
 }
@@ -1,167 +1,342 @@
-use std::ops::{Add, AddAssign, Div, Mul, Sub};
+use crate::simd::Kernels;
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Vector2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct Vector3<T> {
     pub x: T,
     pub y: T,
     pub z: T,
 }
 
-impl Vector3<f32> {
-    pub fn new(x: f32, y: f32, z: f32) -> Self {
-        Self {
-            x,
-            y,
-            z,
-        }
-    }
+/// Aligned so the four components load as one SIMD register.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[repr(C, align(16))]
+pub struct Vector4<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+    pub w: T,
+}
 
-    pub fn normalize(&mut self) -> &Self{
-        let n = self.length();
-        if n != 0. {
-            self.x /= n;
-            self.y /= n;
-            self.z /= n;
+/// Operators and methods shared by all vector sizes, for any numeric `T`.
+macro_rules! impl_vector {
+    ($name:ident, $n:literal, $($field:ident: $index:literal),+) => {
+        impl<T> $name<T> {
+            pub const fn new($($field: T),+) -> Self {
+                Self { $($field),+ }
+            }
         }
-        self
-    }
-
-    pub fn length(&self) -> f32 {
-        self.length_squared().sqrt()
-    }
 
-    pub fn length_squared(&self) -> f32 {
-        self.dot(self)
-    }
+        impl<T: Copy> $name<T> {
+            pub fn splat(v: T) -> Self {
+                Self { $($field: v),+ }
+            }
 
-    pub fn dot(&self, rhs: &Self) -> f32 {
-        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
-    }
+            pub fn as_array(&self) -> [T; $n] {
+                [$(self.$field),+]
+            }
 
-    pub fn cross(&self, rhs: &Self) -> Self {
-        let mut res = Vector3::new(0., 0., 0.);
+            pub fn as_vec(&self) -> Vec<T> {
+                vec![$(self.$field),+]
+            }
 
-        res.x = self.y * rhs.z - self.z * rhs.y;
-        res.y = self.z * rhs.x - self.x * rhs.z;
-        res.z = self.x * rhs.y - self.y * rhs.x;
+            /// Applies `f` to every component.
+            pub fn map<U>(self, f: impl Fn(T) -> U) -> $name<U> {
+                $name { $($field: f(self.$field)),+ }
+            }
+        }
 
-        res
-    }
+        impl<T: Copy + Add<Output = T> + Mul<Output = T>> $name<T> {
+            pub fn dot(&self, rhs: &Self) -> T {
+                let products = [$(self.$field * rhs.$field),+];
+                products[1..].iter().fold(products[0], |sum, &p| sum + p)
+            }
 
-    pub fn as_vec(&self) -> Vec<f32>{
-        let mut v: Vec<f32> = Vec::new();
+            pub fn length_squared(&self) -> T {
+                self.dot(self)
+            }
+        }
 
-        v.push(self.x);
-        v.push(self.y);
-        v.push(self.z);
+        impl<T: Copy + PartialOrd> $name<T> {
+            /// Component-wise minimum.
+            pub fn min(self, rhs: Self) -> Self {
+                Self { $($field: if rhs.$field < self.$field { rhs.$field } else { self.$field }),+ }
+            }
+
+            /// Component-wise maximum.
+            pub fn max(self, rhs: Self) -> Self {
+                Self { $($field: if rhs.$field > self.$field { rhs.$field } else { self.$field }),+ }
+            }
+
+            /// Component-wise clamp between `lo` and `hi`.
+            pub fn clamp(self, lo: Self, hi: Self) -> Self {
+                self.max(lo).min(hi)
+            }
+        }
 
-        v
-    }
+        impl<T> From<[T; $n]> for $name<T> {
+            fn from([$($field),+]: [T; $n]) -> Self {
+                Self { $($field),+ }
+            }
+        }
 
-    pub fn up() -> Vector3<f32>{ Vector3 { x: 0.0, y: 1.0, z: 0.0 } }
-    pub fn down() -> Vector3<f32>{ Vector3 { x: 0.0, y: -1.0, z: 0.0 } }
-    pub fn right() -> Vector3<f32>{ Vector3 { x: 1.0, y: 0.0, z: 0.0 } }
-    pub fn left() -> Vector3<f32>{ Vector3 { x: -1.0, y: 0.0, z: 0.0 } }
-    pub fn forward() -> Vector3<f32>{ Vector3 { x: 0.0, y: 0.0, z: 1.0 } }
-    pub fn backward() -> Vector3<f32>{ Vector3 { x: 0.0, y: 0.0, z: -1.0 } }
-    
-}
+        impl<T> From<$name<T>> for [T; $n] {
+            fn from(v: $name<T>) -> Self {
+                [$(v.$field),+]
+            }
+        }
 
-impl Default for Vector3<f32> {
-    fn default() -> Self {
-        Self {
-            x: 0.,
-            y: 0.,
-            z: 0.,
+        impl<T> Index<usize> for $name<T> {
+            type Output = T;
+            fn index(&self, index: usize) -> &Self::Output {
+                match index {
+                    $($index => &self.$field,)+
+                    _ => panic!("Index out of bounds"),
+                }
+            }
         }
-    }
-}
 
-impl Add for Vector3<f32>{
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self::Output {
-        let mut res = self;
+        impl<T> IndexMut<usize> for $name<T> {
+            fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+                match index {
+                    $($index => &mut self.$field,)+
+                    _ => panic!("Index out of bounds"),
+                }
+            }
+        }
 
-        res.x += rhs.x;
-        res.y += rhs.y;
-        res.z += rhs.z;
+        impl<T: Neg<Output = T>> Neg for $name<T> {
+            type Output = Self;
+            fn neg(self) -> Self::Output {
+                Self { $($field: -self.$field),+ }
+            }
+        }
 
-        res
-    }
-}
+        impl_vector!(@op $name, Add, add, AddAssign, add_assign, $($field),+);
+        impl_vector!(@op $name, Sub, sub, SubAssign, sub_assign, $($field),+);
+        // component-wise, vectors times scalars are below
+        impl_vector!(@op $name, Mul, mul, MulAssign, mul_assign, $($field),+);
+        impl_vector!(@op $name, Div, div, DivAssign, div_assign, $($field),+);
+    };
+
+    // the operator between two vectors and between a vector and a scalar, plus their assigning forms
+    (@op $name:ident, $op:ident, $fn:ident, $op_assign:ident, $fn_assign:ident, $($field:ident),+) => {
+        impl<T: $op<Output = T>> $op for $name<T> {
+            type Output = Self;
+            fn $fn(self, rhs: Self) -> Self::Output {
+                Self { $($field: self.$field.$fn(rhs.$field)),+ }
+            }
+        }
 
-impl Add<f32> for Vector3<f32> {
-    type Output = Self;
-    fn add(self, rhs: f32) -> Self::Output {
-        let mut res = self;
+        impl<T: Copy + $op<Output = T>> $op<T> for $name<T> {
+            type Output = Self;
+            fn $fn(self, rhs: T) -> Self::Output {
+                Self { $($field: self.$field.$fn(rhs)),+ }
+            }
+        }
 
-        res.x += rhs;
-        res.y += rhs;
-        res.z += rhs;
+        impl<T: $op_assign> $op_assign for $name<T> {
+            fn $fn_assign(&mut self, rhs: Self) {
+                $(self.$field.$fn_assign(rhs.$field);)+
+            }
+        }
 
-        res
-    }
+        impl<T: Copy + $op_assign> $op_assign<T> for $name<T> {
+            fn $fn_assign(&mut self, rhs: T) {
+                $(self.$field.$fn_assign(rhs);)+
+            }
+        }
+    };
 }
 
-impl AddAssign<f32> for Vector3<f32> {
-    fn add_assign(&mut self, rhs: f32) {
-        self.x += rhs;
-        self.y += rhs;
-        self.z += rhs;
-    }
-
+impl_vector!(Vector2, 2, x: 0, y: 1);
+impl_vector!(Vector3, 3, x: 0, y: 1, z: 2);
+impl_vector!(Vector4, 4, x: 0, y: 1, z: 2, w: 3);
+
+/// Floating point scalars, for the math that needs square roots, trigonometry or fractions.
+pub trait Float:
+    Copy + PartialOrd + Default + From<f32> + Kernels
+    + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self>
+    + AddAssign + SubAssign + MulAssign + DivAssign
+{
+    const PI: Self;
+
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn asin(self) -> Self;
+    fn acos(self) -> Self;
+    fn atan2(self, x: Self) -> Self;
+    fn is_finite(self) -> bool;
+    fn to_f64(self) -> f64;
+    /// Rounds to the nearest value when narrowing.
+    fn from_f64(v: f64) -> Self;
 }
 
-impl Sub for Vector3<f32> {
-    type Output = Self;
+macro_rules! impl_float {
+    ($t:ident) => {
+        impl Float for $t {
+            const PI: Self = std::$t::consts::PI;
+
+            fn sqrt(self) -> Self { $t::sqrt(self) }
+            fn abs(self) -> Self { $t::abs(self) }
+            fn sin(self) -> Self { $t::sin(self) }
+            fn cos(self) -> Self { $t::cos(self) }
+            fn tan(self) -> Self { $t::tan(self) }
+            fn asin(self) -> Self { $t::asin(self) }
+            fn acos(self) -> Self { $t::acos(self) }
+            fn atan2(self, x: Self) -> Self { $t::atan2(self, x) }
+            fn is_finite(self) -> bool { $t::is_finite(self) }
+            fn to_f64(self) -> f64 { self as f64 }
+            fn from_f64(v: f64) -> Self { v as $t }
+        }
+    };
+}
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        let mut res = self;
+impl_float!(f32);
+impl_float!(f64);
+
+/// Lengths, interpolation and the like, which only make sense for floating point vectors.
+macro_rules! impl_float_vector {
+    ($name:ident) => {
+        impl<T: Float> $name<T> {
+            /// Scales to unit length in place; the zero vector stays zero.
+            pub fn normalize(&mut self) -> &Self {
+                let n = self.length();
+                if n != T::from(0.) {
+                    *self /= n;
+                }
+                self
+            }
+
+            pub fn normalized(mut self) -> Self {
+                self.normalize();
+                self
+            }
+
+            pub fn length(&self) -> T {
+                self.length_squared().sqrt()
+            }
+
+            pub fn distance(&self, rhs: &Self) -> T {
+                (*rhs - *self).length()
+            }
+
+            /// `self` at `t` = 0, `rhs` at `t` = 1.
+            pub fn lerp(self, rhs: Self, t: T) -> Self {
+                self + (rhs - self) * t
+            }
+
+            /// Mirrors a direction on the plane with unit normal `normal`.
+            pub fn reflect(self, normal: Self) -> Self {
+                self - normal * (T::from(2.) * self.dot(&normal))
+            }
+
+            /// Bends the unit direction `self` entering a surface with unit normal `normal`, facing
+            /// against it, where `eta` is the ratio of refractive indices outside / inside.
+            /// `None` on total internal reflection.
+            pub fn refract(self, normal: Self, eta: T) -> Option<Self> {
+                let one = T::from(1.);
+                let cos_in = -self.dot(&normal);
+                let k = one - eta * eta * (one - cos_in * cos_in);
+                if k < T::from(0.) {
+                    return None;
+                }
+                Some(self * eta + normal * (eta * cos_in - k.sqrt()))
+            }
+
+            /// Converts the components, e.g. to f32 once large coordinates have been made relative.
+            pub fn cast<U: Float>(self) -> $name<U> {
+                self.map(|c| U::from_f64(c.to_f64()))
+            }
+
+            /// Whether every component is within `epsilon` of the other vector's.
+            pub fn approx_eq(&self, rhs: &Self, epsilon: T) -> bool {
+                self.as_array().iter().zip(rhs.as_array()).all(|(&a, b)| (a - b).abs() <= epsilon)
+            }
+        }
+    };
+}
 
-        res.x -= rhs.x;
-        res.y -= rhs.y;
-        res.z -= rhs.z;
+impl_float_vector!(Vector2);
+impl_float_vector!(Vector3);
+impl_float_vector!(Vector4);
+
+/// Swizzles returning a smaller or shuffled vector, e.g. `v.xz()`.
+macro_rules! swizzle {
+    ($name:ident => $($fn:ident: $out:ident($($field:ident),+)),+ $(,)?) => {
+        impl<T: Copy> $name<T> {
+            $(
+                pub fn $fn(&self) -> $out<T> {
+                    $out::new($(self.$field),+)
+                }
+            )+
+        }
+    };
+}
 
-        res
+swizzle!(Vector2 => yx: Vector2(y, x));
+swizzle!(Vector3 =>
+    xy: Vector2(x, y), xz: Vector2(x, z), yz: Vector2(y, z),
+    yx: Vector2(y, x), zx: Vector2(z, x), zy: Vector2(z, y),
+    xzy: Vector3(x, z, y), yxz: Vector3(y, x, z), yzx: Vector3(y, z, x),
+    zxy: Vector3(z, x, y), zyx: Vector3(z, y, x),
+);
+swizzle!(Vector4 => xy: Vector2(x, y), xyz: Vector3(x, y, z));
+
+impl<T: Copy> Vector2<T> {
+    pub fn extend(&self, z: T) -> Vector3<T> {
+        Vector3::new(self.x, self.y, z)
     }
 }
 
-impl Sub<f32> for Vector3<f32> {
-    type Output = Self;
-
-    fn sub(self, rhs: f32) -> Self::Output {
-        let mut res = self;
-
-        res.x -= rhs;
-        res.y -= rhs;
-        res.z -= rhs;
-
-        res
+impl<T: Copy> Vector3<T> {
+    pub fn extend(&self, w: T) -> Vector4<T> {
+        Vector4::new(self.x, self.y, self.z, w)
     }
 }
 
-impl Mul<f32> for Vector3<f32> {
-    type Output = Self;
-    fn mul(self, rhs: f32) -> Self::Output {
-        let mut res = self;
-
-        res.x *= rhs;
-        res.y *= rhs;
-        res.z *= rhs;
-
-        res
+impl<T: Copy + Mul<Output = T> + Sub<Output = T>> Vector3<T> {
+    pub fn cross(&self, rhs: &Self) -> Self {
+        Vector3 {
+            x: self.y * rhs.z - self.z * rhs.y,
+            y: self.z * rhs.x - self.x * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x,
+        }
     }
 }
 
-impl Div<f32> for Vector3<f32> {
-    type Output = Self;
-    fn div(self, rhs: f32) -> Self::Output {
-        let mut res = self;
+impl Vector3<f32> {
+    pub fn up() -> Vector3<f32>{ Vector3 { x: 0.0, y: 1.0, z: 0.0 } }
+    pub fn down() -> Vector3<f32>{ Vector3 { x: 0.0, y: -1.0, z: 0.0 } }
+    pub fn right() -> Vector3<f32>{ Vector3 { x: 1.0, y: 0.0, z: 0.0 } }
+    pub fn left() -> Vector3<f32>{ Vector3 { x: -1.0, y: 0.0, z: 0.0 } }
+    pub fn forward() -> Vector3<f32>{ Vector3 { x: 0.0, y: 0.0, z: 1.0 } }
+    pub fn backward() -> Vector3<f32>{ Vector3 { x: 0.0, y: 0.0, z: -1.0 } }
+}
+
+impl<T: Float> Vector4<T> {
+    /// `p` with w = 1, so translations apply to it.
+    pub fn point(p: Vector3<T>) -> Self {
+        p.extend(T::from(1.))
+    }
 
-        res.x /= rhs;
-        res.y /= rhs;
-        res.z /= rhs;
+    /// `d` with w = 0, so translations leave it alone.
+    pub fn direction(d: Vector3<T>) -> Self {
+        d.extend(T::from(0.))
+    }
 
-        res
+    /// Back to 3D by dividing by w, as the rasterizer does with clip coordinates.
+    pub fn project(&self) -> Vector3<T> {
+        self.xyz() / self.w
     }
 }
@@ -0,0 +1,153 @@
+mod common;
+
+use rand::Rng;
+use triangle::buffers::{IndexBuffer, Vertex, VertexBuffer};
+use triangle::bvh::Bvh;
+use triangle::geometry::{Ray, TriangleHit};
+use triangle::mesh::Mesh;
+use triangle::vector::Vector3;
+use common::*;
+
+/// Random triangles in a 20 unit cube.
+fn triangle_soup(count: usize) -> Mesh {
+    deformed_soup(count, |p| p)
+}
+
+/// The same triangles as [`triangle_soup`] with every corner moved by `deform`.
+fn deformed_soup(count: usize, deform: impl Fn(Vector3<f32>) -> Vector3<f32>) -> Mesh {
+    let mut rng = rng();
+    let (mut vb, mut ib) = (VertexBuffer::new(), IndexBuffer::new());
+    for t in 0..count {
+        let center = random_vector(&mut rng, 10.);
+        for c in 0..3 {
+            let p = deform(center + random_vector(&mut rng, 1.));
+            vb.add_vertex(Vertex::new(p.as_array(), [0., 0., 1.], [0., 0.], [1., 1., 1., 1.]));
+            ib.add_index((3 * t + c) as u32);
+        }
+    }
+    Mesh::new(vb, ib)
+}
+
+fn brute_force(mesh: &Mesh, ray: &Ray) -> Option<(usize, TriangleHit)> {
+    (0..mesh.get_triangle_count())
+        .filter_map(|t| ray.intersect_triangle(&mesh.get_triangle(t)).map(|hit| (t, hit)))
+        .min_by(|a, b| a.1.distance.total_cmp(&b.1.distance))
+}
+
+/// Checks closest and any hits against testing every triangle.
+fn assert_matches_brute_force(bvh: &Bvh, mesh: &Mesh) {
+    let mut rng = rng();
+    let mut hits = 0;
+
+    for _ in 0..CASES {
+        let origin = random_vector(&mut rng, 15.);
+        let ray = Ray::new(origin, random_vector(&mut rng, 10.) - origin);
+        let expected = brute_force(mesh, &ray);
+
+        let actual = bvh.closest_hit(&ray);
+        assert_eq!(actual.map(|(t, _)| t), expected.map(|(t, _)| t));
+        if let (Some(a), Some(e)) = (actual, expected) {
+            assert_eq!(a.1, e.1);
+            hits += 1;
+        }
+
+        let max_distance = rng.random_range(0.0..30.);
+        assert_eq!(bvh.any_hit(&ray, max_distance), expected.is_some_and(|(_, e)| e.distance <= max_distance));
+    }
+    assert!(hits > CASES / 4, "too few rays hit anything to be a useful test");
+}
+
+#[test]
+fn queries_match_brute_force() {
+    let mesh = triangle_soup(2000);
+    assert_matches_brute_force(&Bvh::build(&mesh), &mesh);
+}
+
+#[test]
+fn bounds_cover_the_mesh() {
+    let mesh = triangle_soup(100);
+    let bounds = Bvh::build(&mesh).get_bounds();
+    for t in 0..mesh.get_triangle_count() {
+        let triangle = mesh.get_triangle(t);
+        assert!(bounds.contains(triangle.a) && bounds.contains(triangle.b) && bounds.contains(triangle.c));
+    }
+}
+
+#[test]
+fn empty_mesh() {
+    let bvh = Bvh::build(&Mesh::default());
+    assert!(bvh.closest_hit(&Ray::new(Default::default(), Vector3::new(0., 0., 1.))).is_none());
+    assert!(bvh.get_bounds().is_empty());
+}
+
+#[test]
+fn tree_size_is_linear() {
+    let mesh = triangle_soup(5000);
+    let bvh = Bvh::build(&mesh);
+    assert!(bvh.get_node_count() < 2 * mesh.get_triangle_count());
+    assert!(bvh.get_node_count() > 1);
+}
+
+#[test]
+fn coincident_triangles_do_not_split_forever() {
+    let mesh = deformed_soup(100, |_| Vector3::new(1., 2., 3.));
+    let bvh = Bvh::build(&mesh);
+    assert_eq!(bvh.get_node_count(), 1);
+}
+
+#[test]
+fn refit_follows_deformation() {
+    let mesh = triangle_soup(2000);
+    let mut bvh = Bvh::build(&mesh);
+    let twisted = deformed_soup(2000, |p| Vector3::new(p.x * 0.8 + p.z * 0.3, p.y * 1.2, p.z - p.x * 0.3));
+    bvh.refit(&twisted);
+    assert_matches_brute_force(&bvh, &twisted);
+
+    let bounds = bvh.get_bounds();
+    for t in 0..twisted.get_triangle_count() {
+        assert!(bounds.contains(twisted.get_triangle(t).a));
+    }
+}
+
+#[test]
+fn serialization_round_trip() {
+    let mesh = triangle_soup(1000);
+    let bvh = Bvh::build(&mesh);
+    let mut bytes = Vec::new();
+    bvh.write(&mut bytes).unwrap();
+
+    let read = Bvh::read(&mut bytes.as_slice(), &mesh).unwrap();
+    assert_eq!(read.get_node_count(), bvh.get_node_count());
+    assert_eq!(read.get_bounds(), bvh.get_bounds());
+    assert_matches_brute_force(&read, &mesh);
+
+    let path = std::env::temp_dir().join(format!("triangle-bvh-round-trip-{}.bvh", std::process::id()));
+    let path = path.to_str().unwrap();
+    bvh.save(path).unwrap();
+    let loaded = Bvh::load(path, &mesh).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(loaded.get_node_count(), bvh.get_node_count());
+}
+
+#[test]
+fn serialization_rejects_bad_input() {
+    let mesh = triangle_soup(100);
+    let mut bytes = Vec::new();
+    Bvh::build(&mesh).write(&mut bytes).unwrap();
+
+    assert!(Bvh::read(&mut bytes.as_slice(), &triangle_soup(99)).is_err());
+    // as many triangles, somewhere else
+    let moved = deformed_soup(100, |p| Vector3::new(p.x, p.y, p.z + 0.5));
+    assert!(Bvh::read(&mut bytes.as_slice(), &moved).is_err());
+    // the same corners but one, moved towards the middle
+    let nudged = deformed_soup(100, |p| if p == mesh.get_triangle(50).b { p * 0.99 } else { p });
+    assert!(Bvh::read(&mut bytes.as_slice(), &nudged).is_err());
+    assert!(Bvh::read(&mut &bytes[..bytes.len() - 1], &mesh).is_err());
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[0] = b'X';
+    assert!(Bvh::read(&mut wrong_magic.as_slice(), &mesh).is_err());
+    // point the root's first child past the end, after the 48 byte header
+    let mut bad_node = bytes.clone();
+    bad_node[48 + 24..48 + 28].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(Bvh::read(&mut bad_node.as_slice(), &mesh).is_err());
+}
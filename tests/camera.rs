@@ -0,0 +1,197 @@
+mod common;
+
+use triangle::camera::{Camera, Projection};
+use triangle::controller::{FlyController, OrbitController};
+use triangle::matrix::Matrix4;
+use triangle::quaternion::Quaternion;
+use triangle::vector::Vector3;
+use common::{assert_matrix_eq, assert_vector_eq, assert_vector_near};
+
+const WIDTH: f32 = 800.;
+const HEIGHT: f32 = 600.;
+
+fn camera() -> Camera {
+    Camera::new(1., WIDTH / HEIGHT, 0.1, 100.)
+}
+
+/// An orbit around the origin that applies input on the next update.
+fn orbit(distance: f32) -> OrbitController {
+    let mut orbit = OrbitController::new(Vector3::default(), distance);
+    orbit.damping = 0.;
+    orbit
+}
+
+/// Where `camera` sees `point`, in camera space.
+fn in_view(camera: &Camera, point: Vector3<f32>) -> Vector3<f32> {
+    camera.view_matrix().transform_point(point)
+}
+
+#[test]
+fn orbit_starts_behind_the_target_looking_at_it() {
+    let mut camera = camera();
+    let orbit = orbit(5.);
+    orbit.apply(&mut camera);
+    assert_vector_eq(camera.get_position().cast(), Vector3::new(0., 0., 5.));
+    assert_vector_eq(in_view(&camera, Vector3::default()), Vector3::new(0., 0., -5.));
+    assert_eq!(camera.get_focus_distance(), 5.);
+}
+
+#[test]
+fn dragging_turns_the_camera_around_the_target() {
+    let mut camera = camera();
+    let mut orbit = orbit(5.);
+    // a drag to the right turns the scene right, so the camera goes left
+    orbit.rotate((WIDTH / 2., HEIGHT / 2.), (WIDTH / 2. + 100., HEIGHT / 2.), WIDTH, HEIGHT);
+    orbit.update(0.);
+    orbit.apply(&mut camera);
+
+    let position: Vector3<f32> = camera.get_position().cast();
+    assert!(position.x < -1., "{}", position.x);
+    assert!(position.y.abs() < 1e-5);
+    assert!((position.length() - 5.).abs() < 1e-4);
+    // still looking at the target
+    assert_vector_near(in_view(&camera, Vector3::default()), Vector3::new(0., 0., -5.), 1e-4);
+
+    // dragging back undoes it
+    orbit.rotate((WIDTH / 2. + 100., HEIGHT / 2.), (WIDTH / 2., HEIGHT / 2.), WIDTH, HEIGHT);
+    orbit.update(0.);
+    assert_vector_near(orbit.get_position().cast(), Vector3::new(0., 0., 5.), 1e-4);
+}
+
+#[test]
+fn panning_keeps_the_point_under_the_cursor() {
+    let camera = camera();
+    let mut orbit = orbit(5.);
+    // a drag of the whole viewport height moves the view by its height at the target
+    orbit.pan(0., HEIGHT, &camera, HEIGHT);
+    orbit.update(0.);
+    let view_height = 2. * 5. * (camera.get_fov() / 2.).tan();
+    assert_vector_near(orbit.get_target().cast(), Vector3::new(0., view_height, 0.), 1e-4);
+    assert_eq!(orbit.get_distance(), 5.);
+}
+
+#[test]
+fn zooming_dollies_towards_the_target() {
+    let mut orbit = orbit(5.);
+    orbit.zoom(2.);
+    orbit.update(0.);
+    assert!((orbit.get_distance() - 5. * (-0.2f32).exp()).abs() < 1e-5);
+    orbit.zoom(-2.);
+    orbit.update(0.);
+    assert!((orbit.get_distance() - 5.).abs() < 1e-5);
+}
+
+#[test]
+fn damping_eases_motion_in() {
+    let mut damped = OrbitController::new(Vector3::default(), 5.);
+    damped.zoom(2.);
+    damped.update(0.01);
+    let first_step = damped.get_distance();
+    assert!(first_step < 5. && first_step > 5. * (-0.2f32).exp());
+    for _ in 0..200 {
+        damped.update(0.01);
+    }
+    assert!((damped.get_distance() - 5. * (-0.2f32).exp()).abs() < 1e-4);
+}
+
+#[test]
+fn fly_camera_moves_along_where_it_looks() {
+    let mut camera = camera();
+    let mut fly = FlyController::new(Vector3::default(), 2.);
+    // half a second straight ahead
+    fly.update(0.5, Vector3::new(0., 0., -1.), 1.);
+    assert_vector_near(fly.get_position().cast(), Vector3::new(0., 0., -1.), 1e-5);
+
+    // a quarter turn to the left, then ahead again at double speed
+    fly.look(-std::f32::consts::FRAC_PI_2 / fly.look_sensitivity, 0.);
+    fly.update(0.5, Vector3::new(0., 0., -1.), 2.);
+    assert_vector_near(fly.get_position().cast(), Vector3::new(-2., 0., -1.), 1e-5);
+
+    fly.apply(&mut camera);
+    assert_vector_near(camera.get_position().cast(), Vector3::new(-2., 0., -1.), 1e-5);
+    assert_vector_near(in_view(&camera, Vector3::new(-3., 0., -1.)), Vector3::new(0., 0., -1.), 1e-5);
+}
+
+#[test]
+fn fly_camera_pitch_stops_short_of_straight_up() {
+    let mut fly = FlyController::new(Vector3::default(), 1.);
+    fly.look(0., -1e6);
+    let forward = fly.get_rotation().rotate(Vector3::backward());
+    assert!(forward.y > 0.99 && forward.y < 1.);
+
+    // up and down follow the world, not the view
+    fly.update(1., Vector3::new(0., 1., 0.), 1.);
+    assert_vector_near(fly.get_position().cast(), Vector3::new(0., 1., 0.), 1e-5);
+}
+
+#[test]
+fn fly_camera_takes_over_the_orbit_view() {
+    let mut orbit = orbit(5.);
+    orbit.rotate((WIDTH / 2., HEIGHT / 2.), (WIDTH / 2. + 100., HEIGHT / 2. + 50.), WIDTH, HEIGHT);
+    orbit.update(0.);
+    let mut fly = FlyController::new(Vector3::default(), 1.);
+    fly.set_view(orbit.get_position(), orbit.get_rotation());
+
+    let looking = |rotation: Quaternion| rotation.rotate(Vector3::backward());
+    assert_vector_near(looking(fly.get_rotation()), looking(orbit.get_rotation()), 1e-4);
+    assert_vector_near(fly.get_position().cast(), orbit.get_position().cast(), 1e-5);
+}
+
+/// Width and height on screen, in normalized device coordinates, of a box `size` across
+/// standing `distance` in front of `camera`.
+fn projected_extent(camera: &Camera, size: Vector3<f32>, distance: f32) -> (f32, f32) {
+    let view_projection = camera.view_matrix() * camera.get_projection_matrix();
+    let rotation: Quaternion = camera.transform.get_rotation().cast();
+    let position: Vector3<f32> = camera.get_position().cast();
+    let center = position + rotation.rotate(Vector3::backward()) * distance;
+    let (right, up) = (rotation.rotate(Vector3::right()), rotation.rotate(Vector3::up()));
+    let a = view_projection.transform_point(center - right * (size.x / 2.) - up * (size.y / 2.));
+    let b = view_projection.transform_point(center + right * (size.x / 2.) + up * (size.y / 2.));
+    ((b.x - a.x).abs(), (b.y - a.y).abs())
+}
+
+#[test]
+fn switching_projection_keeps_the_size_at_the_focus_distance() {
+    let mut camera = camera();
+    let mut orbit = orbit(7.);
+    orbit.rotate((WIDTH / 2., HEIGHT / 2.), (WIDTH / 2. + 150., HEIGHT / 2. - 80.), WIDTH, HEIGHT);
+    orbit.update(0.);
+    orbit.apply(&mut camera);
+    let size = Vector3::new(1.5, 0.8, 0.);
+
+    let perspective = projected_extent(&camera, size, 7.);
+    camera.toggle_projection();
+    assert_eq!(camera.get_projection(), Projection::Orthographic);
+    let orthographic = projected_extent(&camera, size, 7.);
+    camera.toggle_projection();
+    assert_eq!(camera.get_projection(), Projection::Perspective);
+    let back = projected_extent(&camera, size, 7.);
+
+    for (a, b) in [(perspective, orthographic), (perspective, back)] {
+        assert!((a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4, "{:?} != {:?}", a, b);
+    }
+    assert_eq!(camera.get_focus_distance(), 7.);
+    // nearer things only shrink with distance in perspective
+    assert!(projected_extent(&camera, size, 3.5).0 > 1.9 * perspective.0);
+}
+
+#[test]
+fn viewport_sets_the_aspect_ratio() {
+    let mut camera = camera();
+    camera.set_viewport(1920, 1080);
+    assert_eq!(camera.get_aspect(), 1920. / 1080.);
+    assert_matrix_eq(camera.get_projection_matrix(), Matrix4::project(1., 1920. / 1080., 0.1, 100.));
+    camera.toggle_projection();
+    let height = camera.get_ortho_height();
+    assert_matrix_eq(camera.get_projection_matrix(), Matrix4::orthographic(height, 1920. / 1080., 0.1, 100.));
+}
+
+#[test]
+fn empty_viewports_are_ignored() {
+    let mut camera = camera();
+    let projection = camera.get_projection_matrix();
+    camera.set_viewport(0, 600);
+    camera.set_viewport(800, 0);
+    assert_eq!(camera.get_aspect(), WIDTH / HEIGHT);
+    assert_matrix_eq(camera.get_projection_matrix(), projection);
+}
@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+use clap::Parser;
+use triangle::cli::{parse_color, parse_size, parse_switch, parse_vector, Cli, Command, Format, Size};
+use triangle::color::Color;
+use triangle::display::Shading;
+use triangle::mesh_file::{ConvertOptions, Handedness, ImportOptions, Normals, UpAxis};
+use triangle::model::Model;
+use triangle::repair::RepairOptions;
+use triangle::simplify::{LodOptions, SimplifyOptions};
+use triangle::shader::Light;
+use triangle::vector::Vector3;
+
+fn parse(args: &[&str]) -> Result<Command, clap::Error> {
+    Cli::try_parse_from(["triangle"].iter().chain(args)).map(Cli::into_command)
+}
+
+#[test]
+fn views_without_a_subcommand() {
+    let Ok(Command::View(args)) = parse(&["a.obj", "b.obj", "--no-rotate", "--vsync", "off", "--camera", "-1,2.5,3"]) else {
+        panic!("expected the viewer");
+    };
+    assert_eq!(args.scene.inputs, vec![PathBuf::from("a.obj"), PathBuf::from("b.obj")]);
+    assert!(args.no_rotate);
+    assert!(!args.vsync);
+    assert_eq!(args.scene.camera, Some(Vector3::new(-1., 2.5, 3.)));
+    assert_eq!(args.scene.shader, Shading::Phong);
+    assert_eq!(args.scene.size, Size { width: 600, height: 420 });
+
+    let Ok(Command::View(args)) = parse(&[]) else { panic!("expected the viewer") };
+    assert!(args.scene.inputs.is_empty());
+    assert!(args.vsync);
+}
+
+#[test]
+fn subcommands() {
+    let Ok(Command::Render(args)) = parse(&["render", "scene.ron", "--shader", "normals", "--size", "64x32", "-o", "out.png"]) else {
+        panic!("expected render");
+    };
+    assert_eq!(args.scene.shader, Shading::Normals);
+    assert_eq!(args.scene.size, Size { width: 64, height: 32 });
+    assert_eq!(args.output, PathBuf::from("out.png"));
+
+    assert!(matches!(parse(&["info", "a.obj"]), Ok(Command::Info(args)) if args.format == Format::Text));
+    assert!(matches!(parse(&["info", "--format", "json", "a.obj"]), Ok(Command::Info(args)) if args.format == Format::Json));
+    let Ok(Command::Convert(args)) = parse(&["convert", "a.stl", "b.glb", "--weld", "--normals", "smooth", "--scale", "0.001", "--input-up", "z", "--merge"]) else {
+        panic!("expected convert");
+    };
+    assert_eq!(args.get_options(), ConvertOptions {
+        import: ImportOptions { up_axis: UpAxis::Z, handedness: Handedness::Right, unit_scale: 0.001 },
+        output_up: UpAxis::Y,
+        normals: Some(Normals::Smooth),
+        weld: true,
+        merge: true,
+    });
+    let Ok(Command::Convert(args)) = parse(&["convert", "a.obj", "b.obj"]) else {
+        panic!("expected convert");
+    };
+    assert_eq!(args.get_options(), ConvertOptions::default());
+    assert!(matches!(parse(&["view", "a.obj"]), Ok(Command::View(_))));
+
+    let Ok(Command::Repair(args)) = parse(&["repair", "a.stl"]) else {
+        panic!("expected repair");
+    };
+    assert_eq!(args.output, None);
+    assert_eq!(args.get_options(), RepairOptions::default());
+    let Ok(Command::Repair(args)) = parse(&["repair", "a.stl", "b.stl", "--merge-vertices", "1e-4", "--fill-holes", "8", "--winding"]) else {
+        panic!("expected repair");
+    };
+    assert_eq!(args.output, Some(PathBuf::from("b.stl")));
+    assert_eq!(args.get_options(), RepairOptions {
+        merge_distance: Some(1e-4),
+        degenerate: false,
+        duplicates: false,
+        winding: true,
+        fill_holes: Some(8),
+        unreferenced: false,
+    });
+
+    let Ok(Command::Simplify(args)) = parse(&["simplify", "scan.ply", "scan.glb"]) else {
+        panic!("expected simplify");
+    };
+    assert_eq!(args.get_options(1000), SimplifyOptions { target_triangles: Some(500), max_error: None });
+    let Ok(Command::Simplify(args)) = parse(&["simplify", "scan.ply", "scan.glb", "--ratio", "0.1", "--error", "0.01"]) else {
+        panic!("expected simplify");
+    };
+    assert_eq!(args.get_options(1000), SimplifyOptions { target_triangles: Some(100), max_error: Some(0.01) });
+    let Ok(Command::Simplify(args)) = parse(&["simplify", "scan.ply", "scan.glb", "--error", "0.01"]) else {
+        panic!("expected simplify");
+    };
+    assert_eq!(args.get_options(1000), SimplifyOptions { target_triangles: None, max_error: Some(0.01) });
+
+    let Ok(Command::Render(args)) = parse(&["render", "scan.ply", "--lods", "2"]) else {
+        panic!("expected render");
+    };
+    assert_eq!(args.scene.get_lod_options(), Some(LodOptions { levels: 2, ..LodOptions::default() }));
+    let Ok(Command::View(args)) = parse(&["scan.ply"]) else { panic!("expected the viewer") };
+    assert_eq!(args.scene.get_lod_options(), None);
+}
+
+#[test]
+fn rejects_invalid_arguments() {
+    for args in [
+        &["--shader", "toon"][..],
+        &["--size", "640"],
+        &["--size", "0x480"],
+        &["--camera", "1,2"],
+        &["--vsync", "maybe"],
+        &["--background", "2,0,0"],
+        &["info"],
+        &["info", "--format", "xml", "a.obj"],
+        &["convert", "a.obj"],
+        &["convert", "a.obj", "b.obj", "--scale", "-1"],
+        &["convert", "a.obj", "b.obj", "--input-up", "x"],
+        &["convert", "a.obj", "b.obj", "--handedness", "both"],
+        &["--unit-scale", "0"],
+        &["render", "--no-rotate"],
+        &["repair"],
+        &["repair", "a.obj", "--merge-vertices", "0"],
+        &["repair", "a.obj", "--fill-holes", "-3"],
+        &["simplify", "a.obj"],
+        &["simplify", "a.obj", "b.obj", "--ratio", "2"],
+        &["simplify", "a.obj", "b.obj", "--ratio", "0.5", "--triangles", "10"],
+        &["--lods", "many"],
+    ] {
+        assert!(parse(args).is_err(), "{:?} should not parse", args);
+    }
+}
+
+#[test]
+fn value_parsers() {
+    assert_eq!(parse_size("1280X720"), Ok(Size { width: 1280, height: 720 }));
+    assert!(parse_size("-1x2").is_err());
+    assert_eq!(parse_vector::<f32>(" 1, -2 ,3e1"), Ok(Vector3::new(1., -2., 30.)));
+    assert!(parse_vector::<f32>("1,2,3,4").is_err());
+    assert!(parse_vector::<f32>("1,x,3").is_err());
+    assert_eq!(parse_color("0,0.5,1"), Ok(Color::new(0., 0.5, 1., 1.)));
+    assert_eq!(parse_color("0,0,0,0.5"), Ok(Color::new(0., 0., 0., 0.5)));
+    assert_eq!(parse_color("#ff0000"), Ok(Color::new(1., 0., 0., 1.)));
+    assert!(parse_color("#ff00").is_err());
+    assert!(parse_color("1,1").is_err());
+    assert_eq!(parse_switch("on"), Ok(true));
+    assert_eq!(parse_switch("off"), Ok(false));
+    assert!(parse_switch("yes").is_err());
+}
+
+#[test]
+fn gathers_inputs_into_one_scene() {
+    let Ok(Command::View(args)) = parse(&["assets/scene.ron", "assets/cube.obj", "--background", "#000000", "--light", "0,-1,0"]) else {
+        panic!("expected the viewer");
+    };
+    let (file, save_path) = args.scene.load().unwrap();
+    assert_eq!(save_path, PathBuf::from("assets/scene.ron"));
+    assert_eq!(file.background, Color::new(0., 0., 0., 1.));
+    assert_eq!(file.get_light(), Light::Directional { direction: Vector3::new(0., -1., 0.) });
+    // the scene's own camera is kept and its model paths now start from the working directory
+    assert_eq!(file.camera.position, Vector3::new(0., 2., 8.));
+    assert_eq!(file.nodes.len(), 2);
+    assert_eq!(file.nodes[0].children[0].model.as_deref(), Some("assets/cube.obj"));
+    assert_eq!(file.nodes[1].name, "cube");
+
+    let scene = file.instantiate("").unwrap();
+    assert_eq!(scene.get_models().len(), 3);
+
+    let Ok(Command::View(args)) = parse(&[]) else { panic!("expected the viewer") };
+    let (file, save_path) = args.scene.load().unwrap();
+    assert_eq!(save_path, PathBuf::from("scene.ron"));
+    assert_eq!(file.nodes[0].model.as_deref(), Some("assets/cube.obj"));
+}
+
+#[test]
+fn scenes_defining_the_same_material_are_refused() {
+    let Ok(Command::View(args)) = parse(&["assets/scene.ron", "assets/scene.ron"]) else {
+        panic!("expected the viewer");
+    };
+    let error = args.scene.load().err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("blue"));
+}
+
+#[test]
+fn import_options_apply_to_models_given_directly() {
+    let Ok(Command::View(args)) = parse(&["assets/scene.ron", "assets/cube.obj", "--up", "z", "--handedness", "left", "--unit-scale", "0.01"]) else {
+        panic!("expected the viewer");
+    };
+    let (file, _) = args.scene.load().unwrap();
+    assert!(file.nodes[0].children[0].import.is_default());
+    assert_eq!(file.nodes[1].import, ImportOptions { up_axis: UpAxis::Z, handedness: Handedness::Left, unit_scale: 0.01 });
+}
+
+#[test]
+fn converts_to_obj() {
+    let mut model = Model::default();
+    model.load_obj("assets/cube.obj");
+    let path = std::env::temp_dir().join(format!("triangle-convert-{}.obj", std::process::id()));
+    model.save_obj(&path.to_string_lossy()).unwrap();
+
+    let mut copy = Model::default();
+    copy.load_obj(&path.to_string_lossy());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(copy.get_vertices(), model.get_vertices());
+    assert_eq!(copy.get_indices(), model.get_indices());
+}
@@ -0,0 +1,99 @@
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use triangle::matrix::Matrix4;
+use triangle::mesh::Mesh;
+use triangle::mesh_file::load_mesh;
+use triangle::quaternion::Quaternion;
+use triangle::vector::Vector3;
+
+pub const EPSILON: f32 = 1e-5;
+
+/// How many random cases each property test checks.
+pub const CASES: usize = 500;
+
+/// Seeded, so failures reproduce.
+pub fn rng() -> StdRng {
+    StdRng::seed_from_u64(0x7269616e676c65)
+}
+
+/// A path in the temporary directory that no other test, in this run or a concurrent one, uses.
+pub fn temp_path(extension: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let file = format!("triangle-test-{}-{}.{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed), extension);
+    std::env::temp_dir().join(file)
+}
+
+/// Reads `obj` as the text of an OBJ file, through a temporary file no other test uses.
+pub fn load_obj(obj: &str) -> std::io::Result<Mesh> {
+    let path = temp_path("obj");
+    std::fs::write(&path, obj).unwrap();
+    let mesh = load_mesh(&path);
+    std::fs::remove_file(&path).unwrap();
+    mesh
+}
+
+pub fn from_rows(rows: [[f32; 4]; 4]) -> Matrix4 {
+    let mut m = Matrix4::new();
+    for (r, row) in rows.iter().enumerate() {
+        for (c, value) in row.iter().enumerate() {
+            m[(r, c)] = *value;
+        }
+    }
+    m
+}
+
+pub fn random_vector(rng: &mut StdRng, range: f32) -> Vector3<f32> {
+    Vector3::new(rng.random_range(-range..range), rng.random_range(-range..range), rng.random_range(-range..range))
+}
+
+pub fn random_rotation(rng: &mut StdRng) -> Quaternion {
+    let mut axis = random_vector(rng, 1.);
+    while axis.length_squared() < 1e-3 {
+        axis = random_vector(rng, 1.);
+    }
+    Quaternion::from_angle_axis(rng.random_range(-std::f32::consts::PI..std::f32::consts::PI), axis)
+}
+
+pub fn random_scale(rng: &mut StdRng) -> Vector3<f32> {
+    Vector3::new(rng.random_range(0.2..3.), rng.random_range(0.2..3.), rng.random_range(0.2..3.))
+}
+
+pub fn random_matrix(rng: &mut StdRng) -> Matrix4 {
+    let mut m = Matrix4::new();
+    for r in 0..4 {
+        for c in 0..4 {
+            m[(r, c)] = rng.random_range(-2.0..2.0);
+        }
+    }
+    m
+}
+
+pub fn assert_matrix_near(actual: Matrix4, expected: Matrix4, tolerance: f32) {
+    for r in 0..4 {
+        for c in 0..4 {
+            assert!(
+                (actual[(r, c)] - expected[(r, c)]).abs() < tolerance,
+                "element ({}, {}): {} != {}", r, c, actual[(r, c)], expected[(r, c)]
+            );
+        }
+    }
+}
+
+pub fn assert_matrix_eq(actual: Matrix4, expected: Matrix4) {
+    assert_matrix_near(actual, expected, EPSILON);
+}
+
+pub fn assert_vector_near(actual: Vector3<f32>, expected: Vector3<f32>, tolerance: f32) {
+    assert!(
+        (actual - expected).length() < tolerance,
+        "({}, {}, {}) != ({}, {}, {})", actual.x, actual.y, actual.z, expected.x, expected.y, expected.z
+    );
+}
+
+pub fn assert_vector_eq(actual: Vector3<f32>, expected: Vector3<f32>) {
+    assert_vector_near(actual, expected, EPSILON);
+}
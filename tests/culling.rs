@@ -0,0 +1,91 @@
+mod common;
+
+use triangle::camera::Camera;
+use triangle::culling::{cull, CullStats};
+use triangle::geometry::Aabb;
+use triangle::matrix::Matrix4;
+use triangle::model::Model;
+use triangle::quaternion::{DQuaternion, Quaternion};
+use triangle::vector::Vector3;
+use common::*;
+
+fn cube_at(position: Vector3<f64>) -> Model {
+    let mut model = Model::default();
+    model.load_obj("assets/cube.obj");
+    model.transform.set_position(position);
+    model
+}
+
+fn camera_at(position: Vector3<f64>) -> Camera {
+    let mut camera = Camera::new(1., 1., 0.1, 100.);
+    camera.transform.set_position(position);
+    camera
+}
+
+#[test]
+fn transformed_box_contains_transformed_corners() {
+    let mut rng = rng();
+    let aabb = Aabb::new(Vector3::new(-1., -2., 0.), Vector3::new(3., 1., 2.));
+    for _ in 0..CASES {
+        let m = Matrix4::scale(random_vector(&mut rng, 3.)) * random_rotation(&mut rng).as_mat4() * Matrix4::translate(random_vector(&mut rng, 10.));
+        let transformed = aabb.transformed(&m);
+        for corner in 0..8 {
+            let p = Vector3::new(
+                if corner & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if corner & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if corner & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            );
+            let q = m.transform_point(p);
+            // inside, up to rounding
+            assert!(q.approx_eq(&q.clamp(transformed.min, transformed.max), 1e-4));
+        }
+    }
+}
+
+#[test]
+fn transformed_box_is_tight_for_quarter_turns() {
+    let aabb = Aabb::new(Vector3::new(-1., -2., -3.), Vector3::new(1., 2., 3.));
+    let m = Quaternion::from_angle_axis(std::f32::consts::FRAC_PI_2, Vector3::up()).as_mat4() * Matrix4::translate(Vector3::new(5., 0., 0.));
+    let transformed = aabb.transformed(&m);
+    assert_vector_near(transformed.min, Vector3::new(2., -2., -1.), EPSILON);
+    assert_vector_near(transformed.max, Vector3::new(8., 2., 1.), EPSILON);
+    assert!(Aabb::default().transformed(&m).is_empty());
+}
+
+#[test]
+fn culls_models_outside_the_view() {
+    let models = vec![
+        cube_at(Vector3::new(0., 0., 0.)),
+        // behind the camera
+        cube_at(Vector3::new(0., 0., 20.)),
+        // far off to the side
+        cube_at(Vector3::new(50., 0., 0.)),
+        // beyond the far plane
+        cube_at(Vector3::new(0., 0., -200.)),
+        // partly in view
+        cube_at(Vector3::new(3.2, 0., 0.)),
+        Model::default(),
+    ];
+    let camera = camera_at(Vector3::new(0., 0., 5.));
+    let (visible, stats) = cull(&models, &camera);
+    assert_eq!(visible, vec![0, 4]);
+    assert_eq!(stats, CullStats { drawn: 2, culled: 4 });
+    assert_eq!(stats.to_string(), "2 drawn, 4 culled");
+}
+
+#[test]
+fn culling_follows_the_camera() {
+    let models = vec![cube_at(Vector3::new(0., 0., 0.)), cube_at(Vector3::new(0., 0., 20.))];
+    let mut camera = camera_at(Vector3::new(0., 0., 10.));
+    assert_eq!(cull(&models, &camera).0, vec![0]);
+    camera.transform.set_rotation(DQuaternion::from_angle_axis(std::f64::consts::PI, Vector3::new(0., 1., 0.)));
+    assert_eq!(cull(&models, &camera).0, vec![1]);
+}
+
+#[test]
+fn culling_far_from_the_origin() {
+    let site = Vector3::new(500_000.123, 5_400_000.456, 250.789);
+    let models = vec![cube_at(site), cube_at(site + Vector3::new(0., 0., 10.))];
+    let camera = camera_at(site + Vector3::new(0., 0., 5.));
+    assert_eq!(cull(&models, &camera).0, vec![0]);
+}
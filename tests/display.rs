@@ -0,0 +1,25 @@
+use triangle::display::{CullMode, DisplayMode};
+
+#[test]
+fn display_modes_cycle_through_all_and_wrap() {
+    let mut mode = DisplayMode::default();
+    assert_eq!(mode, DisplayMode::ALL[0]);
+    for expected in DisplayMode::ALL.iter().skip(1) {
+        mode = mode.next();
+        assert_eq!(mode, *expected);
+    }
+    assert_eq!(mode.next(), DisplayMode::Solid);
+}
+
+#[test]
+fn cull_modes_cycle_through_all_and_wrap() {
+    let mut mode = CullMode::default();
+    let mut seen = vec![mode];
+    for _ in 0..2 {
+        mode = mode.next();
+        assert!(!seen.contains(&mode), "{:?} came round twice", mode);
+        seen.push(mode);
+    }
+    assert_eq!(seen, vec![CullMode::Back, CullMode::Front, CullMode::None]);
+    assert_eq!(mode.next(), CullMode::Back);
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use common::*;
+use rand::Rng;
+use triangle::geometry::{Aabb, Frustum, Plane, Ray, Sphere, Triangle};
+use triangle::matrix::Matrix4;
+use triangle::vector::Vector3;
+
+fn unit_triangle() -> Triangle {
+    Triangle::new(Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.), Vector3::new(0., 1., 0.))
+}
+
+/// A camera at the origin looking down -z with a 90 degree field of view.
+fn frustum() -> Frustum {
+    Frustum::from_matrix(Matrix4::identity() * Matrix4::project(std::f32::consts::FRAC_PI_2, 1., 0.1, 100.))
+}
+
+#[test]
+fn ray_triangle_hit() {
+    let ray = Ray::new(Vector3::new(0.25, 0.5, 2.), Vector3::new(0., 0., -1.));
+    let hit = ray.intersect_triangle(&unit_triangle()).unwrap();
+    assert!((hit.distance - 2.).abs() < EPSILON);
+    assert_vector_near(hit.barycentric, Vector3::new(0.25, 0.25, 0.5), EPSILON);
+    assert_vector_near(hit.normal, Vector3::new(0., 0., 1.), EPSILON);
+
+    // the back side is hit too, with the same winding normal
+    let ray = Ray::new(Vector3::new(0.25, 0.5, -2.), Vector3::new(0., 0., 1.));
+    let hit = ray.intersect_triangle(&unit_triangle()).unwrap();
+    assert!((hit.distance - 2.).abs() < EPSILON);
+    assert_vector_near(hit.normal, Vector3::new(0., 0., 1.), EPSILON);
+}
+
+#[test]
+fn ray_triangle_miss() {
+    let triangle = unit_triangle();
+    let down = Vector3::new(0., 0., -1.);
+    assert!(Ray::new(Vector3::new(0.6, 0.6, 1.), down).intersect_triangle(&triangle).is_none());
+    assert!(Ray::new(Vector3::new(-0.1, 0.5, 1.), down).intersect_triangle(&triangle).is_none());
+    // behind the origin
+    assert!(Ray::new(Vector3::new(0.2, 0.2, -1.), down).intersect_triangle(&triangle).is_none());
+    // parallel to the plane
+    assert!(Ray::new(Vector3::new(-1., 0.2, 0.), Vector3::new(1., 0., 0.)).intersect_triangle(&triangle).is_none());
+    // degenerate
+    let line = Triangle::new(Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.), Vector3::new(2., 0., 0.));
+    assert!(Ray::new(Vector3::new(0.5, 0., 1.), down).intersect_triangle(&line).is_none());
+}
+
+#[test]
+fn ray_triangle_recovers_barycentrics() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let triangle = Triangle::new(random_vector(&mut rng, 5.), random_vector(&mut rng, 5.), random_vector(&mut rng, 5.));
+        if triangle.area() < 0.5 {
+            continue;
+        }
+        let (u, v) = (rng.random_range(0.05..0.9f32), rng.random_range(0.05..0.9f32));
+        if u + v > 0.95 {
+            continue;
+        }
+        let weights = Vector3::new(1. - u - v, u, v);
+        let target = triangle.point_at(weights);
+        let origin = target + triangle.normal() * 3. + random_vector(&mut rng, 1.);
+        let hit = Ray::new(origin, target - origin).intersect_triangle(&triangle).unwrap();
+
+        assert!((hit.distance - origin.distance(&target)).abs() < 1e-3);
+        assert!(hit.barycentric.approx_eq(&weights, 1e-3));
+        assert!(Ray::new(origin, target - origin).at(hit.distance).approx_eq(&target, 1e-3));
+    }
+}
+
+#[test]
+fn ray_aabb() {
+    let aabb = Aabb::new(Vector3::new(-1., -1., -1.), Vector3::new(1., 1., 1.));
+
+    let hit = Ray::new(Vector3::new(0.5, 0., 5.), Vector3::new(0., 0., -1.)).intersect_aabb(&aabb).unwrap();
+    assert!((hit.distance - 4.).abs() < EPSILON);
+    assert_vector_eq(hit.normal, Vector3::new(0., 0., 1.));
+
+    let hit = Ray::new(Vector3::new(-3., 0.5, 0.5), Vector3::new(1., 0., 0.)).intersect_aabb(&aabb).unwrap();
+    assert!((hit.distance - 2.).abs() < EPSILON);
+    assert_vector_eq(hit.normal, Vector3::new(-1., 0., 0.));
+
+    // from inside, the exit
+    let hit = Ray::new(Vector3::default(), Vector3::new(0., 1., 0.)).intersect_aabb(&aabb).unwrap();
+    assert!((hit.distance - 1.).abs() < EPSILON);
+    assert_vector_eq(hit.normal, Vector3::new(0., 1., 0.));
+
+    assert!(Ray::new(Vector3::new(2., 0., 5.), Vector3::new(0., 0., -1.)).intersect_aabb(&aabb).is_none());
+    assert!(Ray::new(Vector3::new(0., 0., 5.), Vector3::new(0., 0., 1.)).intersect_aabb(&aabb).is_none());
+    assert!(Ray::new(Vector3::new(0., 3., 5.), Vector3::new(0., -0.1, -1.)).intersect_aabb(&aabb).is_none());
+}
+
+#[test]
+fn ray_sphere() {
+    let sphere = Sphere::new(Vector3::new(0., 0., -5.), 1.);
+
+    let hit = Ray::new(Vector3::default(), Vector3::new(0., 0., -1.)).intersect_sphere(&sphere).unwrap();
+    assert!((hit.distance - 4.).abs() < EPSILON);
+    assert_vector_near(hit.normal, Vector3::new(0., 0., 1.), EPSILON);
+
+    let hit = Ray::new(Vector3::new(0., 0., -5.), Vector3::new(1., 0., 0.)).intersect_sphere(&sphere).unwrap();
+    assert!((hit.distance - 1.).abs() < EPSILON);
+    assert_vector_near(hit.normal, Vector3::new(1., 0., 0.), EPSILON);
+
+    assert!(Ray::new(Vector3::default(), Vector3::new(0., 0., 1.)).intersect_sphere(&sphere).is_none());
+    assert!(Ray::new(Vector3::new(0., 1.5, 0.), Vector3::new(0., 0., -1.)).intersect_sphere(&sphere).is_none());
+}
+
+#[test]
+fn ray_plane() {
+    let plane = Plane::from_point_normal(Vector3::new(0., 2., 0.), Vector3::new(0., 3., 0.));
+    assert_vector_eq(plane.normal, Vector3::new(0., 1., 0.));
+    assert!((plane.signed_distance(Vector3::new(7., 5., 1.)) - 3.).abs() < EPSILON);
+
+    let hit = Ray::new(Vector3::default(), Vector3::new(0., 1., 1.)).intersect_plane(&plane).unwrap();
+    assert!((hit.distance - 8f32.sqrt()).abs() < EPSILON);
+    assert!(Ray::new(Vector3::default(), Vector3::new(1., 0., 0.)).intersect_plane(&plane).is_none());
+    assert!(Ray::new(Vector3::default(), Vector3::new(0., -1., 0.)).intersect_plane(&plane).is_none());
+
+    let through = Plane::from_points(Vector3::new(0., 0., 1.), Vector3::new(1., 0., 1.), Vector3::new(0., 1., 1.));
+    assert_vector_near(through.normal, Vector3::new(0., 0., 1.), EPSILON);
+    assert!((through.distance + 1.).abs() < EPSILON);
+}
+
+#[test]
+fn aabb_from_points() {
+    assert!(Aabb::default().is_empty());
+    let aabb = Aabb::from_points([Vector3::new(1., -2., 3.), Vector3::new(-1., 4., 0.), Vector3::new(0., 0., 5.)]);
+    assert_vector_eq(aabb.min, Vector3::new(-1., -2., 0.));
+    assert_vector_eq(aabb.max, Vector3::new(1., 4., 5.));
+    assert_vector_eq(aabb.center(), Vector3::new(0., 1., 2.5));
+    assert!(aabb.contains(Vector3::new(0., 0., 0.)));
+    assert!(!aabb.contains(Vector3::new(0., 5., 0.)));
+    assert_eq!(aabb.union(Aabb::default()), aabb);
+}
+
+#[test]
+fn frustum_planes() {
+    let frustum = frustum();
+    assert!(frustum.contains_point(Vector3::new(0., 0., -1.)));
+    assert!(frustum.contains_point(Vector3::new(4.9, -4.9, -5.)));
+    assert!(!frustum.contains_point(Vector3::new(5.1, 0., -5.)));
+    assert!(!frustum.contains_point(Vector3::new(0., 0., 1.)));
+    assert!(!frustum.contains_point(Vector3::new(0., 0., -0.05)));
+    assert!(!frustum.contains_point(Vector3::new(0., 0., -101.)));
+    for plane in frustum.planes {
+        assert!((plane.normal.length() - 1.).abs() < EPSILON);
+    }
+}
+
+#[test]
+fn frustum_follows_the_view() {
+    // a camera at (10, 0, 0), turned a quarter around y
+    let view = Matrix4::translate(Vector3::new(-10., 0., 0.)) * Matrix4::rotate_euler_y(-std::f32::consts::FRAC_PI_2);
+    let eye = Matrix4::rotate_euler_y(std::f32::consts::FRAC_PI_2) * Matrix4::translate(Vector3::new(10., 0., 0.));
+    let frustum = Frustum::from_matrix(view * Matrix4::project(1., 1., 0.1, 100.));
+    let ahead = eye.transform_point(Vector3::new(0., 0., -5.));
+    let behind = eye.transform_point(Vector3::new(0., 0., 5.));
+    assert!(frustum.contains_point(ahead));
+    assert!(!frustum.contains_point(behind));
+}
+
+#[test]
+fn frustum_culls_aabbs_and_spheres() {
+    let frustum = frustum();
+    let at = |center: Vector3<f32>| Aabb::new(center - Vector3::splat(0.5), center + Vector3::splat(0.5));
+
+    assert!(frustum.intersects_aabb(&at(Vector3::new(0., 0., -10.))));
+    // straddling the right plane
+    assert!(frustum.intersects_aabb(&at(Vector3::new(10.3, 0., -10.))));
+    // surrounding the camera
+    assert!(frustum.intersects_aabb(&Aabb::new(Vector3::splat(-1000.), Vector3::splat(1000.))));
+    assert!(!frustum.intersects_aabb(&at(Vector3::new(0., 0., 10.))));
+    assert!(!frustum.intersects_aabb(&at(Vector3::new(12., 0., -10.))));
+    assert!(!frustum.intersects_aabb(&at(Vector3::new(0., 0., -200.))));
+
+    assert!(frustum.intersects_sphere(&Sphere::new(Vector3::new(0., 0., -10.), 1.)));
+    assert!(frustum.intersects_sphere(&Sphere::new(Vector3::new(10.5, 0., -10.), 1.)));
+    assert!(!frustum.intersects_sphere(&Sphere::new(Vector3::new(12., 0., -10.), 1.)));
+    assert!(!frustum.intersects_sphere(&Sphere::new(Vector3::new(0., 0., 5.), 1.)));
+}
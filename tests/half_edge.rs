@@ -0,0 +1,165 @@
+mod common;
+
+use triangle::half_edge::HalfEdgeMesh;
+use triangle::inspect::MeshReport;
+use triangle::mesh::Mesh;
+use triangle::mesh_file::load_mesh;
+use triangle::vector::Vector3;
+use common::load_obj;
+
+fn cube() -> Mesh {
+    load_mesh("assets/cube.obj").unwrap()
+}
+
+/// Every live half-edge agrees with its twin, its next and its prev.
+fn assert_consistent(mesh: &HalfEdgeMesh) {
+    for h in mesh.get_half_edges() {
+        let t = mesh.get_twin(h);
+        assert_eq!(mesh.get_twin(t), h);
+        assert_eq!(mesh.get_target(h), mesh.get_origin(t));
+        assert_eq!(mesh.get_prev(mesh.get_next(h)), h);
+        assert_eq!(mesh.get_origin(mesh.get_next(h)), mesh.get_target(h));
+        assert_eq!(mesh.get_face(mesh.get_next(h)), mesh.get_face(h));
+    }
+    for v in mesh.get_vertices() {
+        assert!(mesh.get_outgoing(v).iter().all(|&h| mesh.get_origin(h) == v));
+    }
+}
+
+const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvn 0 0 1\nf 1/1/1 2/2/1 3/3/1\nf 1/1/1 3/3/1 4/4/1\n";
+
+#[test]
+fn joins_the_cube_into_a_closed_surface() {
+    let mesh = HalfEdgeMesh::new(&cube());
+    assert_consistent(&mesh);
+    assert_eq!((mesh.get_vertex_count(), mesh.get_edge_count(), mesh.get_face_count()), (8, 18, 12));
+    assert!(mesh.get_boundary_loops().is_empty());
+    for v in mesh.get_vertices() {
+        assert!(!mesh.is_boundary_vertex(v));
+        let neighbours = mesh.get_vertex_neighbours(v);
+        assert_eq!(neighbours.len(), mesh.get_vertex_faces(v).len());
+        assert!(neighbours.iter().all(|&n| mesh.find_half_edge(v, n).is_some() && mesh.find_half_edge(n, v).is_some()));
+    }
+    for f in mesh.get_faces() {
+        assert_eq!(mesh.get_face_neighbours(f).len(), 3);
+    }
+}
+
+#[test]
+fn converts_back_unchanged() {
+    let cube = cube();
+    let (vb, ib) = HalfEdgeMesh::new(&cube).to_buffers();
+    assert_eq!(ib.get_indices(), cube.ib.get_indices());
+    assert_eq!(vb.get_vertices(), cube.vb.get_vertices());
+
+    let groups = load_obj(&format!("{}g a\nf 1 2 3\ng b\nf 1 3 4\n", "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n")).unwrap();
+    let back = HalfEdgeMesh::new(&groups).to_mesh();
+    assert_eq!(back.submeshes, groups.submeshes);
+}
+
+#[test]
+fn finds_boundary_loops() {
+    let square = HalfEdgeMesh::new(&load_obj(SQUARE).unwrap());
+    assert_consistent(&square);
+    let loops = square.get_boundary_loops();
+    assert_eq!(loops.len(), 1);
+    assert_eq!(loops[0].len(), 4);
+    // against the winding of the faces, which go 0 1 2 3 anticlockwise
+    let at = |i: usize| square.get_position(loops[0][i]);
+    assert!((at(1) - at(0)).cross(&(at(2) - at(1))).z < 0.);
+
+    let two = load_obj(&format!("{}v 5 0 0\nv 6 0 0\nv 6 1 0\nf 5 6 7\n", SQUARE)).unwrap();
+    assert_eq!(HalfEdgeMesh::new(&two).get_boundary_loops().len(), 2);
+}
+
+#[test]
+fn flips_edges() {
+    let mut square = HalfEdgeMesh::new(&load_obj(SQUARE).unwrap());
+    let diagonal = square.get_half_edges().find(|&h| !square.is_boundary_edge(h)).unwrap();
+    let (a, b) = (square.get_origin(diagonal), square.get_target(diagonal));
+    assert!(square.flip_edge(diagonal));
+    assert_consistent(&square);
+    assert!(square.find_half_edge(a, b).is_none());
+    let mesh = square.to_mesh();
+    let report = MeshReport::new(&mesh);
+    assert_eq!((report.triangles, report.boundary_edges, report.degenerate_triangles), (2, 4, 0));
+    // still facing +z, and the corners keep their texture coordinates
+    for t in 0..2 {
+        assert!(mesh.get_triangle(t).normal().z > 0.);
+    }
+    for i in 0..mesh.vb.get_vertex_count() {
+        let vertex = mesh.vb.get_vertex(i);
+        let p = vertex.get_position();
+        assert_eq!(vertex.get_uv(), [p.x, p.y]);
+    }
+
+    let boundary = square.get_half_edges().find(|&h| square.is_boundary(h)).unwrap();
+    assert!(!square.flip_edge(boundary));
+}
+
+#[test]
+fn splits_edges() {
+    let mut square = HalfEdgeMesh::new(&load_obj(SQUARE).unwrap());
+    let diagonal = square.get_half_edges().find(|&h| !square.is_boundary_edge(h)).unwrap();
+    let m = square.split_edge(diagonal);
+    assert_consistent(&square);
+    assert_eq!(square.get_position(m), Vector3::new(0.5, 0.5, 0.));
+    assert_eq!(square.get_vertex_neighbours(m).len(), 4);
+    assert_eq!(square.get_face_count(), 4);
+
+    let side = square.get_half_edges().find(|&h| square.is_boundary(h)).unwrap();
+    let n = square.split_edge(side);
+    assert_consistent(&square);
+    assert!(square.is_boundary_vertex(n));
+    assert_eq!(square.get_boundary_loops()[0].len(), 5);
+
+    let mesh = square.to_mesh();
+    assert_eq!(mesh.get_triangle_count(), 5);
+    // the six corners loaded, plus the middle, which both halves share, and the new side vertex
+    assert_eq!(mesh.vb.get_vertex_count(), 8);
+    for i in 0..mesh.vb.get_vertex_count() {
+        let vertex = mesh.vb.get_vertex(i);
+        let p = vertex.get_position();
+        assert_eq!(vertex.get_uv(), [p.x, p.y]);
+        assert_eq!(vertex.get_normal(), Vector3::new(0., 0., 1.));
+    }
+    assert!(!MeshReport::new(&mesh).is_watertight());
+}
+
+#[test]
+fn collapses_edges_keeping_the_surface_closed() {
+    let mut cube = HalfEdgeMesh::new(&cube());
+    let h = cube.get_half_edges().next().unwrap();
+    let (u, v) = (cube.get_origin(h), cube.get_target(h));
+    assert!(cube.collapse_edge(h));
+    assert_consistent(&cube);
+    assert_eq!((cube.get_vertex_count(), cube.get_edge_count(), cube.get_face_count()), (7, 15, 10));
+    assert!(!cube.get_vertices().any(|w| w == u));
+    assert!(cube.get_vertices().any(|w| w == v));
+    let report = MeshReport::new(&cube.to_mesh());
+    assert!(report.is_watertight());
+
+    // a tetrahedron has nothing left to collapse
+    let tetrahedron = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 3 2\nf 1 2 4\nf 2 3 4\nf 3 1 4\n";
+    let mut tetrahedron = HalfEdgeMesh::new(&load_obj(tetrahedron).unwrap());
+    assert!(tetrahedron.get_half_edges().collect::<Vec<_>>().into_iter().all(|h| !tetrahedron.collapse_edge(h)));
+    assert_eq!(tetrahedron.get_face_count(), 4);
+
+    let mut triangle = HalfEdgeMesh::new(&load_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap());
+    assert!(!triangle.collapse_edge(0));
+}
+
+#[test]
+fn collapses_boundary_edges() {
+    let mut square = HalfEdgeMesh::new(&load_obj(SQUARE).unwrap());
+    let side = square.get_half_edges().find(|&h| square.is_boundary(h)).unwrap();
+    let m = square.split_edge(side);
+    let h = square.get_outgoing(m).into_iter().find(|&h| square.is_boundary(h)).unwrap();
+    assert!(square.collapse_edge(h));
+    assert_consistent(&square);
+    assert_eq!((square.get_vertex_count(), square.get_face_count()), (4, 2));
+    assert_eq!(square.get_boundary_loops()[0].len(), 4);
+    // the interior edge from both ends of a boundary would pinch the square
+    let diagonal = square.get_half_edges().find(|&h| !square.is_boundary_edge(h)).unwrap();
+    assert!(!square.collapse_edge(diagonal));
+}
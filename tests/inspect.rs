@@ -0,0 +1,94 @@
+mod common;
+
+use std::io::ErrorKind;
+use triangle::inspect::{MeshReport, Part};
+use triangle::model::Model;
+use triangle::vector::Vector3;
+use common::load_obj;
+
+fn inspect(obj: &str) -> MeshReport {
+    MeshReport::new(&load_obj(obj).unwrap())
+}
+
+const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+#[test]
+fn the_cube_is_watertight() {
+    let mut model = Model::default();
+    model.load_obj("assets/cube.obj");
+    let report = MeshReport::new(model.get_mesh());
+
+    assert_eq!(report.vertices, 36);
+    assert_eq!(report.indices, 36);
+    assert_eq!(report.triangles, 12);
+    assert_eq!(report.unique_positions, 8);
+    let bounds = report.bounds.unwrap();
+    assert_eq!(bounds.min, Vector3::new(-1., -1., -1.));
+    assert_eq!(bounds.size, Vector3::new(2., 2., 2.));
+    assert!(report.attributes.normals && report.attributes.uvs && !report.attributes.colors);
+    assert_eq!((report.boundary_edges, report.open_boundaries, report.non_manifold_edges, report.degenerate_triangles), (0, 0, 0, 0));
+    assert!(report.is_watertight());
+}
+
+#[test]
+fn polygons_are_triangulated_and_open_edges_found() {
+    let report = inspect(&format!("{}f 1 2 3 4\n", SQUARE));
+    assert_eq!(report.triangles, 2);
+    assert_eq!(report.unique_positions, 4);
+    // the diagonal is shared, the four sides are not
+    assert_eq!(report.boundary_edges, 4);
+    assert_eq!(report.open_boundaries, 1);
+    assert!(!report.attributes.normals && !report.attributes.uvs);
+    assert!(!report.is_watertight());
+
+    let two = inspect(&format!("{}v 5 0 0\nv 6 0 0\nv 6 1 0\nf 1 2 3 4\nf 5 6 7\n", SQUARE));
+    assert_eq!(two.open_boundaries, 2);
+}
+
+#[test]
+fn finds_non_manifold_edges_and_degenerate_triangles() {
+    let fin = inspect(&format!("{}v 0 0 1\nf 1 2 3\nf 2 1 4\nf 1 2 5\n", SQUARE));
+    assert_eq!(fin.non_manifold_edges, 1);
+
+    let flat = inspect("v 0 0 0\nv 1 0 0\nv 2 0 0\nv 0 1 0\nf 1 2 3\nf 1 2 4\nf 4 4 2\n");
+    assert_eq!(flat.degenerate_triangles, 2);
+}
+
+#[test]
+fn lists_groups_and_materials() {
+    let obj = format!(
+        "{}vt 0 0\nvn 0 0 1\ng front\nusemtl red\nf 1/1/1 2/1/1 3/1/1\nusemtl blue\nf -4//-1 -2//-1 -1//-1\ng back\nusemtl red\nf 3/1 2/1 1/1\n",
+        SQUARE,
+    );
+    let report = inspect(&obj);
+    assert_eq!(report.triangles, 3);
+    assert_eq!(report.groups, vec![
+        Part { name: String::from("front"), triangles: 2 },
+        Part { name: String::from("back"), triangles: 1 },
+    ]);
+    assert_eq!(report.materials, vec![
+        Part { name: String::from("red"), triangles: 2 },
+        Part { name: String::from("blue"), triangles: 1 },
+    ]);
+    // the last face has no normals and the middle one no texture coordinates
+    assert!(!report.attributes.normals && !report.attributes.uvs);
+}
+
+#[test]
+fn empty_meshes_have_no_bounds() {
+    let report = inspect("# nothing here\n");
+    assert_eq!(report.triangles, 0);
+    assert_eq!(report.bounds, None);
+    let json = serde_json::to_value(&report).unwrap();
+    assert!(json["bounds"].is_null());
+    assert_eq!(json["triangles"], 0);
+}
+
+#[test]
+fn malformed_files_are_errors() {
+    for (name, obj, line) in [("range", "v 0 0 0\nf 1 2 3\n", 2), ("number", "v 0 zero 0\n", 1), ("short", "v 0 0 0\nf 1 1\n", 2)] {
+        let error = load_obj(obj).err().unwrap_or_else(|| panic!("{} loaded", name));
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with(&format!("line {}:", line)), "{}", error);
+    }
+}
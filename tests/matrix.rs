@@ -0,0 +1,162 @@
+mod common;
+
+use rand::Rng;
+use triangle::matrix::Matrix4;
+use triangle::transform::Transform;
+use triangle::vector::Vector3;
+use common::*;
+
+fn random_transform(rng: &mut rand::rngs::StdRng) -> Transform {
+    let mut transform = Transform::default();
+    transform.set_position(random_vector(rng, 10.));
+    transform.set_rotation(random_rotation(rng));
+    transform.set_scale(random_scale(rng));
+    transform
+}
+
+#[test]
+fn determinant_of_known_matrices() {
+    assert!((Matrix4::identity().determinant() - 1.).abs() < EPSILON);
+    assert!((Matrix4::scale(Vector3::new(2., 3., 4.)).determinant() - 24.).abs() < EPSILON);
+    assert!((Matrix4::translate(Vector3::new(5., -1., 2.)).determinant() - 1.).abs() < EPSILON);
+    let m = from_rows([
+        [2., 0., 1., 3.],
+        [1., 1., 0., 2.],
+        [0., 4., 1., 1.],
+        [3., 0., 2., 1.],
+    ]);
+    assert!((m.determinant() + 20.).abs() < 1e-4, "{}", m.determinant());
+}
+
+#[test]
+fn determinant_is_multiplicative() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let (a, b) = (random_matrix(&mut rng), random_matrix(&mut rng));
+        let expected = a.determinant() * b.determinant();
+        let actual = (a * b).determinant();
+        assert!((actual - expected).abs() < 1e-3 * expected.abs().max(1.), "{} != {}", actual, expected);
+    }
+}
+
+#[test]
+fn determinant_ignores_transpose() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let m = random_matrix(&mut rng);
+        assert!((m.determinant() - m.transpose().determinant()).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn inverse_times_matrix_is_identity() {
+    let mut rng = rng();
+    let mut checked = 0;
+    while checked < CASES {
+        let m = random_matrix(&mut rng);
+        // keep away from nearly singular matrices, where f32 loses too much
+        if m.determinant().abs() < 0.5 {
+            continue;
+        }
+        let inverse = m.inverse().unwrap();
+        assert_matrix_near(m * inverse, Matrix4::identity(), 1e-3);
+        assert_matrix_near(inverse * m, Matrix4::identity(), 1e-3);
+        assert!((inverse.determinant() * m.determinant() - 1.).abs() < 1e-3);
+        checked += 1;
+    }
+}
+
+#[test]
+fn singular_matrices_have_no_inverse() {
+    let flat = Matrix4::scale(Vector3::new(1., 0., 1.));
+    assert!(flat.inverse().is_none());
+    assert!(flat.affine_inverse().is_none());
+
+    let mut rng = rng();
+    for _ in 0..CASES {
+        // two equal rows, with small integers so the determinant cancels exactly
+        let mut rows = [[0.; 4]; 4];
+        for v in rows.iter_mut().flatten() {
+            *v = rng.random_range(-5..=5) as f32;
+        }
+        let r = rng.random_range(0..3);
+        rows[r + 1] = rows[r];
+        let m = from_rows(rows);
+        assert_eq!(m.determinant(), 0.);
+        assert!(m.inverse().is_none());
+    }
+}
+
+#[test]
+fn affine_inverse_matches_general_inverse() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let m = random_transform(&mut rng).as_matrix();
+        assert_matrix_near(m.affine_inverse().unwrap(), m.inverse().unwrap(), 1e-3);
+        assert_matrix_near(m * m.affine_inverse().unwrap(), Matrix4::identity(), 1e-4);
+    }
+}
+
+#[test]
+fn transform_inverse_matrix_matches_inverse() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let transform = random_transform(&mut rng);
+        assert_matrix_near(transform.inverse_matrix(), transform.as_matrix().inverse().unwrap(), 1e-3);
+    }
+}
+
+#[test]
+fn decompose_recovers_translation_rotation_and_scale() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let transform = random_transform(&mut rng);
+        let (translation, rotation, scale) = transform.as_matrix().decompose();
+        assert_vector_near(translation, transform.get_position(), 1e-4);
+        assert_vector_near(scale, transform.get_scale(), 1e-4);
+        // q and -q are the same rotation
+        assert!(rotation.dot(transform.get_rotation()).abs() > 1. - 1e-4);
+    }
+}
+
+#[test]
+fn decompose_round_trips_through_transform() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let m = random_transform(&mut rng).as_matrix();
+        assert_matrix_near(Transform::from_matrix(m).as_matrix(), m, 1e-4);
+    }
+}
+
+#[test]
+fn decompose_keeps_mirroring() {
+    let m = Matrix4::scale(Vector3::new(1., -2., 3.)) * Matrix4::translate(Vector3::new(1., 2., 3.));
+    let (_, _, scale) = m.decompose();
+    assert!(scale.x * scale.y * scale.z < 0.);
+    assert_matrix_near(Transform::from_matrix(m).as_matrix(), m, 1e-5);
+}
+
+#[test]
+fn normal_matrix_keeps_normals_perpendicular() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let m = random_transform(&mut rng).as_matrix();
+        let n = m.normal_matrix();
+
+        let normal = random_vector(&mut rng, 1.);
+        // any tangent of the surface, i.e. any vector perpendicular to the normal
+        let tangent = normal.cross(&random_vector(&mut rng, 1.));
+        let moved_normal = n.transform_direction(normal);
+        let moved_tangent = m.transform_direction(tangent);
+        let cos = moved_normal.dot(&moved_tangent) / (moved_normal.length() * moved_tangent.length()).max(1e-6);
+        assert!(cos.abs() < 1e-3, "{}", cos);
+    }
+}
+
+#[test]
+fn normal_matrix_ignores_translation() {
+    let m = Matrix4::translate(Vector3::new(3., 4., 5.));
+    assert_matrix_eq(m.normal_matrix(), Matrix4::identity());
+    let p = m.normal_matrix().transform_point(Vector3::new(1., 2., 3.));
+    assert_vector_eq(p, Vector3::new(1., 2., 3.));
+}
@@ -0,0 +1,324 @@
+mod common;
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use triangle::matrix::Matrix4;
+use triangle::mesh::Mesh;
+use triangle::model::Model;
+use triangle::mesh_file::{load_mesh, save_mesh, ConvertOptions, Handedness, ImportOptions, MeshFormat, Normals, UpAxis};
+use triangle::vector::Vector3;
+use common::assert_vector_eq;
+
+fn temp(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("triangle-mesh-file-{}-{}", std::process::id(), name))
+}
+
+fn cube() -> Mesh {
+    load_mesh("assets/cube.obj").unwrap()
+}
+
+/// Six times the enclosed volume, positive when the triangles face outwards.
+fn volume(mesh: &Mesh) -> f32 {
+    (0..mesh.get_triangle_count()).map(|t| {
+        let triangle = mesh.get_triangle(t);
+        triangle.a.dot(&triangle.b.cross(&triangle.c))
+    }).sum()
+}
+
+#[test]
+fn formats_follow_the_extension() {
+    assert_eq!(MeshFormat::from_path("a.OBJ").unwrap(), MeshFormat::Obj);
+    assert_eq!(MeshFormat::from_path("dir/a.stl").unwrap(), MeshFormat::Stl);
+    assert_eq!(MeshFormat::from_path("a.ply").unwrap(), MeshFormat::Ply);
+    assert_eq!(MeshFormat::from_path("a.gltf").unwrap(), MeshFormat::Gltf);
+    assert_eq!(MeshFormat::from_path("a.glb").unwrap(), MeshFormat::Glb);
+    assert!(MeshFormat::from_path("a.fbx").is_err());
+    assert!(MeshFormat::from_path("obj").is_err());
+}
+
+#[test]
+fn every_format_round_trips() {
+    let cube = cube();
+    for extension in ["obj", "stl", "ply", "gltf", "glb"] {
+        let path = temp(&format!("cube.{}", extension));
+        save_mesh(&cube, &path).unwrap();
+        let mesh = load_mesh(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(mesh.get_triangle_count(), 12, "{}", extension);
+        for t in 0..12 {
+            let (a, b) = (cube.get_triangle(t), mesh.get_triangle(t));
+            assert_vector_eq(a.a, b.a);
+            assert_vector_eq(a.b, b.b);
+            assert_vector_eq(a.c, b.c);
+        }
+        assert!(volume(&mesh) > 0., "{}", extension);
+        // STL only keeps positions
+        if extension != "stl" {
+            assert_eq!(mesh.attributes, cube.attributes, "{}", extension);
+            let corner = |m: &Mesh, i: usize| m.vb.get_vertex(m.ib.get_index(i) as usize);
+            for i in 0..36 {
+                assert_vector_eq(corner(&cube, i).get_normal(), corner(&mesh, i).get_normal());
+                assert_eq!(corner(&cube, i).get_uv(), corner(&mesh, i).get_uv(), "{}", extension);
+            }
+        }
+    }
+}
+
+#[test]
+fn groups_and_materials_survive_obj_and_gltf() {
+    let path = temp("groups.obj");
+    std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\ng a\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 3 4\ng b\nf 3 2 1\n").unwrap();
+    let mesh = load_mesh(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(mesh.submeshes.len(), 3);
+
+    for extension in ["obj", "gltf", "glb"] {
+        let path = temp(&format!("groups.{}", extension));
+        save_mesh(&mesh, &path).unwrap();
+        let loaded = load_mesh(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.submeshes, mesh.submeshes, "{}", extension);
+    }
+}
+
+#[test]
+fn reads_text_stl() {
+    let path = temp("text.stl");
+    std::fs::write(&path, "solid first\n facet normal 0 0 0\n  outer loop\n   vertex 0 0 0\n   vertex 1 0 0\n   vertex 0 1 0\n  endloop\n endfacet\nendsolid first\n\
+        solid second\n facet normal 0 0 1\n  outer loop\n   vertex 0 0 1\n   vertex 1 0 1\n   vertex 0 1 1\n  endloop\n endfacet\nendsolid second\n").unwrap();
+    let mesh = load_mesh(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(mesh.get_triangle_count(), 2);
+    let groups: Vec<&str> = mesh.submeshes.iter().map(|s| s.group.as_str()).collect();
+    assert_eq!(groups, ["first", "second"]);
+    // the zero normal is replaced by the face normal
+    assert_vector_eq(mesh.vb.get_vertex(0).get_normal(), Vector3::new(0., 0., 1.));
+}
+
+#[test]
+fn reads_text_ply_with_polygons_and_colours() {
+    let path = temp("text.ply");
+    std::fs::write(&path, "ply\nformat ascii 1.0\ncomment a square\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+        property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n\
+        0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n4 0 1 2 3\n").unwrap();
+    let mesh = load_mesh(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(mesh.get_triangle_count(), 2);
+    assert!(mesh.attributes.colors && !mesh.attributes.normals && !mesh.attributes.uvs);
+    assert_eq!(mesh.vb.get_vertex(1).get_color(), [0., 1., 0., 1.]);
+    assert_vector_eq(mesh.vb.get_vertex(0).get_normal(), Vector3::new(0., 0., 1.));
+
+    let broken = temp("broken.ply");
+    std::fs::write(&broken, "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0\n3 0 1 2\n").unwrap();
+    assert!(load_mesh(&broken).is_err());
+    std::fs::remove_file(&broken).unwrap();
+}
+
+#[test]
+fn gltf_nodes_are_placed_and_strips_triangulated() {
+    let bin = temp("strip.bin");
+    let positions: [f32; 12] = [0., 0., 0., 1., 0., 0., 0., 1., 0., 1., 1., 0.];
+    std::fs::write(&bin, positions.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>()).unwrap();
+    let path = temp("strip.gltf");
+    let document = serde_json::json!({
+        "asset": { "version": "2.0" },
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "translation": [10., 0., 0.], "children": [1] },
+            { "name": "strip", "mesh": 0, "scale": [2., 2., 2.] },
+        ],
+        "meshes": [{ "name": "quad", "primitives": [{ "attributes": { "POSITION": 0 }, "mode": 5 }] }],
+        "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3" }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 48 }],
+        "buffers": [{ "byteLength": 48, "uri": bin.file_name().unwrap().to_str().unwrap() }],
+    });
+    std::fs::write(&path, document.to_string()).unwrap();
+    let mesh = load_mesh(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&bin).unwrap();
+
+    assert_eq!(mesh.get_triangle_count(), 2);
+    assert_eq!(mesh.submeshes[0].group, "quad");
+    let second = mesh.get_triangle(1);
+    // the second triangle of a strip is turned round to keep the winding
+    assert_vector_eq(second.a, Vector3::new(10., 2., 0.));
+    assert_vector_eq(second.b, Vector3::new(12., 0., 0.));
+    assert_vector_eq(second.c, Vector3::new(12., 2., 0.));
+    assert!(!mesh.attributes.normals);
+    assert_vector_eq(mesh.vb.get_vertex(mesh.ib.get_index(3) as usize).get_normal(), Vector3::new(0., 0., 1.));
+}
+
+/// Loads a glTF triangle whose buffer holds `bin`, with `attributes`, `indices` and `accessors`
+/// laid over it.
+fn load_gltf(name: &str, bin: &[u8], attributes: serde_json::Value, indices: Option<usize>, accessors: serde_json::Value) -> std::io::Result<Mesh> {
+    let bin_path = temp(&format!("{}.bin", name));
+    std::fs::write(&bin_path, bin).unwrap();
+    let path = temp(&format!("{}.gltf", name));
+    let document = serde_json::json!({
+        "asset": { "version": "2.0" },
+        "meshes": [{ "primitives": [{ "attributes": attributes, "indices": indices }] }],
+        "accessors": accessors,
+        "bufferViews": [{ "buffer": 0, "byteLength": bin.len() }],
+        "buffers": [{ "byteLength": bin.len(), "uri": bin_path.file_name().unwrap().to_str().unwrap() }],
+    });
+    std::fs::write(&path, document.to_string()).unwrap();
+    let mesh = load_mesh(&path);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&bin_path).unwrap();
+    mesh
+}
+
+#[test]
+fn gltf_attributes_must_match_the_positions() {
+    let positions: [f32; 9] = [0., 0., 0., 1., 0., 0., 0., 1., 0.];
+    let bin: Vec<u8> = positions.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let position = serde_json::json!({ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" });
+    let mesh = load_gltf("attributes", &bin, serde_json::json!({ "POSITION": 0 }), None, serde_json::json!([position])).unwrap();
+    assert_eq!(mesh.get_triangle_count(), 1);
+
+    // normals for one vertex only
+    let short = serde_json::json!({ "bufferView": 0, "componentType": 5126, "count": 1, "type": "VEC3" });
+    let error = load_gltf("short", &bin, serde_json::json!({ "POSITION": 0, "NORMAL": 1 }), None, serde_json::json!([position, short])).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    // texture coordinates with three components
+    let wide = serde_json::json!({ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" });
+    let error = load_gltf("wide", &bin, serde_json::json!({ "POSITION": 0, "TEXCOORD_0": 1 }), None, serde_json::json!([position, wide])).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    // colours with two
+    let narrow = serde_json::json!({ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC2" });
+    let error = load_gltf("narrow", &bin, serde_json::json!({ "POSITION": 0, "COLOR_0": 1 }), None, serde_json::json!([position, narrow])).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn gltf_indices_are_read_as_integers() {
+    let positions: [f32; 9] = [0., 0., 0., 1., 0., 0., 0., 1., 0.];
+    let mut bin: Vec<u8> = positions.iter().flat_map(|v| v.to_le_bytes()).collect();
+    bin.extend([2u16, 1, 0].iter().flat_map(|i| i.to_le_bytes()));
+    let position = serde_json::json!({ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" });
+    let attributes = serde_json::json!({ "POSITION": 0 });
+    let shorts = serde_json::json!({ "bufferView": 0, "byteOffset": 36, "componentType": 5123, "count": 3, "type": "SCALAR" });
+    let mesh = load_gltf("shorts", &bin, attributes.clone(), Some(1), serde_json::json!([position, shorts])).unwrap();
+    // wound the other way from the positions' order
+    assert_vector_eq(mesh.get_triangle(0).a, Vector3::new(0., 1., 0.));
+    assert_vector_eq(mesh.get_triangle(0).c, Vector3::new(0., 0., 0.));
+
+    // indices must be unsigned integers
+    bin.extend([0, 0]);
+    bin.extend([0f32, 1., 2.].iter().flat_map(|v| v.to_le_bytes()));
+    let floats = serde_json::json!({ "bufferView": 0, "byteOffset": 44, "componentType": 5126, "count": 3, "type": "SCALAR" });
+    assert!(load_gltf("floats", &bin, attributes, Some(1), serde_json::json!([position, floats])).is_err());
+}
+
+#[test]
+fn up_axes_turn_into_each_other() {
+    let z_to_y = UpAxis::Z.rotation_to(UpAxis::Y);
+    assert_vector_eq(z_to_y.transform_point(Vector3::new(0., 0., 1.)), Vector3::new(0., 1., 0.));
+    assert_vector_eq(z_to_y.transform_point(Vector3::new(0., 1., 0.)), Vector3::new(0., 0., -1.));
+    let round_trip = z_to_y * UpAxis::Y.rotation_to(UpAxis::Z);
+    assert_vector_eq(round_trip.transform_point(Vector3::new(1., 2., 3.)), Vector3::new(1., 2., 3.));
+    assert!(z_to_y.determinant() > 0.);
+}
+
+#[test]
+fn import_options_map_file_coordinates_onto_ours() {
+    // a Z up file in millimetres
+    let z_up_mm = ImportOptions { up_axis: UpAxis::Z, unit_scale: 0.001, ..ImportOptions::default() };
+    let m = z_up_mm.get_matrix();
+    assert_vector_eq(m.transform_point(Vector3::new(0., 0., 1000.)), Vector3::new(0., 1., 0.));
+    assert_vector_eq(m.transform_point(Vector3::new(1000., 0., 0.)), Vector3::new(1., 0., 0.));
+    assert!(m.determinant() > 0.);
+
+    // left-handed files are mirrored along their depth axis
+    let left = ImportOptions { handedness: Handedness::Left, ..ImportOptions::default() };
+    assert_vector_eq(left.get_matrix().transform_point(Vector3::new(1., 2., 3.)), Vector3::new(1., 2., -3.));
+    let left_z_up = ImportOptions { up_axis: UpAxis::Z, handedness: Handedness::Left, ..ImportOptions::default() };
+    assert_vector_eq(left_z_up.get_matrix().transform_point(Vector3::new(1., 2., 3.)), Vector3::new(1., 3., 2.));
+    assert!(ImportOptions::default().get_matrix().determinant() == 1.);
+}
+
+#[test]
+fn left_handed_imports_keep_normals_and_winding_in_agreement() {
+    let mut mesh = cube();
+    ImportOptions { handedness: Handedness::Left, ..ImportOptions::default() }.apply(&mut mesh);
+    assert!(volume(&mesh) > 0.);
+    for t in 0..mesh.get_triangle_count() {
+        let triangle = mesh.get_triangle(t);
+        let face = (triangle.b - triangle.a).cross(&(triangle.c - triangle.a));
+        assert!(mesh.vb.get_vertex(mesh.ib.get_index(3 * t) as usize).get_normal().dot(&face) > 0.);
+    }
+}
+
+#[test]
+fn models_remember_how_they_were_imported() {
+    let import = ImportOptions { up_axis: UpAxis::Z, ..ImportOptions::default() };
+    let mut model = Model::default();
+    model.try_load_with("assets/pawn.obj", import).unwrap();
+    assert_eq!(model.get_import_options(), import);
+    // the pawn stands along y in the file, so along -z once read as Z up
+    let bounds = model.get_bounds();
+    assert!(bounds.size().z > bounds.size().y);
+    assert!(bounds.min.z < -3.);
+
+    model.try_load("assets/pawn.obj").unwrap();
+    assert_eq!(model.get_import_options(), ImportOptions::default());
+}
+
+#[test]
+fn unit_scales_must_be_positive() {
+    for unit_scale in [0., -1., f32::NAN, f32::INFINITY] {
+        let import = ImportOptions { unit_scale, ..ImportOptions::default() };
+        assert!(import.validate().is_err());
+        let mut model = Model::default();
+        let error = model.try_load_with("assets/cube.obj", import).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+    assert!(ImportOptions { unit_scale: 0.001, ..ImportOptions::default() }.validate().is_ok());
+}
+
+#[test]
+fn mirroring_keeps_triangles_facing_out() {
+    let mut mesh = cube();
+    mesh.transform(&Matrix4::scale(Vector3::new(-1., 1., 1.)));
+    assert!(volume(&mesh) > 0.);
+}
+
+#[test]
+fn convert_options() {
+    let mut mesh = cube();
+    let import = ImportOptions { up_axis: UpAxis::Z, unit_scale: 2., ..ImportOptions::default() };
+    let options = ConvertOptions { import, normals: Some(Normals::Smooth), weld: true, merge: true, ..ConvertOptions::default() };
+    options.apply(&mut mesh);
+
+    assert_eq!(mesh.get_triangle_count(), 12);
+    assert!(mesh.submeshes.is_empty());
+    // with one normal per position, only seams in the texture coordinates keep corners apart
+    let corners: HashSet<[u32; 5]> = (0..mesh.vb.get_vertex_count()).map(|i| {
+        let (p, [u, v]) = (mesh.vb.get_vertex(i).get_position(), mesh.vb.get_vertex(i).get_uv());
+        [p.x, p.y, p.z, u, v].map(f32::to_bits)
+    }).collect();
+    assert_eq!(mesh.vb.get_vertex_count(), corners.len());
+    assert!(mesh.vb.get_vertex_count() < 36);
+    assert_eq!(mesh.weld(), 0);
+    for i in 0..mesh.vb.get_vertex_count() {
+        let vertex = mesh.vb.get_vertex(i);
+        let p = vertex.get_position();
+        assert!(p.x.abs() == 2. && p.y.abs() == 2. && p.z.abs() == 2.);
+        // smooth normals at a corner point away from the centre
+        assert!(vertex.get_normal().dot(&p) > 0.);
+        assert!((vertex.get_normal().length() - 1.).abs() < 1e-5);
+    }
+
+    let mut flat = cube();
+    flat.weld();
+    flat.recompute_normals(false);
+    assert_eq!(flat.vb.get_vertex_count(), 36);
+    for t in 0..12 {
+        let triangle = flat.get_triangle(t);
+        let normal = (triangle.b - triangle.a).cross(&(triangle.c - triangle.a)).normalized();
+        assert_vector_eq(flat.vb.get_vertex(flat.ib.get_index(3 * t) as usize).get_normal(), normal);
+    }
+}
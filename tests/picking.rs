@@ -0,0 +1,100 @@
+mod common;
+
+use triangle::camera::{Camera, Projection};
+use triangle::model::Model;
+use triangle::picking::{pick, pick_screen};
+use triangle::quaternion::DQuaternion;
+use triangle::vector::Vector3;
+use common::*;
+
+const WIDTH: f32 = 640.;
+const HEIGHT: f32 = 480.;
+
+fn cube_at(position: Vector3<f64>) -> Model {
+    let mut model = Model::default();
+    model.load_obj("assets/cube.obj");
+    model.transform.set_position(position);
+    model
+}
+
+fn camera_at(position: Vector3<f64>) -> Camera {
+    let mut camera = Camera::new(1., WIDTH / HEIGHT, 0.1, 100.);
+    camera.transform.set_position(position);
+    camera
+}
+
+#[test]
+fn screen_ray_through_the_center_looks_forward() {
+    let camera = camera_at(Vector3::new(3., 2., 5.));
+    let ray = camera.screen_ray(WIDTH / 2., HEIGHT / 2., WIDTH, HEIGHT);
+    assert_vector_near(ray.direction, Vector3::new(0., 0., -1.), EPSILON);
+    // camera-relative, starting on the near plane
+    assert_vector_near(ray.origin, Vector3::new(0., 0., -0.1), EPSILON);
+
+    // the top left corner is up and to the left
+    let corner = camera.screen_ray(0., 0., WIDTH, HEIGHT);
+    assert!(corner.direction.x < 0. && corner.direction.y > 0. && corner.direction.z < 0.);
+    let expected_y = (0.5f32).tan();
+    assert!((corner.direction.y / -corner.direction.z - expected_y).abs() < 1e-4);
+}
+
+#[test]
+fn orthographic_rays_are_parallel() {
+    let mut camera = camera_at(Vector3::new(0., 0., 5.));
+    camera.set_projection(Projection::Orthographic);
+    let a = camera.screen_ray(10., 20., WIDTH, HEIGHT);
+    let b = camera.screen_ray(600., 400., WIDTH, HEIGHT);
+    assert_vector_near(a.direction, Vector3::new(0., 0., -1.), EPSILON);
+    assert_vector_near(b.direction, Vector3::new(0., 0., -1.), EPSILON);
+    assert!(a.origin.distance(&b.origin) > 0.1);
+}
+
+#[test]
+fn picks_the_cube_face_under_the_cursor() {
+    let models = vec![cube_at(Vector3::default())];
+    let camera = camera_at(Vector3::new(0., 0., 5.));
+    let hit = pick_screen(&models, &camera, WIDTH / 2., HEIGHT / 2., WIDTH, HEIGHT).unwrap();
+
+    assert_eq!(hit.model, 0);
+    assert!(hit.point.approx_eq(&Vector3::new(0., 0., 1.), 1e-5));
+    assert!((hit.distance - 3.9).abs() < 1e-4);
+    assert_vector_near(hit.normal, Vector3::new(0., 0., 1.), EPSILON);
+    assert!((hit.barycentric.x + hit.barycentric.y + hit.barycentric.z - 1.).abs() < EPSILON);
+
+    assert!(pick_screen(&models, &camera, 5., 5., WIDTH, HEIGHT).is_none());
+}
+
+#[test]
+fn the_nearest_model_wins() {
+    let models = vec![cube_at(Vector3::new(0., 0., -10.)), cube_at(Vector3::default()), cube_at(Vector3::new(0., 0., -20.))];
+    let camera = camera_at(Vector3::new(0., 0., 5.));
+    let hit = pick_screen(&models, &camera, WIDTH / 2., HEIGHT / 2., WIDTH, HEIGHT).unwrap();
+    assert_eq!(hit.model, 1);
+}
+
+#[test]
+fn picks_through_the_model_transform() {
+    let mut model = cube_at(Vector3::new(1., 0., 0.));
+    model.transform.set_scale(Vector3::new(2., 2., 2.));
+    model.transform.set_rotation(DQuaternion::from_angle_axis(std::f64::consts::FRAC_PI_4, Vector3::new(0., 1., 0.)));
+    let models = vec![model];
+    let camera = camera_at(Vector3::new(1., 0., 10.));
+
+    let ray = camera.screen_ray(WIDTH / 2., HEIGHT / 2., WIDTH, HEIGHT);
+    let hit = pick(&models, &camera, &ray).unwrap();
+    // the rotated cube's front edge is sqrt(2) * 2 in front of its center
+    let front = 2. * 2f64.sqrt();
+    assert!(hit.point.approx_eq(&Vector3::new(1., 0., front), 1e-4));
+    assert!((hit.distance as f64 - (10. - front - 0.1)).abs() < 1e-4);
+    assert!(hit.normal.z > 0.5 && (hit.normal.length() - 1.).abs() < EPSILON);
+}
+
+#[test]
+fn picking_is_precise_far_from_the_origin() {
+    let site = Vector3::new(500_000.123, 5_400_000.456, 250.789);
+    let models = vec![cube_at(site)];
+    let camera = camera_at(site + Vector3::new(0.25, 0.5, 5.));
+    let ray = camera.screen_ray(WIDTH / 2., HEIGHT / 2., WIDTH, HEIGHT);
+    let hit = pick(&models, &camera, &ray).unwrap();
+    assert!(hit.point.approx_eq(&(site + Vector3::new(0.25, 0.5, 1.)), 1e-5));
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use std::f32::consts::FRAC_PI_2;
+use triangle::camera::Camera;
+use triangle::matrix::DMatrix4;
+use triangle::model::Model;
+use triangle::quaternion::{DQuaternion, Quaternion};
+use triangle::shader::{BaseShader, PhongShader};
+use triangle::transform::DTransform;
+use triangle::vector::Vector3;
+use common::*;
+
+// a survey site in UTM coordinates, where f32 only resolves about 6 cm
+const SITE: Vector3<f64> = Vector3::new(500_000.123, 5_400_000.456, 250.789);
+
+#[test]
+fn f32_cannot_hold_site_coordinates() {
+    let offset = Vector3::new(0.001, 0.001, 0.);
+    let nudged = (SITE + offset).cast::<f32>() - SITE.cast::<f32>();
+    assert!(nudged.x == 0. || (nudged.x - 0.001).abs() > 0.01, "the premise of the f64 path");
+}
+
+#[test]
+fn relative_transform_is_exact_near_the_origin() {
+    let mut transform = DTransform::default();
+    transform.set_position(SITE + Vector3::new(0.001, 0.002, -0.003));
+    transform.set_rotation(DQuaternion::from_angle_axis(0.5, Vector3::new(0., 0., 1.)));
+
+    let relative = transform.relative_to(SITE);
+    assert_vector_near(relative.get_position(), Vector3::new(0.001, 0.002, -0.003), 1e-9);
+    assert!(relative.get_rotation().dot(Quaternion::from_angle_axis(0.5, Vector3::forward())) > 1. - 1e-7);
+}
+
+#[test]
+fn double_precision_matrix_math() {
+    let mut transform = DTransform::default();
+    transform.set_position(SITE);
+    transform.set_rotation(DQuaternion::from_angle_axis(1.2, Vector3::new(1., 2., 3.)));
+    transform.set_scale(Vector3::new(2., 2., 2.));
+
+    let m = transform.as_matrix();
+    let p = Vector3::new(0.25, -0.5, 1.);
+    let back = m.inverse().unwrap().transform_point(m.transform_point(p));
+    assert!(back.approx_eq(&p, 1e-9));
+    assert!(((m * transform.inverse_matrix()).cast::<f32>().determinant() - 1.).abs() < 1e-6);
+
+    let (t, r, s) = m.decompose();
+    assert!(t.approx_eq(&SITE, 1e-9));
+    assert!(s.approx_eq(&Vector3::splat(2.), 1e-12));
+    assert!(r.dot(transform.get_rotation()).abs() > 1. - 1e-12);
+    let identity = DMatrix4::identity();
+    assert!((identity.determinant() - 1.).abs() < 1e-15);
+}
+
+#[test]
+fn camera_relative_view_matches_full_view() {
+    let mut camera = Camera::new(FRAC_PI_2, 1., 0.1, 100.);
+    camera.transform.set_position(Vector3::new(3., 4., 5.));
+    camera.transform.look_at(Vector3::new(0., 0., 0.), Vector3::new(0., 1., 0.));
+
+    // at small coordinates both paths agree: moving the world by -camera then viewing from the origin
+    let mut model = DTransform::default();
+    model.set_position(Vector3::new(1., -1., 2.));
+    let full = model.cast::<f32>().as_matrix() * camera.view_matrix();
+    let relative = model.relative_to(camera.get_position()).as_matrix() * camera.relative_view_matrix();
+    assert_matrix_near(relative, full, 1e-5);
+}
+
+#[test]
+fn rendering_far_from_the_origin_keeps_millimetres() {
+    let mut camera = Camera::new(FRAC_PI_2, 1., 0.01, 100.);
+    camera.transform.set_position(SITE);
+
+    let mut model = Model::default();
+    model.transform.set_position(SITE + Vector3::new(0.001, 0., -1.));
+
+    // the model matrix sent to the shader is the fourth 4x4 after three colors
+    let uniforms = PhongShader::default().as_vec(&model, &camera);
+    let model_matrix = &uniforms[12..28];
+    // column-major, translation in the last column
+    assert!((model_matrix[12] - 0.001).abs() < 1e-7, "{}", model_matrix[12]);
+    assert!((model_matrix[14] + 1.).abs() < 1e-7, "{}", model_matrix[14]);
+
+    let view = &uniforms[28..44];
+    assert_eq!(&view[12..15], &[0., 0., 0.]);
+}
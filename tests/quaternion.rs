@@ -0,0 +1,225 @@
+mod common;
+
+use std::f32::consts::{FRAC_PI_2, PI};
+use rand::Rng;
+use triangle::quaternion::Quaternion;
+use triangle::vector::Vector3;
+use common::*;
+
+fn assert_same_rotation(actual: Quaternion, expected: Quaternion, tolerance: f32) {
+    // q and -q are the same rotation
+    let d = actual.normalized().dot(expected.normalized()).abs();
+    assert!(d > 1. - tolerance, "rotations differ, |dot| = {}", d);
+}
+
+fn random_direction(rng: &mut rand::rngs::StdRng) -> Vector3<f32> {
+    let mut v = random_vector(rng, 1.);
+    while v.length_squared() < 1e-2 {
+        v = random_vector(rng, 1.);
+    }
+    v.normalize();
+    v
+}
+
+#[test]
+fn product_composes_rotations() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let (a, b) = (random_rotation(&mut rng), random_rotation(&mut rng));
+        let v = random_vector(&mut rng, 5.);
+        // b is applied first
+        assert_vector_near((a * b).rotate(v), a.rotate(b.rotate(v)), 1e-4);
+    }
+}
+
+#[test]
+fn product_matches_hamilton_table() {
+    let i = Quaternion::new(0., 1., 0., 0.);
+    let j = Quaternion::new(0., 0., 1., 0.);
+    let k = Quaternion::new(0., 0., 0., 1.);
+    let minus_one = Quaternion::new(-1., 0., 0., 0.);
+    assert!((i * j).dot(k) > 1. - EPSILON);
+    assert!((j * k).dot(i) > 1. - EPSILON);
+    assert!((k * i).dot(j) > 1. - EPSILON);
+    assert!((j * i).dot(-k) > 1. - EPSILON);
+    assert!((i * j * k).dot(minus_one) > 1. - EPSILON);
+}
+
+#[test]
+fn product_of_unit_quaternions_stays_unit() {
+    let mut rng = rng();
+    let mut q = Quaternion::default();
+    for _ in 0..CASES {
+        q *= random_rotation(&mut rng);
+        assert!((q.norm() - 1.).abs() < 1e-4, "{}", q.norm());
+    }
+}
+
+#[test]
+fn times_inverse_is_identity() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let q = Quaternion::new(
+            rng.random_range(-2.0..2.0),
+            rng.random_range(-2.0..2.0),
+            rng.random_range(-2.0..2.0),
+            rng.random_range(-2.0..2.0),
+        );
+        if q.norm() < 0.1 {
+            continue;
+        }
+        let identity = Quaternion::default();
+        assert!((q * q.inverse()).dot(identity) > 1. - 1e-4);
+        assert!((q.inverse() * q).dot(identity) > 1. - 1e-4);
+        assert!(((q * q.inverse()).norm() - 1.).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn rotate_matches_matrix() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let q = random_rotation(&mut rng);
+        let v = random_vector(&mut rng, 5.);
+        assert_vector_near(q.rotate(v), q.as_mat4().transform_direction(v), 1e-4);
+        assert!((q.rotate(v).length() - v.length()).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn rotation_matrix_round_trip() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let q = random_rotation(&mut rng);
+        assert_same_rotation(Quaternion::from_rotation_matrix(q.as_mat4()), q, 1e-5);
+    }
+}
+
+#[test]
+fn euler_angles_follow_fly_camera_order() {
+    let q = Quaternion::from_euler_angles(0.3, 0.7, -0.4);
+    let expected = Quaternion::from_angle_axis(0.3, Vector3::up())
+        * Quaternion::from_angle_axis(0.7, Vector3::right())
+        * Quaternion::from_angle_axis(-0.4, Vector3::forward());
+    assert_same_rotation(q, expected, 1e-6);
+}
+
+#[test]
+fn euler_angles_round_trip() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let angles = Vector3::new(
+            rng.random_range(-FRAC_PI_2 + 0.01..FRAC_PI_2 - 0.01),
+            rng.random_range(-PI..PI),
+            rng.random_range(-PI..PI),
+        );
+        let back = Quaternion::from_euler_vector(angles).to_euler_angles();
+        assert_vector_near(back, angles, 1e-3);
+    }
+}
+
+#[test]
+fn euler_angles_of_any_rotation_give_it_back() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let q = random_rotation(&mut rng);
+        assert_same_rotation(Quaternion::from_euler_vector(q.to_euler_angles()), q, 1e-5);
+    }
+}
+
+#[test]
+fn euler_angles_at_gimbal_lock() {
+    for pitch in [FRAC_PI_2, -FRAC_PI_2] {
+        let q = Quaternion::from_euler_angles(0.5, pitch, 0.2);
+        let angles = q.to_euler_angles();
+        assert!((angles.x - pitch).abs() < 1e-3);
+        assert_eq!(angles.z, 0.);
+        assert_same_rotation(Quaternion::from_euler_vector(angles), q, 1e-5);
+    }
+}
+
+#[test]
+fn slerp_ends_and_midpoint() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let (a, b) = (random_rotation(&mut rng), random_rotation(&mut rng));
+        assert_same_rotation(a.slerp(b, 0.), a, 1e-5);
+        assert_same_rotation(a.slerp(b, 1.), b, 1e-5);
+
+        // the midpoint is as far from both ends
+        let mid = a.slerp(b, 0.5);
+        assert!((mid.norm() - 1.).abs() < 1e-4);
+        assert!((mid.dot(a).abs() - mid.dot(b).abs()).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn slerp_turns_at_constant_speed() {
+    let a = Quaternion::default();
+    let b = Quaternion::from_angle_axis(2., Vector3::up());
+    for t in [0.1, 0.25, 0.6, 0.9] {
+        assert_same_rotation(a.slerp(b, t), Quaternion::from_angle_axis(2. * t, Vector3::up()), 1e-6);
+    }
+}
+
+#[test]
+fn slerp_takes_the_short_way() {
+    let a = Quaternion::from_angle_axis(0.1, Vector3::up());
+    // the same rotation as 0.3 about up, but with the opposite sign
+    let b = -Quaternion::from_angle_axis(0.3, Vector3::up());
+    assert_same_rotation(a.slerp(b, 0.5), Quaternion::from_angle_axis(0.2, Vector3::up()), 1e-6);
+    assert_same_rotation(a.nlerp(b, 0.5), Quaternion::from_angle_axis(0.2, Vector3::up()), 1e-6);
+}
+
+#[test]
+fn nlerp_is_unit_and_close_to_slerp() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let a = random_rotation(&mut rng);
+        let b = a * Quaternion::from_angle_axis(0.2, random_direction(&mut rng));
+        let t = rng.random_range(0.0..1.0);
+        let n = a.nlerp(b, t);
+        assert!((n.norm() - 1.).abs() < 1e-5);
+        assert_same_rotation(n, a.slerp(b, t), 1e-4);
+    }
+}
+
+#[test]
+fn from_to_rotation_turns_from_into_to() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let (from, to) = (random_direction(&mut rng), random_direction(&mut rng));
+        let q = Quaternion::from_to_rotation(from * 3., to * 0.5);
+        assert_vector_near(q.rotate(from), to, 1e-4);
+        assert!((q.norm() - 1.).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn from_to_rotation_of_opposite_directions() {
+    for from in [Vector3::right(), Vector3::up(), Vector3::new(1., 2., 3.)] {
+        let to = -from;
+        let q = Quaternion::from_to_rotation(from, to);
+        assert_vector_near(q.rotate(from), to, 1e-4);
+    }
+}
+
+#[test]
+fn look_rotation_points_minus_z_forward() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let forward = random_direction(&mut rng);
+        let q = Quaternion::look_rotation(forward * 2., Vector3::up());
+        assert_vector_near(q.rotate(Vector3::backward()), forward, 1e-4);
+        // no roll: the right axis stays level
+        assert!(q.rotate(Vector3::right()).y.abs() < 1e-4);
+        assert!(q.rotate(Vector3::up()).y >= 0.);
+    }
+}
+
+#[test]
+fn look_rotation_along_up() {
+    let q = Quaternion::look_rotation(Vector3::up(), Vector3::up());
+    assert_vector_near(q.rotate(Vector3::backward()), Vector3::up(), 1e-5);
+    assert!((q.norm() - 1.).abs() < 1e-5);
+}
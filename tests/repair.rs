@@ -0,0 +1,146 @@
+mod common;
+
+use triangle::inspect::MeshReport;
+use triangle::mesh::Mesh;
+use triangle::model::Model;
+use triangle::repair::{RepairOptions, RepairReport};
+use triangle::vector::Vector3;
+use common::load_obj;
+
+const CUBE_CORNERS: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n";
+/// Every face of the unit cube but the top one, facing outwards.
+const OPEN_BOX: &str = "f 1 4 3 2\nf 1 2 6 5\nf 3 4 8 7\nf 1 5 8 4\nf 2 3 7 6\n";
+
+#[test]
+fn removes_degenerate_and_duplicate_triangles() {
+    let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 2 0 0\ng a\nf 1 2 3\nf 1 2 4\nf 3 3 1\ng b\nf 2 3 1\nf 3 2 1\nf 1 2 3\n";
+    let mut mesh = load_obj(obj).unwrap();
+    assert_eq!(mesh.get_degenerate_triangles(), vec![1, 2]);
+
+    assert_eq!(mesh.remove_degenerate_triangles(), 2);
+    assert_eq!(mesh.get_triangle_count(), 4);
+    // the same corners in any order are the same triangle
+    assert_eq!(mesh.remove_duplicate_triangles(), 3);
+    assert_eq!(mesh.get_triangle_count(), 1);
+    let groups: Vec<(&str, usize, usize)> = mesh.submeshes.iter().map(|s| (s.group.as_str(), s.first_triangle, s.triangle_count)).collect();
+    assert_eq!(groups, vec![("a", 0, 1)]);
+
+    assert_eq!(mesh.remove_unreferenced_vertices(), 15);
+    assert_eq!(mesh.vb.get_vertex_count(), 3);
+    assert_eq!(mesh.ib.get_indices(), vec![0, 1, 2]);
+}
+
+#[test]
+fn merges_vertices_that_nearly_touch() {
+    let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1.00001 0 0\nv 1 1 0\nv 0.00001 0.99999 0\nf 1 2 3\nf 4 5 6\n";
+    let mut mesh = load_obj(obj).unwrap();
+    assert_eq!(MeshReport::new(&mesh).boundary_edges, 6);
+
+    assert_eq!(mesh.merge_close_vertices(1e-4), 2);
+    assert_eq!(mesh.vb.get_vertex_count(), 4);
+    let report = MeshReport::new(&mesh);
+    assert_eq!((report.unique_positions, report.boundary_edges), (4, 4));
+    assert_eq!(mesh.merge_close_vertices(1e-4), 0);
+}
+
+#[test]
+fn flipping_reverses_every_triangle() {
+    let mut mesh = load_obj(&format!("{}{}", CUBE_CORNERS, OPEN_BOX)).unwrap();
+    let before = mesh.ib.get_indices();
+    mesh.flip_winding();
+    let after = mesh.ib.get_indices();
+    for (old, new) in before.chunks(3).zip(after.chunks(3)) {
+        assert_eq!(new, [old[0], old[2], old[1]]);
+    }
+}
+
+#[test]
+fn orienting_flips_exactly_the_inverted_faces() {
+    let closed = format!("{}{}f 5 6 7 8\n", CUBE_CORNERS, OPEN_BOX);
+    // the front and the right side written the wrong way round, two triangles each
+    let inverted = closed.replace("f 1 2 6 5", "f 5 6 2 1").replace("f 2 3 7 6", "f 6 7 3 2");
+    let mut mesh = load_obj(&inverted).unwrap();
+    let facing_out = |mesh: &Mesh, t: usize| {
+        let triangle = mesh.get_triangle(t);
+        let center = (triangle.a + triangle.b + triangle.c) / 3. - Vector3::new(0.5, 0.5, 0.5);
+        triangle.normal().dot(&center) > 0.
+    };
+    let wrong: Vec<usize> = (0..12).filter(|&t| !facing_out(&mesh, t)).collect();
+    assert_eq!(wrong, vec![2, 3, 8, 9]);
+
+    let before = mesh.ib.get_indices();
+    assert_eq!(mesh.orient_winding(), 4);
+    let after = mesh.ib.get_indices();
+    for t in 0..12 {
+        let (old, new) = (&before[3 * t..3 * t + 3], &after[3 * t..3 * t + 3]);
+        if wrong.contains(&t) {
+            assert_eq!(new, [old[0], old[2], old[1]]);
+        } else {
+            assert_eq!(new, old);
+        }
+        assert!(facing_out(&mesh, t));
+    }
+    assert_eq!(mesh.orient_winding(), 0);
+
+    // a cube turned inside out as a whole is consistent, but faces inwards
+    mesh.flip_winding();
+    assert_eq!(mesh.orient_winding(), 12);
+    assert!((0..12).all(|t| facing_out(&mesh, t)));
+}
+
+#[test]
+fn fills_small_holes_facing_outwards() {
+    let mut mesh = load_obj(&format!("{}{}", CUBE_CORNERS, OPEN_BOX)).unwrap();
+    assert_eq!(MeshReport::new(&mesh).open_boundaries, 1);
+
+    assert_eq!(mesh.fill_holes(3), 0);
+    assert_eq!(mesh.fill_holes(4), 1);
+    assert_eq!(mesh.get_triangle_count(), 12);
+    assert_eq!(mesh.submeshes.last().map(|s| s.triangle_count), Some(12));
+    assert!(MeshReport::new(&mesh).is_watertight());
+    // the lid agrees with the sides, which already face outwards
+    assert_eq!(mesh.orient_winding(), 0);
+    for t in 10..12 {
+        let normal = mesh.get_triangle(t).normal();
+        assert!(normal.approx_eq(&Vector3::new(0., 0., 1.), 1e-6), "{:?}", normal);
+        let vertex = mesh.vb.get_vertex(mesh.ib.get_index(3 * t) as usize);
+        assert!(vertex.get_normal().approx_eq(&Vector3::new(0., 0., 1.), 1e-6));
+    }
+}
+
+#[test]
+fn reports_non_manifold_edges_and_vertices() {
+    let fin = load_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 -1 0\nv 0 0 1\nf 1 2 3\nf 2 1 4\nf 1 2 5\n").unwrap();
+    assert_eq!(fin.get_non_manifold_edges(), vec![[Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.)]]);
+    assert!(fin.get_non_manifold_vertices().is_empty());
+
+    // two triangles touching only at a corner
+    let bowtie = load_obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nv -1 0 0\nv -1 -1 0\nf 1 2 3\nf 1 4 5\n").unwrap();
+    assert_eq!(bowtie.get_non_manifold_vertices(), vec![Vector3::new(0., 0., 0.)]);
+    assert!(bowtie.get_non_manifold_edges().is_empty());
+    assert_eq!(MeshReport::new(&bowtie).non_manifold_vertices, 1);
+
+    let mut model = Model::default();
+    model.load_obj("assets/cube.obj");
+    assert!(model.get_mesh().get_non_manifold_vertices().is_empty());
+}
+
+#[test]
+fn repairs_in_one_go() {
+    // the open box with a face turned inside out, a repeated face and a sliver
+    let obj = format!("{}{}f 5 6 7\nf 5 6 7\nf 1 2 2\n", CUBE_CORNERS, OPEN_BOX.replace("f 2 3 7 6", "f 6 7 3 2"));
+    let mut mesh = load_obj(&obj).unwrap();
+    assert_eq!(load_obj(&obj).unwrap().repair(&RepairOptions::default()), RepairReport {
+        degenerate_triangles: 1,
+        duplicate_triangles: 1,
+        unreferenced_vertices: 6,
+        ..RepairReport::default()
+    });
+
+    let options = RepairOptions { winding: true, fill_holes: Some(4), ..RepairOptions::default() };
+    let report = mesh.repair(&options);
+    assert_eq!((report.degenerate_triangles, report.duplicate_triangles, report.flipped_triangles, report.filled_holes), (1, 1, 2, 1));
+    let after = MeshReport::new(&mesh);
+    assert!(after.is_watertight());
+    assert_eq!(after.triangles, 12);
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use triangle::model::Model;
+use triangle::quaternion::DQuaternion;
+use triangle::scene::Scene;
+use triangle::transform::DTransform;
+use triangle::vector::Vector3;
+use common::temp_path;
+
+fn at(x: f64, y: f64, z: f64) -> DTransform {
+    let mut transform = DTransform::default();
+    transform.set_position(Vector3::new(x, y, z));
+    transform
+}
+
+fn position(scene: &Scene, id: triangle::scene::NodeId) -> Vector3<f64> {
+    scene.get_world_matrix(id).transform_point(Vector3::default())
+}
+
+#[test]
+fn children_follow_their_parents() {
+    let mut scene = Scene::new();
+    let assembly = scene.add_node("assembly", None, at(10., 0., 0.));
+    let part = scene.add_node("part", Some(assembly), at(0., 1., 0.));
+    let bolt = scene.add_node("bolt", Some(part), at(0., 0., 2.));
+    scene.update();
+    assert!(position(&scene, bolt).approx_eq(&Vector3::new(10., 1., 2.), 1e-12));
+
+    // a quarter turn of the assembly swings the bolt around it
+    scene.local_transform_mut(assembly).set_rotation(DQuaternion::from_angle_axis(std::f64::consts::FRAC_PI_2, Vector3::new(0., 1., 0.)));
+    scene.update();
+    assert!(position(&scene, part).approx_eq(&Vector3::new(10., 1., 0.), 1e-12));
+    assert!(position(&scene, bolt).approx_eq(&Vector3::new(12., 1., 0.), 1e-12));
+
+    scene.local_transform_mut(assembly).set_scale(Vector3::new(2., 2., 2.));
+    scene.update();
+    assert!(position(&scene, bolt).approx_eq(&Vector3::new(14., 2., 0.), 1e-12));
+}
+
+#[test]
+fn world_transforms_are_cached_until_update() {
+    let mut scene = Scene::new();
+    let parent = scene.add_node("parent", None, at(1., 0., 0.));
+    let child = scene.add_node("child", Some(parent), at(1., 0., 0.));
+    let sibling = scene.add_node("sibling", Some(parent), at(0., 1., 0.));
+    scene.update();
+
+    scene.set_local_transform(child, at(5., 0., 0.));
+    assert!(position(&scene, child).approx_eq(&Vector3::new(2., 0., 0.), 1e-12));
+    scene.update();
+    assert!(position(&scene, child).approx_eq(&Vector3::new(6., 0., 0.), 1e-12));
+    assert!(position(&scene, sibling).approx_eq(&Vector3::new(1., 1., 0.), 1e-12));
+    assert_eq!(scene.get_world_transform(child).get_position(), position(&scene, child));
+}
+
+#[test]
+fn models_are_placed_by_their_nodes() {
+    let mut scene = Scene::new();
+    let assembly = scene.add_node("assembly", None, at(0., 0., -3.));
+    let mut model = Model::default();
+    model.transform = at(1., 0., 0.);
+    let node = scene.add_model("cube", Some(assembly), model);
+    scene.update();
+
+    let index = scene.get_model_index(node).unwrap();
+    assert_eq!(scene.get_model_node(index), Some(node));
+    assert!(scene.get_models()[index].transform.get_position().approx_eq(&Vector3::new(1., 0., -3.), 1e-12));
+
+    scene.local_transform_mut(assembly).set_position(Vector3::new(0., 5., 0.));
+    scene.update();
+    assert!(scene.get_model(node).unwrap().transform.get_position().approx_eq(&Vector3::new(1., 5., 0.), 1e-12));
+}
+
+#[test]
+fn reparenting_keeps_the_local_transform() {
+    let mut scene = Scene::new();
+    let a = scene.add_node("a", None, at(1., 0., 0.));
+    let b = scene.add_node("b", None, at(0., 10., 0.));
+    let child = scene.add_node("child", Some(a), at(0., 0., 1.));
+    scene.update();
+
+    assert!(scene.set_parent(child, Some(b)));
+    scene.update();
+    assert_eq!(scene.get_parent(child), Some(b));
+    assert!(scene.get_children(a).is_empty());
+    assert_eq!(scene.get_children(b), &[child]);
+    assert!(position(&scene, child).approx_eq(&Vector3::new(0., 10., 1.), 1e-12));
+
+    assert!(scene.set_parent(child, None));
+    assert_eq!(scene.get_roots(), &[a, b, child]);
+}
+
+#[test]
+fn reparenting_rejects_cycles() {
+    let mut scene = Scene::new();
+    let a = scene.add_node("a", None, DTransform::default());
+    let b = scene.add_node("b", Some(a), DTransform::default());
+    let c = scene.add_node("c", Some(b), DTransform::default());
+    assert!(!scene.set_parent(a, Some(c)));
+    assert!(!scene.set_parent(a, Some(a)));
+    assert_eq!(scene.get_parent(a), None);
+    assert!(scene.is_ancestor(a, c));
+    assert!(!scene.is_ancestor(c, a));
+}
+
+#[test]
+fn obj_groups_become_child_nodes() {
+    let cube = std::fs::read_to_string("assets/cube.obj").unwrap();
+    // split the cube's faces into two named objects
+    let mut faces = 0;
+    let grouped: Vec<String> = cube.lines().filter(|l| !l.starts_with("o ")).flat_map(|l| {
+        let mut lines = Vec::new();
+        if l.starts_with("f ") {
+            if faces == 0 {
+                lines.push("o top".to_string());
+            } else if faces == 6 {
+                lines.push("g bottom".to_string());
+            }
+            faces += 1;
+        }
+        lines.push(l.to_string());
+        lines
+    }).collect();
+    let path = temp_path("obj");
+    std::fs::write(&path, grouped.join("\n")).unwrap();
+
+    let mut scene = Scene::new();
+    let root = scene.import_obj(path.to_str().unwrap(), None).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // the file's node is named after it
+    assert_eq!(scene.get_name(root), path.file_stem().unwrap().to_str().unwrap());
+    let children = scene.get_children(root);
+    assert_eq!(children.len(), 2);
+    assert_eq!(scene.get_name(children[0]), "top");
+    assert_eq!(scene.get_name(children[1]), "bottom");
+    assert_eq!(scene.find("bottom"), Some(children[1]));
+    assert_eq!(scene.get_models().len(), 2);
+    assert!(!scene.get_model(children[0]).unwrap().get_bounds().is_empty());
+}
+
+#[test]
+fn importing_a_missing_or_broken_obj_fails_and_adds_nothing() {
+    let mut scene = Scene::new();
+    assert!(scene.import_obj("assets/missing.obj", None).is_err());
+
+    let path = temp_path("obj");
+    std::fs::write(&path, "v 0 0 0\nf 1 2 3\n").unwrap();
+    let result = scene.import_obj(path.to_str().unwrap(), None);
+    std::fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+    assert!(scene.get_roots().is_empty());
+    assert!(scene.get_models().is_empty());
+}
@@ -0,0 +1,137 @@
+use std::fs;
+use triangle::camera::{Camera, Projection};
+use triangle::color::Color;
+use triangle::mesh_file::{Handedness, ImportOptions, UpAxis};
+use triangle::scene_file::{CameraDescription, NodeDescription, SceneFile};
+use triangle::shader::{Light, Material};
+use triangle::vector::Vector3;
+
+fn cube(name: &str) -> NodeDescription {
+    NodeDescription { name: name.into(), model: Some("cube.obj".into()), ..NodeDescription::default() }
+}
+
+#[test]
+fn missing_fields_take_defaults() {
+    let file = SceneFile::from_ron("(nodes: [(name: \"cube\", model: Some(\"cube.obj\"))])").unwrap();
+    assert_eq!(file.camera, CameraDescription::default());
+    assert_eq!(file.lights, vec![Light::default()]);
+    assert_eq!(file.background, Color::new(0., 0., 0., 1.));
+    assert_eq!(file.nodes, vec![cube("cube")]);
+    assert_eq!(file.nodes[0].scale, Vector3::new(1., 1., 1.));
+
+    assert_eq!(SceneFile::from_json("{}").unwrap(), SceneFile::default());
+    assert!(SceneFile::from_ron("(nodes: 3)").is_err());
+    assert!(SceneFile::from_json("{\"camera\": {\"fov\": \"wide\"}}").is_err());
+}
+
+#[test]
+fn round_trips_through_ron_and_json() {
+    let mut parent = NodeDescription { name: "parent".into(), position: Vector3::new(1., 2., 3.), ..NodeDescription::default() };
+    parent.children.push(NodeDescription { material: Some("green".into()), rotation: Vector3::new(0., 90., 0.), ..cube("child") });
+    let file = SceneFile {
+        background: Color::new(0.1, 0.2, 0.3, 1.),
+        camera: CameraDescription { projection: Projection::Orthographic, ..CameraDescription::default() },
+        lights: vec![Light::Directional { direction: Vector3::new(0., -1., 0.) }],
+        materials: vec![Material { name: "green".into(), diffuse: Color::new(0., 1., 0., 1.), ..Material::default() }],
+        nodes: vec![parent],
+    };
+    assert_eq!(SceneFile::from_ron(&file.to_ron().unwrap()).unwrap(), file);
+    assert_eq!(SceneFile::from_json(&file.to_json().unwrap()).unwrap(), file);
+
+    let path = std::env::temp_dir().join(format!("triangle-scene-{}.json", std::process::id()));
+    file.save(&path).unwrap();
+    assert_eq!(SceneFile::load(&path).unwrap(), file);
+    fs::remove_file(&path).unwrap();
+    assert!(file.save(path.with_extension("txt")).is_err());
+}
+
+#[test]
+fn instantiates_the_scene_graph() {
+    let mut parent = NodeDescription { name: "parent".into(), position: Vector3::new(10., 0., 0.), ..NodeDescription::default() };
+    parent.children.push(NodeDescription { material: Some("green".into()), position: Vector3::new(0., 1., 0.), ..cube("child") });
+    let file = SceneFile {
+        materials: vec![Material { name: "green".into(), diffuse: Color::new(0., 1., 0., 1.), ..Material::default() }],
+        nodes: vec![parent, cube("other")],
+        ..SceneFile::default()
+    };
+    let scene = file.instantiate("assets").unwrap();
+
+    assert_eq!(scene.get_node_count(), 3);
+    assert_eq!(scene.get_roots().len(), 2);
+    let child = scene.find("child").unwrap();
+    assert_eq!(scene.get_parent(child), scene.find("parent"));
+    let model = scene.get_model(child).unwrap();
+    assert_eq!(model.material, file.materials[0]);
+    assert_eq!(model.get_path(), Some("assets/cube.obj"));
+    assert!(model.transform.get_position().approx_eq(&Vector3::new(10., 1., 0.), 1e-9));
+    assert_eq!(scene.get_model(scene.find("other").unwrap()).unwrap().material, Material::default());
+}
+
+#[test]
+fn instantiate_rejects_missing_models_and_materials() {
+    let missing = SceneFile { nodes: vec![cube("cube")], ..SceneFile::default() };
+    assert!(missing.instantiate("no/such/dir").is_err());
+    let unknown = SceneFile { nodes: vec![NodeDescription { material: Some("nope".into()), ..cube("cube") }], ..SceneFile::default() };
+    assert!(unknown.instantiate("assets").is_err());
+}
+
+#[test]
+fn captures_the_current_state() {
+    let mut node = NodeDescription { material: Some("green".into()), position: Vector3::new(1., 2., 3.), ..cube("cube") };
+    node.rotation = Vector3::new(10., 20., 30.);
+    node.scale = Vector3::new(2., 2., 2.);
+    let file = SceneFile {
+        background: Color::new(0.5, 0.5, 0.5, 1.),
+        materials: vec![Material { name: "green".into(), diffuse: Color::new(0., 1., 0., 1.), ..Material::default() }],
+        nodes: vec![node.clone(), cube("plain")],
+        ..SceneFile::default()
+    };
+    let scene = file.instantiate("assets").unwrap();
+    let mut camera = Camera::new(1., 1.5, 0.1, 100.);
+    file.camera.apply(&mut camera);
+
+    let captured = SceneFile::capture(&scene, &camera, &file.lights, file.background, "assets");
+    assert_eq!(captured.background, file.background);
+    assert_eq!(captured.lights, file.lights);
+    assert_eq!(captured.materials, file.materials);
+    assert_eq!(captured.nodes.len(), 2);
+    let saved = &captured.nodes[0];
+    assert_eq!(saved.model.as_deref(), Some("cube.obj"));
+    assert_eq!(saved.material.as_deref(), Some("green"));
+    assert!(saved.position.approx_eq(&node.position, 1e-9));
+    assert!(saved.rotation.approx_eq(&node.rotation, 1e-6));
+    assert!(saved.scale.approx_eq(&node.scale, 1e-9));
+    assert_eq!(captured.nodes[1].material, None);
+
+    assert!(captured.camera.position.approx_eq(&file.camera.position, 1e-6));
+    assert!(captured.camera.target.approx_eq(&file.camera.target, 1e-5));
+    assert!((captured.camera.fov - file.camera.fov).abs() < 1e-4);
+}
+
+#[test]
+fn import_options_are_applied_and_kept() {
+    let import = ImportOptions { up_axis: UpAxis::Z, handedness: Handedness::Left, unit_scale: 0.5 };
+    let file = SceneFile { nodes: vec![NodeDescription { import, ..cube("imported") }, cube("plain")], ..SceneFile::default() };
+    // only nodes that need them write them out
+    let ron = file.to_ron().unwrap();
+    assert_eq!(ron.matches("import:").count(), 1);
+    assert_eq!(SceneFile::from_ron(&ron).unwrap(), file);
+
+    let scene = file.instantiate("assets").unwrap();
+    let models = scene.get_models();
+    assert_eq!(models[0].get_import_options(), import);
+    assert_eq!(models[1].get_import_options(), ImportOptions::default());
+    let (imported, plain) = (models[0].get_bounds(), models[1].get_bounds());
+    assert!(imported.size().approx_eq(&(plain.size() * 0.5), 1e-6));
+
+    let captured = SceneFile::capture(&scene, &Camera::new(1., 1.5, 0.1, 100.), &file.lights, file.background, "assets");
+    assert_eq!(captured.nodes[0].import, import);
+    assert!(captured.nodes[1].import.is_default());
+}
+
+#[test]
+fn example_scene_loads() {
+    let file = SceneFile::load("assets/scene.ron").unwrap();
+    let scene = file.instantiate("assets").unwrap();
+    assert_eq!(scene.get_models().len(), 2);
+}
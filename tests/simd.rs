@@ -0,0 +1,65 @@
+mod common;
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use triangle::matrix::Matrix4;
+use triangle::quaternion::Quaternion;
+use triangle::simd::{self, scalar, Rows};
+use triangle::vector::Vector4;
+use common::*;
+
+fn rows(m: &Matrix4) -> Rows<f32> {
+    let mut rows = [[0.; 4]; 4];
+    for (r, row) in rows.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = m[(r, c)];
+        }
+    }
+    rows
+}
+
+fn random_components(rng: &mut StdRng) -> [f32; 4] {
+    [0; 4].map(|_| rng.random_range(-2.0..2.0))
+}
+
+/// SIMD kernels add in another order, or fused, so the last bits may differ.
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() <= 1e-5 * e.abs().max(1.), "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn kernels_agree_with_the_scalar_ones() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let (a, b) = (rows(&random_matrix(&mut rng)), rows(&random_matrix(&mut rng)));
+        assert_close(simd::mat4_mul(&a, &b).as_flattened(), scalar::mat4_mul(&a, &b).as_flattened());
+
+        let v = random_components(&mut rng);
+        assert_close(&simd::vec4_mul_mat4(v, &a), &scalar::vec4_mul_mat4(v, &a));
+
+        let (p, q) = (random_components(&mut rng), random_components(&mut rng));
+        assert_close(&simd::quat_mul(p, q), &scalar::quat_mul(p, q));
+    }
+}
+
+#[test]
+fn math_types_agree_with_the_scalar_kernels() {
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let (a, b) = (random_matrix(&mut rng), random_matrix(&mut rng));
+        assert_close(rows(&(a * b)).as_flattened(), scalar::mat4_mul(&rows(&a), &rows(&b)).as_flattened());
+
+        let [x, y, z, w] = random_components(&mut rng);
+        let v = Vector4::new(x, y, z, w) * a;
+        assert_close(&[v.x, v.y, v.z, v.w], &scalar::vec4_mul_mat4([x, y, z, w], &rows(&a)));
+
+        // Quaternion does not give its components back, so compare the rotations they make
+        let (p, q) = (random_components(&mut rng), random_components(&mut rng));
+        let quaternion = |[w, i, j, k]: [f32; 4]| Quaternion::new(w, i, j, k);
+        let product = quaternion(p) * quaternion(q);
+        let expected = quaternion(scalar::quat_mul(p, q));
+        assert_close(rows(&product.as_mat4()).as_flattened(), rows(&expected.as_mat4()).as_flattened());
+    }
+}
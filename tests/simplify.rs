@@ -0,0 +1,139 @@
+use triangle::buffers::{IndexBuffer, Vertex, VertexBuffer};
+use triangle::camera::Camera;
+use triangle::half_edge::HalfEdgeMesh;
+use triangle::inspect::MeshReport;
+use triangle::mesh::Mesh;
+use triangle::mesh_file::load_mesh;
+use triangle::model::Model;
+use triangle::renderer::Draw;
+use triangle::shader::Light;
+use triangle::simplify::{LodOptions, SimplifyOptions};
+use triangle::vector::Vector3;
+
+/// A flat unit square of `n` by `n` cells facing +z, textured in two halves: the left with
+/// u = x, the right with u = x + 10, so the middle column is a seam.
+fn grid(n: usize) -> Mesh {
+    let mut vb = VertexBuffer::new();
+    let mut ib = IndexBuffer::new();
+    for half in 0..2 {
+        let columns = (half * n / 2)..=((half + 1) * n / 2);
+        let first = vb.get_vertex_count() as u32;
+        let width = columns.clone().count() as u32;
+        for j in 0..=n {
+            for i in columns.clone() {
+                let (x, y) = (i as f32 / n as f32, j as f32 / n as f32);
+                vb.add_vertex(Vertex::new([x, y, 0.], [0., 0., 1.], [x + 10. * half as f32, y], [1., 1., 1., 1.]));
+            }
+        }
+        for j in 0..n as u32 {
+            for i in 0..width - 1 {
+                let corner = first + j * width + i;
+                for index in [corner, corner + 1, corner + width + 1, corner, corner + width + 1, corner + width] {
+                    ib.add_index(index);
+                }
+            }
+        }
+    }
+    let mut mesh = Mesh::new(vb, ib);
+    mesh.attributes.normals = true;
+    mesh.attributes.uvs = true;
+    mesh
+}
+
+#[test]
+fn flattens_a_plane_keeping_its_border_and_seam() {
+    let mesh = grid(16);
+    assert_eq!(mesh.get_triangle_count(), 512);
+    // whatever costs nothing: only the border and the seam hold vertices back
+    let (simplified, error) = mesh.simplify(&SimplifyOptions { target_triangles: None, max_error: Some(1e-5) });
+    assert!(error < 1e-5, "{}", error);
+    assert!(simplified.get_triangle_count() < 100, "{}", simplified.get_triangle_count());
+
+    // the same square, still one piece with one border
+    let report = MeshReport::new(&simplified);
+    assert_eq!(report.bounds.unwrap().size, Vector3::new(1., 1., 0.));
+    assert_eq!(report.open_boundaries, 1);
+    assert_eq!(report.degenerate_triangles, 0);
+    let area: f32 = (0..simplified.get_triangle_count()).map(|t| simplified.get_triangle(t).area()).sum();
+    assert!((area - 1.).abs() < 1e-5, "{}", area);
+    for t in 0..simplified.get_triangle_count() {
+        assert!(simplified.get_triangle(t).normal().z > 0.);
+    }
+    // the texture is not torn or stretched
+    for i in 0..simplified.vb.get_vertex_count() {
+        let vertex = simplified.vb.get_vertex(i);
+        let (p, [u, v]) = (vertex.get_position(), vertex.get_uv());
+        assert!((u - p.x).abs() < 1e-5 || (u - p.x - 10.).abs() < 1e-5, "{:?} at {:?}", [u, v], p);
+        assert!((v - p.y).abs() < 1e-5);
+        assert_eq!(vertex.get_normal(), Vector3::new(0., 0., 1.));
+    }
+    let seam = HalfEdgeMesh::new(&simplified);
+    assert!(seam.get_vertices().filter(|&v| seam.is_seam_vertex(v)).all(|v| seam.get_position(v).x == 0.5));
+    assert_eq!(seam.get_vertices().filter(|&v| seam.is_seam_vertex(v)).count(), 17);
+}
+
+#[test]
+fn stops_at_the_target_or_the_error() {
+    let pawn = load_mesh("assets/pawn.obj").unwrap();
+    let triangles = pawn.get_triangle_count();
+
+    let (half, error) = pawn.simplify(&SimplifyOptions { target_triangles: Some(triangles / 2), max_error: None });
+    assert!(half.get_triangle_count() <= triangles / 2 && half.get_triangle_count() >= triangles / 2 - 1);
+    assert!(error > 0.);
+    let report = MeshReport::new(&half);
+    assert!(report.is_watertight());
+    let names = |report: MeshReport| report.groups.into_iter().map(|g| g.name).collect::<Vec<_>>();
+    assert_eq!(names(report), names(MeshReport::new(&pawn)));
+
+    let (tight, tight_error) = pawn.simplify(&SimplifyOptions { target_triangles: None, max_error: Some(error / 4.) });
+    assert!(tight_error <= error / 4.);
+    assert!(tight.get_triangle_count() > half.get_triangle_count() && tight.get_triangle_count() < triangles);
+
+    let (same, no_error) = pawn.simplify(&SimplifyOptions { target_triangles: Some(triangles), max_error: None });
+    assert_eq!((same.get_triangle_count(), no_error), (triangles, 0.));
+}
+
+#[test]
+fn makes_levels_of_detail() {
+    let pawn = load_mesh("assets/pawn.obj").unwrap();
+    let lods = pawn.generate_lods(&LodOptions { levels: 2, ratio: 0.5, tolerance: 0.01 });
+    assert_eq!(lods.len(), 2);
+    assert!(lods[0].mesh.get_triangle_count() <= pawn.get_triangle_count() / 2);
+    assert!(lods[1].mesh.get_triangle_count() < lods[0].mesh.get_triangle_count());
+    assert!(lods[0].error <= lods[1].error);
+    assert_eq!(lods[0].distance, lods[0].error / 0.01);
+    assert!(lods[0].distance <= lods[1].distance);
+
+    // a plane loses nothing at first, so its first level is drawn from close up
+    let flat = grid(8).generate_lods(&LodOptions::default());
+    assert_eq!((flat[0].error, flat[0].distance), (0., 0.));
+}
+
+#[test]
+fn draws_coarser_levels_further_away() {
+    let mut model = Model::default();
+    model.try_load("assets/pawn.obj").unwrap();
+    let full = model.get_mesh().get_triangle_count();
+    assert_eq!(model.get_lod_mesh(1e9).get_triangle_count(), full);
+
+    model.generate_lods(&LodOptions { levels: 2, ratio: 0.5, tolerance: 0.01 });
+    let [near, far] = [0, 1].map(|i| model.get_lods()[i].distance as f64);
+    let [first, second] = [0, 1].map(|i| model.get_lods()[i].mesh.get_triangle_count());
+    let triangles = |model: &Model, distance: f64| model.get_lod_mesh(distance).get_triangle_count();
+    assert_eq!(triangles(&model, 0.), if near == 0. { first } else { full });
+    assert_eq!(triangles(&model, (near + far) / 2.), first);
+    assert_eq!(triangles(&model, far * 2.), second);
+    // twice the size, twice as far before switching
+    model.transform.set_scale(Vector3::new(2., 2., 2.));
+    assert_eq!(triangles(&model, far * 1.5), first);
+
+    let mut camera = Camera::new(1., 1., 0.1, 1e6);
+    camera.transform.set_position(Vector3::new(0., 0., far * 4.));
+    assert!(model.get_camera_distance(&camera) > far * 2.);
+    let draw = Draw::new(&model, &camera, Light::default());
+    assert_eq!(draw.mesh.get_triangle_count(), second);
+
+    // loading another mesh drops the levels made for the old one
+    model.try_load("assets/cube.obj").unwrap();
+    assert!(model.get_lods().is_empty());
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use std::f32::consts::{FRAC_1_SQRT_2, PI, SQRT_2};
+use triangle::camera::Camera;
+use triangle::matrix::Matrix4;
+use triangle::quaternion::Quaternion;
+use triangle::transform::Transform;
+use triangle::vector::Vector3;
+use common::*;
+
+fn camera_at(eye: Vector3<f32>, target: Vector3<f32>, up: Vector3<f32>) -> Camera {
+    let mut camera = Camera::new(PI / 2., 1., 0.1, 100.);
+    camera.transform.set_position(eye.cast());
+    camera.transform.look_at(target.cast(), up.cast());
+    camera
+}
+
+// references are gluLookAt, transposed because vectors multiply matrices from the left here
+#[test]
+fn view_matrix_matches_glu_look_at() {
+    let camera = camera_at(Vector3::new(1., 2., 3.), Vector3::new(0., 0., 0.), Vector3::up());
+    assert_matrix_eq(camera.view_matrix(), from_rows([
+        [0.9486833, -0.1690309, 0.2672612, 0.],
+        [0., 0.8451543, 0.5345225, 0.],
+        [-0.3162278, -0.5070926, 0.8017837, 0.],
+        [0., 0., -3.7416574, 1.],
+    ]));
+}
+
+#[test]
+fn view_matrix_matches_glu_look_at_with_z_up() {
+    let camera = camera_at(Vector3::new(4., -2., 1.), Vector3::new(1., 1., 1.), Vector3::new(0., 0., 1.));
+    assert_matrix_eq(camera.view_matrix(), from_rows([
+        [FRAC_1_SQRT_2, 0., FRAC_1_SQRT_2, 0.],
+        [FRAC_1_SQRT_2, 0., -FRAC_1_SQRT_2, 0.],
+        [0., 1., 0., 0.],
+        [-SQRT_2, -1., -3. * SQRT_2, 1.],
+    ]));
+}
+
+#[test]
+fn view_matrix_of_unrotated_camera_is_a_translation() {
+    let mut camera = Camera::new(PI / 2., 1., 0.1, 100.);
+    camera.transform.set_position(Vector3::new(0., 0., 5.));
+    assert_matrix_eq(camera.view_matrix(), Matrix4::translate(Vector3::new(0., 0., -5.)));
+}
+
+#[test]
+fn inverse_matrix_undoes_as_matrix() {
+    let mut transform = Transform::default();
+    transform.set_position(Vector3::new(1., -2., 3.));
+    transform.set_rotation(Quaternion::from_angle_axis(0.7, Vector3::new(1., 2., -1.)));
+    transform.set_scale(Vector3::new(2., 2., 2.));
+    assert_matrix_eq(transform.as_matrix() * transform.inverse_matrix(), Matrix4::identity());
+    assert_matrix_eq(transform.inverse_matrix() * transform.as_matrix(), Matrix4::identity());
+}
+
+#[test]
+fn look_at_points_minus_z_at_target() {
+    let mut transform = Transform::default();
+    transform.set_position(Vector3::new(3., 1., -2.));
+    transform.set_scale(Vector3::new(1., 2., 3.));
+    let target = Vector3::new(-1., 4., 2.);
+    transform.look_at(target, Vector3::up());
+
+    let mut expected = target - transform.get_position();
+    expected.normalize();
+    assert_vector_eq(transform.get_rotation().rotate(Vector3::backward()), expected);
+    assert_vector_eq(transform.get_position(), Vector3::new(3., 1., -2.));
+    assert_vector_eq(transform.get_scale(), Vector3::new(1., 2., 3.));
+}
+
+#[test]
+fn look_at_along_up_still_gives_a_rotation() {
+    let mut transform = Transform::default();
+    transform.look_at(Vector3::new(0., -5., 0.), Vector3::up());
+    let rotation = transform.get_rotation();
+    assert!((rotation.norm() - 1.).abs() < EPSILON);
+    assert_vector_eq(rotation.rotate(Vector3::backward()), Vector3::down());
+}
+
+#[test]
+fn look_at_own_position_keeps_the_rotation() {
+    let mut transform = Transform::default();
+    transform.set_position(Vector3::new(1., 2., 3.));
+    transform.look_at(Vector3::new(0., 0., 0.), Vector3::up());
+    let forward = transform.get_rotation().rotate(Vector3::backward());
+    transform.look_at(Vector3::new(1., 2., 3.), Vector3::up());
+    assert_eq!(transform.get_rotation().rotate(Vector3::backward()), forward);
+}
+
+#[test]
+fn from_rotation_matrix_inverts_as_mat4() {
+    // covers every branch of the conversion, including half turns where w is zero
+    let rotations = [
+        Quaternion::default(),
+        Quaternion::from_angle_axis(0.5, Vector3::new(1., 1., 0.)),
+        Quaternion::from_angle_axis(PI, Vector3::right()),
+        Quaternion::from_angle_axis(PI, Vector3::up()),
+        Quaternion::from_angle_axis(PI, Vector3::forward()),
+        Quaternion::from_angle_axis(2.5, Vector3::new(-1., 3., 2.)),
+    ];
+    for q in rotations {
+        let m = q.as_mat4();
+        assert_matrix_eq(Quaternion::from_rotation_matrix(m).as_mat4(), m);
+    }
+}
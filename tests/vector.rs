@@ -0,0 +1,159 @@
+mod common;
+
+use triangle::matrix::Matrix4;
+use triangle::quaternion::Quaternion;
+use triangle::vector::{Vector2, Vector3, Vector4};
+use common::*;
+
+#[test]
+fn operators_work_for_integers() {
+    let a = Vector3::new(1, -2, 3);
+    let b = Vector3::new(4, 5, -6);
+    assert_eq!(a + b, Vector3::new(5, 3, -3));
+    assert_eq!(a - b, Vector3::new(-3, -7, 9));
+    assert_eq!(a * b, Vector3::new(4, -10, -18));
+    assert_eq!(a * 2, Vector3::new(2, -4, 6));
+    assert_eq!(-a, Vector3::new(-1, 2, -3));
+    assert_eq!(a.dot(&b), -24);
+    assert_eq!(a.cross(&b), Vector3::new(-3, 18, 13));
+
+    let mut c = a;
+    c += b;
+    c -= Vector3::splat(1);
+    c *= 3;
+    c /= Vector3::new(1, 3, -1);
+    assert_eq!(c, Vector3::new(12, 2, 12));
+}
+
+#[test]
+fn float_methods_work_for_f64() {
+    let mut v = Vector3::new(3f64, 0., 4.);
+    assert_eq!(v.length(), 5.);
+    v.normalize();
+    assert!(v.approx_eq(&Vector3::new(0.6, 0., 0.8), 1e-12));
+    assert_eq!(Vector3::new(0f64, 0., 0.).normalized(), Vector3::new(0., 0., 0.));
+    let far = Vector3::new(1_000_000.25f64, 2_000_000.5, 0.);
+    assert_eq!(far.distance(&Vector3::new(1_000_000f64, 2_000_000., 0.)), (0.25f64 * 0.25 + 0.5 * 0.5).sqrt());
+}
+
+#[test]
+fn index_and_conversions() {
+    let mut v = Vector4::new(1., 2., 3., 4.);
+    assert_eq!((v[0], v[1], v[2], v[3]), (1., 2., 3., 4.));
+    v[2] = 7.;
+    assert_eq!(v.z, 7.);
+    assert_eq!(<[f32; 4]>::from(v), [1., 2., 7., 4.]);
+    assert_eq!(Vector2::from([5, 6]), Vector2::new(5, 6));
+    assert_eq!(Vector3::new(1, 2, 3).map(|c| c as f32 / 2.), Vector3::new(0.5, 1., 1.5));
+}
+
+#[test]
+#[should_panic]
+fn index_out_of_bounds_panics() {
+    let v = Vector2::new(1., 2.);
+    let _ = v[2];
+}
+
+#[test]
+fn swizzles() {
+    let v = Vector3::new(1, 2, 3);
+    assert_eq!(v.xz(), Vector2::new(1, 3));
+    assert_eq!(v.zy(), Vector2::new(3, 2));
+    assert_eq!(v.yzx(), Vector3::new(2, 3, 1));
+    assert_eq!(v.zyx(), Vector3::new(3, 2, 1));
+    assert_eq!(v.extend(4).xyz(), v);
+    assert_eq!(v.xy().extend(9), Vector3::new(1, 2, 9));
+    assert_eq!(Vector2::new(1, 2).yx(), Vector2::new(2, 1));
+}
+
+#[test]
+fn lerp_min_max_clamp() {
+    let a = Vector3::new(0., 10., -4.);
+    let b = Vector3::new(2., 0., 4.);
+    assert_eq!(a.lerp(b, 0.), a);
+    assert_eq!(a.lerp(b, 1.), b);
+    assert_eq!(a.lerp(b, 0.25), Vector3::new(0.5, 7.5, -2.));
+    assert_eq!(a.min(b), Vector3::new(0., 0., -4.));
+    assert_eq!(a.max(b), Vector3::new(2., 10., 4.));
+    assert_eq!(Vector3::new(5, -5, 1).clamp(Vector3::splat(0), Vector3::splat(2)), Vector3::new(2, 0, 1));
+}
+
+#[test]
+fn reflect_mirrors_on_the_plane() {
+    let d = Vector3::new(1., -1., 0.);
+    assert_eq!(d.reflect(Vector3::up()), Vector3::new(1., 1., 0.));
+    // reflecting twice gives the direction back
+    let n = Vector3::new(1., 2., 2.) / 3.;
+    assert!(d.reflect(n).reflect(n).approx_eq(&d, 1e-6));
+    assert!((d.reflect(n).length() - d.length()).abs() < 1e-6);
+}
+
+#[test]
+fn refract_follows_snell() {
+    let eta = 1. / 1.5;
+    let mut incoming = Vector3::new(1., -1., 0.);
+    incoming.normalize();
+    let out = incoming.refract(Vector3::up(), eta).unwrap();
+    // sin of the angles to the normal scale with eta
+    let sin_in = incoming.cross(&Vector3::up()).length();
+    let sin_out = out.cross(&Vector3::up()).length();
+    assert!((sin_out - sin_in * eta).abs() < 1e-6);
+    assert!((out.length() - 1.).abs() < 1e-6);
+    assert!(out.y < 0.);
+
+    // straight through
+    let down = Vector3::down().refract(Vector3::up(), eta).unwrap();
+    assert!(down.approx_eq(&Vector3::down(), 1e-6));
+}
+
+#[test]
+fn refract_total_internal_reflection() {
+    let mut grazing = Vector3::new(1., -0.1, 0.);
+    grazing.normalize();
+    assert!(grazing.refract(Vector3::up(), 1.5).is_none());
+}
+
+#[test]
+fn approx_eq_is_per_component() {
+    let a = Vector2::new(1., 2.);
+    assert!(a.approx_eq(&Vector2::new(1.05, 1.95), 0.1));
+    assert!(!a.approx_eq(&Vector2::new(1.2, 2.), 0.1));
+}
+
+#[test]
+fn vector_times_matrix() {
+    let m = Matrix4::translate(Vector3::new(1., 2., 3.));
+    assert_eq!(Vector4::new(1., 1., 1., 1.) * m, Vector4::new(2., 3., 4., 1.));
+    assert_eq!(Vector4::new(1., 1., 1., 0.) * m, Vector4::new(1., 1., 1., 0.));
+    // a column vector sees the transposed matrix
+    assert_eq!(m.transpose() * Vector4::new(1., 1., 1., 1.), Vector4::new(2., 3., 4., 1.));
+
+    let mut rng = rng();
+    for _ in 0..CASES {
+        let (a, b) = (random_matrix(&mut rng), random_matrix(&mut rng));
+        let v = random_vector(&mut rng, 3.).extend(1.);
+        // applying a then b is multiplying by a * b
+        assert!(((v * a) * b).approx_eq(&(v * (a * b)), 1e-3));
+        assert!((a * v).approx_eq(&(v * a.transpose()), 1e-5));
+    }
+}
+
+#[test]
+fn point_and_direction_transforms() {
+    let m = Matrix4::scale(Vector3::new(2., 2., 2.))
+        * Quaternion::from_angle_axis(std::f32::consts::FRAC_PI_2, Vector3::up()).as_mat4()
+        * Matrix4::translate(Vector3::new(10., 0., 0.));
+    assert_vector_near(m.transform_point(Vector3::new(1., 0., 0.)), Vector3::new(10., 0., -2.), 1e-5);
+    assert_vector_near(m.transform_direction(Vector3::new(1., 0., 0.)), Vector3::new(0., 0., -2.), 1e-5);
+}
+
+#[test]
+fn transform_point_divides_by_w() {
+    let projection = Matrix4::project(std::f32::consts::FRAC_PI_2, 1., 1., 100.);
+    // the near plane maps to depth 0 and the far plane to 1
+    let near = projection.transform_point(Vector3::new(0.5, -0.5, -1.));
+    assert_vector_near(near, Vector3::new(0.5, -0.5, 0.), 1e-5);
+    let far = projection.transform_point(Vector3::new(0., 0., -100.));
+    assert!((far.z - 1.).abs() < 1e-5);
+    assert_eq!(Vector4::new(2., 4., 6., 2.).project(), Vector3::new(1., 2., 3.));
+}